@@ -2,18 +2,22 @@
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
 use anyhow::Error;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::Path;
 
-static IDPS: &[IdP] = &[IdP {
-    display_name: "Microsoft",
-    url: "https://login.microsoftonline.com",
-    email_domains: &["ferrous-systems.com"],
-}];
+/// Identity providers always available, in addition to the ones configured in the document.
+static BUILTIN_IDPS: &[(&str, &str, &str, &[&str])] =
+    &[("microsoft", "Microsoft", "https://login.microsoftonline.com", &["ferrous-systems.com"])];
 
 #[derive(Debug, serde::Deserialize)]
 pub(crate) struct Config {
     pub(crate) roles: HashMap<String, Role>,
+    #[serde(default)]
+    idps: BTreeMap<String, IdP>,
+    /// Groups of roles where only some of the roles need to sign the document. Roles not part of
+    /// a quorum must always sign the document.
+    #[serde(default, rename = "quorum")]
+    quorums: Vec<Quorum>,
 }
 
 impl Config {
@@ -26,32 +30,200 @@ pub(crate) fn load(source_dir: &Path) -> Result<Self, Error> {
             }
             Err(err) => return Err(err.into()),
         };
-        Ok(toml::from_slice(&content)?)
+        let mut config: Config = toml::from_slice(&content)?;
+
+        for (id, display_name, url, email_domains) in BUILTIN_IDPS {
+            config.idps.entry(id.to_string()).or_insert_with(|| IdP {
+                display_name: display_name.to_string(),
+                url: url.to_string(),
+                email_domains: email_domains.iter().map(|d| d.to_string()).collect(),
+            });
+        }
+
+        config.validate()?;
+        Ok(config)
     }
-}
 
-#[derive(Debug, serde::Deserialize)]
-pub(crate) struct Role {
-    pub(crate) email: String,
-}
+    fn validate(&self) -> Result<(), Error> {
+        let mut domains = HashMap::new();
+        for (id, idp) in &self.idps {
+            for domain in &idp.email_domains {
+                if let Some(other) = domains.insert(domain, id) {
+                    anyhow::bail!(
+                        "email domain {domain} is used by both the {other} and {id} idps"
+                    );
+                }
+            }
+        }
 
-impl Role {
-    pub(crate) fn idp(&self) -> Result<&'static IdP, Error> {
-        let Some((_, domain)) = self.email.split_once('@') else {
-            anyhow::bail!("invalid email address: {}", self.email);
+        for (name, role) in &self.roles {
+            match (&role.email, role.identities.is_empty()) {
+                (Some(_), false) => anyhow::bail!("role {name} has both email and identities"),
+                (None, true) => anyhow::bail!("role {name} has no identities"),
+                _ => {}
+            }
+            for email in role.emails() {
+                self.idp(email)?;
+            }
+        }
+
+        let mut in_quorum = BTreeSet::new();
+        for quorum in &self.quorums {
+            for role in &quorum.roles {
+                if !self.roles.contains_key(role) {
+                    anyhow::bail!("unknown role {role} in quorum");
+                }
+                if !in_quorum.insert(role) {
+                    anyhow::bail!("role {role} is part of multiple quorums");
+                }
+            }
+            if quorum.min_signatures == 0 || quorum.min_signatures > quorum.roles.len() {
+                anyhow::bail!("quorum {quorum} can never be satisfied");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the identity provider the email must authenticate with.
+    pub(crate) fn idp(&self, email: &str) -> Result<&IdP, Error> {
+        let Some((_, domain)) = email.split_once('@') else {
+            anyhow::bail!("invalid email address: {email}");
         };
 
-        for idp in IDPS {
-            if idp.email_domains.contains(&domain) {
+        for idp in self.idps.values() {
+            if idp.email_domains.iter().any(|d| d == domain) {
                 return Ok(idp);
             }
         }
         anyhow::bail!("unsupported email domain: {domain}");
     }
+
+    /// Returns the signature requirements not satisfied by the roles that signed the document,
+    /// given along with the verified email of whoever signed for them. A quorum is only met by
+    /// signatures of distinct people, even when someone is allowed to sign for multiple roles.
+    pub(crate) fn unmet_requirements(&self, signed: &BTreeMap<&str, String>) -> Vec<String> {
+        let in_quorum =
+            self.quorums.iter().flat_map(|q| q.roles.iter()).map(|r| r.as_str()).collect();
+
+        let mut unmet = Vec::new();
+        let all_roles = self.roles.keys().map(|r| r.as_str()).collect::<BTreeSet<_>>();
+        for role in all_roles.difference(&in_quorum) {
+            if !signed.contains_key(role) {
+                unmet.push(format!("missing signature for role {role}"));
+            }
+        }
+        for quorum in &self.quorums {
+            let signers =
+                quorum.roles.iter().filter_map(|r| signed.get(r.as_str())).collect::<BTreeSet<_>>();
+            if signers.len() < quorum.min_signatures {
+                unmet.push(format!("only {} distinct signers for quorum {quorum}", signers.len()));
+            }
+        }
+        unmet
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct Role {
+    email: Option<String>,
+    /// Identities allowed to sign for this role, when more than one person can do so.
+    #[serde(default)]
+    identities: Vec<Identity>,
 }
 
+impl Role {
+    pub(crate) fn emails(&self) -> impl Iterator<Item = &str> {
+        self.email.iter().chain(self.identities.iter().map(|i| &i.email)).map(|e| e.as_str())
+    }
+
+    pub(crate) fn accepts(&self, email: &str) -> bool {
+        self.emails().any(|e| e == email)
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Identity {
+    email: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub(crate) struct IdP {
-    pub(crate) display_name: &'static str,
-    pub(crate) url: &'static str,
-    pub(crate) email_domains: &'static [&'static str],
+    pub(crate) display_name: String,
+    pub(crate) url: String,
+    email_domains: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Quorum {
+    roles: Vec<String>,
+    min_signatures: usize,
+}
+
+impl std::fmt::Display for Quorum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{} of {}\"", self.min_signatures, self.roles.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        [roles.author]
+        email = "author@ferrous-systems.com"
+
+        [roles.verification-engineer]
+        identities = [{ email = "alice@ferrous-systems.com" }, { email = "bob@ferrous-systems.com" }]
+
+        [roles.certification-engineer]
+        identities = [{ email = "bob@ferrous-systems.com" }, { email = "carol@ferrous-systems.com" }]
+
+        [[quorum]]
+        roles = ["verification-engineer", "certification-engineer"]
+        min-signatures = 2
+    "#;
+
+    fn unmet(signed: &[(&'static str, &str)]) -> Vec<String> {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let signed = signed.iter().map(|(role, email)| (*role, email.to_string())).collect();
+        config.unmet_requirements(&signed)
+    }
+
+    fn unmet_quorum(signers: usize) -> String {
+        let quorum = "\"2 of verification-engineer, certification-engineer\"";
+        format!("only {signers} distinct signers for quorum {quorum}")
+    }
+
+    #[test]
+    fn test_requirements_met() {
+        let signed = [
+            ("author", "author@ferrous-systems.com"),
+            ("verification-engineer", "alice@ferrous-systems.com"),
+            ("certification-engineer", "bob@ferrous-systems.com"),
+        ];
+        assert_eq!(Vec::<String>::new(), unmet(&signed));
+    }
+
+    #[test]
+    fn test_requirements_missing_signatures() {
+        let signed = [("verification-engineer", "alice@ferrous-systems.com")];
+        assert_eq!(
+            vec!["missing signature for role author".to_string(), unmet_quorum(1)],
+            unmet(&signed)
+        );
+    }
+
+    #[test]
+    fn test_quorum_signed_twice_by_the_same_person() {
+        let signed = [
+            ("author", "author@ferrous-systems.com"),
+            ("verification-engineer", "bob@ferrous-systems.com"),
+            ("certification-engineer", "bob@ferrous-systems.com"),
+        ];
+        assert_eq!(vec![unmet_quorum(1)], unmet(&signed));
+    }
 }
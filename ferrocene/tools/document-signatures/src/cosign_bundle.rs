@@ -1,41 +1,118 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
-use anyhow::Error;
+use crate::trust_root::TrustRoot;
+use anyhow::{Context, Error};
 use asn1_rs::oid;
 use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use sha2::{Digest, Sha256};
 use std::io::{BufReader, Cursor};
 use std::path::Path;
 use x509_parser::certificate::X509Certificate;
 use x509_parser::extensions::{GeneralName, ParsedExtension};
 use x509_parser::pem::Pem;
+use x509_parser::time::ASN1Time;
+use x509_parser::x509::SubjectPublicKeyInfo;
 
 pub(crate) struct RawCosignBundle {
     pem: Pem,
+    raw_cert: Vec<u8>,
+    signature: Vec<u8>,
+    rekor_bundle: Option<RekorBundle>,
 }
 
 impl RawCosignBundle {
     pub(crate) fn load(path: &Path) -> Result<Self, Error> {
-        let contents = std::fs::read(path)?;
-        let bundle: OnDiskBundle = serde_json::from_slice(&contents)?;
+        Self::from_json(&std::fs::read(path)?)
+    }
+
+    fn from_json(contents: &[u8]) -> Result<Self, Error> {
+        let bundle: OnDiskBundle = serde_json::from_slice(contents)?;
 
-        let raw_pem = BufReader::new(Cursor::new(
-            base64::engine::general_purpose::STANDARD.decode(&bundle.cert)?,
-        ));
-        let pem = Pem::read(raw_pem)?.0;
-        Ok(Self { pem })
+        let raw_cert = base64::engine::general_purpose::STANDARD.decode(&bundle.cert)?;
+        let pem = Pem::read(BufReader::new(Cursor::new(&raw_cert)))?.0;
+        let signature =
+            base64::engine::general_purpose::STANDARD.decode(&bundle.base64_signature)?;
+        Ok(Self { pem, raw_cert, signature, rekor_bundle: bundle.rekor_bundle })
     }
 
     pub(crate) fn parse(&self) -> Result<CosignBundle<'_>, Error> {
-        Ok(CosignBundle { certificate: self.pem.parse_x509()? })
+        Ok(CosignBundle { raw: self, certificate: self.pem.parse_x509()? })
     }
 }
 
 pub(crate) struct CosignBundle<'raw> {
+    raw: &'raw RawCosignBundle,
     certificate: X509Certificate<'raw>,
 }
 
 impl CosignBundle<'_> {
+    /// Verifies the bundle was produced by signing `artifact`, without contacting the Sigstore
+    /// infrastructure. This checks that:
+    ///
+    /// * The signature of the artifact was made with the key in the certificate.
+    /// * The signed entry timestamp was signed by the pinned Rekor key, proving the signature was
+    ///   recorded in the transparency log at the time in the timestamp.
+    /// * The entry recorded in the transparency log matches the signature and the certificate.
+    /// * The certificate was issued by the pinned Fulcio, and was valid at the time of signing.
+    ///
+    /// Note that this doesn't check the identity in the certificate.
+    ///
+    /// The inclusion proof of the entry is not checked, as the bundles produced by cosign don't
+    /// contain one. The signed entry timestamp is enough here: it's Rekor's signed promise to
+    /// include the entry, made when the entry was integrated, and it's what `cosign verify
+    /// --offline` relies on too. Checking an inclusion proof would additionally detect a log
+    /// breaking that promise, but only against a checkpoint fetched from the log or its
+    /// witnesses, which can't be done without network access anyway.
+    pub(crate) fn verify_offline(
+        &self,
+        artifact: &[u8],
+        trust_root: &TrustRoot,
+    ) -> Result<(), Error> {
+        verify_p256(self.certificate.public_key(), artifact, &self.raw.signature)
+            .context("the signature doesn't match the artifact")?;
+
+        let Some(rekor) = &self.raw.rekor_bundle else {
+            anyhow::bail!("the bundle doesn't include a signed entry timestamp");
+        };
+        let set =
+            base64::engine::general_purpose::STANDARD.decode(&rekor.signed_entry_timestamp)?;
+        verify_p256(&trust_root.rekor_key()?, &serde_json::to_vec(&rekor.payload)?, &set)
+            .context("the signed entry timestamp was not signed by the pinned rekor key")?;
+        if rekor.payload.log_id != trust_root.rekor_log_id() {
+            anyhow::bail!("the entry was recorded in an unknown log: {}", rekor.payload.log_id);
+        }
+
+        let body: RekorEntry = serde_json::from_slice(
+            &base64::engine::general_purpose::STANDARD.decode(&rekor.payload.body)?,
+        )
+        .context("failed to parse the transparency log entry")?;
+        if body.kind != "hashedrekord" {
+            anyhow::bail!("unsupported transparency log entry kind: {}", body.kind);
+        }
+        let hash = &body.spec.data.hash;
+        if hash.algorithm != "sha256" || hash.value != hex::encode(Sha256::digest(artifact)) {
+            anyhow::bail!("the transparency log entry was recorded for another artifact");
+        }
+        let decode = |data: &str| base64::engine::general_purpose::STANDARD.decode(data);
+        if decode(&body.spec.signature.content)? != self.raw.signature {
+            anyhow::bail!("the transparency log entry was recorded for another signature");
+        }
+        if decode(&body.spec.signature.public_key.content)? != self.raw.raw_cert {
+            anyhow::bail!("the transparency log entry was recorded for another certificate");
+        }
+
+        let signed_at = ASN1Time::from_timestamp(rekor.payload.integrated_time)?;
+        trust_root.verify_certificate(&self.certificate, signed_at)?;
+        match self.certificate.extended_key_usage()? {
+            Some(eku) if eku.value.code_signing => {}
+            _ => anyhow::bail!("the certificate is not valid for code signing"),
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn email(&self) -> Result<String, Error> {
         let ext = self
             .certificate
@@ -61,7 +138,221 @@ pub(crate) fn idp(&self) -> Result<String, Error> {
     }
 }
 
+fn verify_p256(
+    public_key: &SubjectPublicKeyInfo<'_>,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), Error> {
+    UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, &public_key.subject_public_key.data)
+        .verify(message, signature)
+        .map_err(|_| anyhow::anyhow!("invalid ECDSA P-256 signature"))
+}
+
 #[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub(crate) struct OnDiskBundle {
+    base64_signature: String,
     cert: String,
+    rekor_bundle: Option<RekorBundle>,
+}
+
+#[derive(serde::Deserialize)]
+struct RekorBundle {
+    #[serde(rename = "SignedEntryTimestamp")]
+    signed_entry_timestamp: String,
+    #[serde(rename = "Payload")]
+    payload: RekorPayload,
+}
+
+/// Payload of the signed entry timestamp. Rekor signs the canonical JSON serialization of it, so
+/// the fields must be kept in alphabetical order.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RekorPayload {
+    body: String,
+    integrated_time: i64,
+    #[serde(rename = "logID")]
+    log_id: String,
+    log_index: i64,
+}
+
+#[derive(serde::Deserialize)]
+struct RekorEntry {
+    kind: String,
+    spec: RekorEntrySpec,
+}
+
+#[derive(serde::Deserialize)]
+struct RekorEntrySpec {
+    data: RekorEntryData,
+    signature: RekorEntrySignature,
+}
+
+#[derive(serde::Deserialize)]
+struct RekorEntryData {
+    hash: RekorEntryHash,
+}
+
+#[derive(serde::Deserialize)]
+struct RekorEntryHash {
+    algorithm: String,
+    value: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RekorEntrySignature {
+    content: String,
+    public_key: RekorEntryPublicKey,
+}
+
+#[derive(serde::Deserialize)]
+struct RekorEntryPublicKey {
+    content: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{trust_root, AUTHOR_BUNDLE as BUNDLE, PINNED_TOML as ARTIFACT};
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    #[test]
+    fn test_verify_offline() -> Result<(), Error> {
+        let raw = RawCosignBundle::from_json(BUNDLE)?;
+        let bundle = raw.parse()?;
+        bundle.verify_offline(ARTIFACT, &trust_root())?;
+        assert_eq!("jane@ferrous-systems.com", bundle.email()?);
+        assert_eq!("https://login.microsoftonline.com", bundle.idp()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_offline_pinned_trust_root() -> Result<(), Error> {
+        let raw = RawCosignBundle::from_json(BUNDLE)?;
+        let err = raw.parse()?.verify_offline(ARTIFACT, &TrustRoot::pinned()?).unwrap_err();
+        assert_eq!(
+            "the signed entry timestamp was not signed by the pinned rekor key",
+            err.to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_offline_changed_artifact() {
+        let artifact = String::from_utf8_lossy(ARTIFACT).replace("DOC-1", "DOC-2");
+        assert_eq!(
+            "the signature doesn't match the artifact",
+            verify_err(artifact.as_bytes(), |_| {})
+        );
+    }
+
+    #[test]
+    fn test_verify_offline_changed_signed_entry_timestamp() {
+        assert_eq!(
+            "the signed entry timestamp was not signed by the pinned rekor key",
+            verify_err(ARTIFACT, |raw| raw.rekor_bundle.as_mut().unwrap().payload.log_index += 1)
+        );
+    }
+
+    #[test]
+    fn test_verify_offline_without_signed_entry_timestamp() {
+        assert_eq!(
+            "the bundle doesn't include a signed entry timestamp",
+            verify_err(ARTIFACT, |raw| raw.rekor_bundle = None)
+        );
+    }
+
+    #[test]
+    fn test_verify_offline_unknown_log() {
+        let log_id = "0".repeat(64);
+        assert_eq!(
+            format!("the entry was recorded in an unknown log: {log_id}"),
+            verify_err(ARTIFACT, |raw| resign(raw, |payload| payload.log_id = log_id.clone()))
+        );
+    }
+
+    #[test]
+    fn test_verify_offline_mismatched_entry() {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let change_entry = |pointer: &str, value: String| {
+            verify_err(ARTIFACT, |raw| {
+                resign(raw, |payload| {
+                    let mut body: serde_json::Value =
+                        serde_json::from_slice(&engine.decode(&payload.body).unwrap()).unwrap();
+                    *body.pointer_mut(pointer).unwrap() = value.into();
+                    payload.body = engine.encode(serde_json::to_vec(&body).unwrap());
+                })
+            })
+        };
+
+        assert_eq!(
+            "the transparency log entry was recorded for another artifact",
+            change_entry("/spec/data/hash/value", hex::encode(Sha256::digest(b"other")))
+        );
+        assert_eq!(
+            "the transparency log entry was recorded for another signature",
+            change_entry("/spec/signature/content", engine.encode(b"other"))
+        );
+        assert_eq!(
+            "the transparency log entry was recorded for another certificate",
+            change_entry(
+                "/spec/signature/publicKey/content",
+                engine.encode(include_bytes!("../test_data/fulcio.pem"))
+            )
+        );
+    }
+
+    #[test]
+    fn test_verify_offline_certificate_not_valid() {
+        let raw = RawCosignBundle::from_json(BUNDLE).unwrap();
+        let validity = raw.parse().unwrap().certificate.validity().clone();
+
+        for integrated_time in
+            [validity.not_before.timestamp() - 1, validity.not_after.timestamp() + 1]
+        {
+            assert_eq!(
+                "the signing certificate was not valid at the time of signing",
+                verify_err(ARTIFACT, |raw| {
+                    resign(raw, |payload| payload.integrated_time = integrated_time)
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_offline_untrusted_issuer() -> Result<(), Error> {
+        let raw = RawCosignBundle::from_json(BUNDLE)?;
+        let trust_root = TrustRoot::for_tests(
+            include_bytes!("../test_data/untrusted-fulcio.pem"),
+            include_bytes!("../test_data/rekor.pub"),
+        )?;
+        let err = raw.parse()?.verify_offline(ARTIFACT, &trust_root).unwrap_err();
+        assert_eq!("the signing certificate was not issued by the pinned fulcio", err.to_string());
+        Ok(())
+    }
+
+    /// Verifies the test bundle after changing it with `modify`, returning the error.
+    fn verify_err(artifact: &[u8], modify: impl FnOnce(&mut RawCosignBundle)) -> String {
+        let mut raw = RawCosignBundle::from_json(BUNDLE).unwrap();
+        modify(&mut raw);
+        match raw.parse().unwrap().verify_offline(artifact, &trust_root()) {
+            Ok(()) => panic!("the bundle should not be valid"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    /// Changes the payload of the signed entry timestamp, signing it again with the test Rekor key
+    /// so that only the change itself can fail the verification.
+    fn resign(raw: &mut RawCosignBundle, modify: impl FnOnce(&mut RekorPayload)) {
+        let rekor = raw.rekor_bundle.as_mut().unwrap();
+        modify(&mut rekor.payload);
+
+        let pem = Pem::read(Cursor::new(include_bytes!("../test_data/rekor.key"))).unwrap().0;
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &pem.contents).unwrap();
+        let payload = serde_json::to_vec(&rekor.payload).unwrap();
+        let set = key.sign(&SystemRandom::new(), &payload).unwrap();
+        rekor.signed_entry_timestamp = base64::engine::general_purpose::STANDARD.encode(set);
+    }
 }
@@ -6,8 +6,13 @@
 mod pinned;
 mod sign;
 mod signature_files;
+mod status;
+#[cfg(test)]
+mod test_utils;
+mod trust_root;
 mod verify;
 
+use crate::trust_root::TrustRoot;
 use anyhow::{Context, Error};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -21,19 +26,33 @@
 
 fn main() -> Result<(), Error> {
     let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    // The status mode reports on any number of documents at once.
+    if args.first().map(|mode| mode.as_str()) == Some("status") {
+        let documents = &args[1..];
+        if documents.is_empty() || documents.len() % 2 != 0 {
+            eprintln!("required arguments: status (<source-dir> <output-dir>)...");
+            std::process::exit(1);
+        }
+        let documents =
+            documents.chunks(2).map(|pair| (Path::new(&pair[0]), Path::new(&pair[1]))).collect();
+        return status::status(documents, &StatusOptions::load()?);
+    }
+
     if args.len() != 3 {
-        eprintln!("required arguments: sign|verify <source-dir> <output-dir>");
+        eprintln!("required arguments: sign|verify|verify-offline <source-dir> <output-dir>");
         std::process::exit(1);
     }
     let mode = &args[0];
     let source_dir = Path::new(&args[1]);
     let output_dir = Path::new(&args[2]);
 
-    let options = CliOptions::load()?;
-
     match &mode[..] {
-        "sign" => sign::sign(&source_dir, &output_dir, &options)?,
-        "verify" => verify::verify(&source_dir, &output_dir, &options)?,
+        "sign" => sign::sign(source_dir, output_dir, &CliOptions::load()?)?,
+        "verify" => verify::verify(source_dir, output_dir, &CliOptions::load()?)?,
+        "verify-offline" => {
+            verify::verify_offline(source_dir, output_dir, &OfflineOptions::load()?)?
+        }
         other => anyhow::bail!("unknown mode: {other}"),
     }
 
@@ -56,6 +75,36 @@ fn load() -> Result<Self, Error> {
     }
 }
 
+/// Options for verifying signatures without network access, for example in air-gapped
+/// environments where neither S3 nor the Sigstore infrastructure can be reached.
+struct OfflineOptions {
+    /// Directory containing the signature files, named after their UUID.
+    signatures_dir: PathBuf,
+    /// Sigstore trust material the signatures are checked against, see the trust_root module.
+    trust_root: TrustRoot,
+}
+
+impl OfflineOptions {
+    fn load() -> Result<Self, Error> {
+        Ok(Self { signatures_dir: env("SIGNATURES_DIR")?, trust_root: TrustRoot::pinned()? })
+    }
+}
+
+struct StatusOptions {
+    s3_cache_dir: PathBuf,
+    /// Where to write the status as JSON, in addition to the table printed to stdout.
+    json_output: Option<PathBuf>,
+}
+
+impl StatusOptions {
+    fn load() -> Result<Self, Error> {
+        Ok(Self {
+            s3_cache_dir: env("S3_CACHE_DIR")?,
+            json_output: std::env::var_os("DOCUMENT_SIGNATURES_STATUS_JSON").map(PathBuf::from),
+        })
+    }
+}
+
 fn env<T>(var: &str) -> Result<T, Error>
 where
     T: FromStr,
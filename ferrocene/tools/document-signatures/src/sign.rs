@@ -7,6 +7,7 @@
 use crate::signature_files::SignatureFiles;
 use crate::CliOptions;
 use anyhow::{Context, Error};
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::process::Command;
 use tempfile::NamedTempFile;
@@ -56,17 +57,46 @@ pub(crate) fn sign(
     let bundle = raw_bundle.parse()?;
 
     let email = bundle.email()?;
-    let Some((role_name, role)) = config.roles.iter().find(|(_, role)| role.email == email) else {
+    let mut roles =
+        config.roles.iter().filter(|(_, role)| role.accepts(&email)).collect::<Vec<_>>();
+    roles.sort_by_key(|(name, _)| *name);
+    // The same person could sign for multiple roles: prefer the ones not signed yet.
+    let Some((role_name, _)) = roles
+        .iter()
+        .find(|(name, _)| !signature_files.contains(&bundle_file_name(name)))
+        .or(roles.first())
+    else {
         anyhow::bail!("email {email} has no role in the document's signature config.toml");
     };
 
-    let role_idp = role.idp()?;
-    if role_idp.url != bundle.idp()? {
-        anyhow::bail!("you must authenticate with {}", role_idp.display_name);
+    let idp = config.idp(&email)?;
+    if idp.url != bundle.idp()? {
+        anyhow::bail!("you must authenticate with {}", idp.display_name);
     }
 
-    signature_files
-        .write(&format!("{role_name}.cosign-bundle"), &std::fs::read(bundle_temp.path())?)?;
+    signature_files.write(&bundle_file_name(role_name), &std::fs::read(bundle_temp.path())?)?;
+    eprintln!("signed the document as role {role_name}");
+
+    let mut signed = BTreeMap::new();
+    for role in config.roles.keys() {
+        if let Some(bundle) = signature_files.on_disk_as_tempfile(&bundle_file_name(role))? {
+            let email = RawCosignBundle::load(bundle.path())?.parse()?.email()?;
+            signed.insert(role.as_str(), email);
+        }
+    }
+    let unmet = config.unmet_requirements(&signed);
+    if unmet.is_empty() {
+        eprintln!("all the signatures required by the document are present");
+    } else {
+        eprintln!("signatures still required by the document:");
+        for requirement in unmet {
+            eprintln!("- {requirement}");
+        }
+    }
 
     Ok(())
 }
+
+pub(crate) fn bundle_file_name(role_name: &str) -> String {
+    format!("{role_name}.cosign-bundle")
+}
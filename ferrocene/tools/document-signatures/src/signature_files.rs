@@ -3,7 +3,8 @@
 
 //! This module implements an abstraction for reading and writing signature-related files. Behind
 //! the scenes, the files are written to an S3 bucket and recorded in the `signature.toml` file,
-//! and files are read from the `src/bootstrap`-maintained cache.
+//! and files are read from the `src/bootstrap`-maintained cache (or from a local directory with
+//! the same layout, when verifying signatures offline).
 
 use crate::{CliOptions, TOML_HEADER_COMMENTS};
 use anyhow::{bail, Context, Error};
@@ -18,11 +19,27 @@
 pub(crate) struct SignatureFiles<'opts> {
     signature_toml: Signature,
     signature_toml_path: PathBuf,
-    options: &'opts CliOptions,
+    files_dir: &'opts Path,
+    // Only present when the signature files can be uploaded.
+    s3_bucket: Option<&'opts str>,
 }
 
 impl<'opts> SignatureFiles<'opts> {
     pub(crate) fn load(document: &Path, options: &'opts CliOptions) -> Result<Self, Error> {
+        Self::load_inner(document, &options.s3_cache_dir, Some(&options.s3_bucket))
+    }
+
+    /// Loads the signature files from a local directory, without any access to S3. Files in the
+    /// directory are named after their UUID, like in the cache. Writing files is not supported.
+    pub(crate) fn load_local(document: &Path, dir: &'opts Path) -> Result<Self, Error> {
+        Self::load_inner(document, dir, None)
+    }
+
+    fn load_inner(
+        document: &Path,
+        files_dir: &'opts Path,
+        s3_bucket: Option<&'opts str>,
+    ) -> Result<Self, Error> {
         let signature_toml_path = document.join("signature").join("signature.toml");
 
         let signature_toml = if signature_toml_path.exists() {
@@ -31,7 +48,11 @@ pub(crate) fn load(document: &Path, options: &'opts CliOptions) -> Result<Self,
             Signature { files: BTreeMap::new() }
         };
 
-        Ok(Self { signature_toml, signature_toml_path, options })
+        Ok(Self { signature_toml, signature_toml_path, files_dir, s3_bucket })
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.signature_toml.files.contains_key(name)
     }
 
     pub(crate) fn read(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
@@ -41,7 +62,7 @@ pub(crate) fn read(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
         };
 
         Ok(Some(
-            std::fs::read(self.options.s3_cache_dir.join(uuid.to_string()))
+            std::fs::read(self.files_dir.join(uuid.to_string()))
                 // Assume that if a file is in `signature.toml` it must exist in S3.
                 .context("this is a bootstrap bug (file is supposed to be cached)")
                 .with_context(|| {
@@ -61,7 +82,7 @@ pub(crate) fn on_disk_as_tempfile(&self, name: &str) -> Result<Option<NamedTempF
             return Ok(None);
         };
 
-        let mut cache = File::open(self.options.s3_cache_dir.join(uuid.to_string()))
+        let mut cache = File::open(self.files_dir.join(uuid.to_string()))
             .context("this is a bootstrap bug (the file is supposed to be cached)")
             .with_context(|| {
                 format!("failed to retrieve signature file {name} (with UUID {uuid})")
@@ -75,12 +96,15 @@ pub(crate) fn on_disk_as_tempfile(&self, name: &str) -> Result<Option<NamedTempF
     }
 
     pub(crate) fn write(&mut self, name: &str, contents: &[u8]) -> Result<(), Error> {
+        let Some(s3_bucket) = self.s3_bucket else {
+            bail!("cannot write {name}, signature files are read from a local directory");
+        };
         let uuid = Uuid::new_v4();
 
         // First off, we upload the file to S3, named after the UUID.
         let mut command = Command::new("aws")
             .args(["s3", "cp", "-"])
-            .arg(format!("s3://{}/{uuid}", self.options.s3_bucket))
+            .arg(format!("s3://{s3_bucket}/{uuid}"))
             .stdin(Stdio::piped())
             .spawn()
             .with_context(|| format!("failed to invoke AWS CLI to upload {name}"))?;
@@ -98,7 +122,7 @@ pub(crate) fn write(&mut self, name: &str, contents: &[u8]) -> Result<(), Error>
 
         // Then we write the file in the local cache, to avoid having bootstrap read it from S3
         // the next time it's invoked.
-        std::fs::write(self.options.s3_cache_dir.join(uuid.to_string()), contents)?;
+        std::fs::write(self.files_dir.join(uuid.to_string()), contents)?;
 
         // And finally we update `signature.toml` to record the UUID of the file.
         self.signature_toml.files.insert(name.into(), uuid);
@@ -0,0 +1,351 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Reports which documents are signed and by which roles, without failing on the first document
+//! needing attention like `verify` does. Signatures are not cryptographically verified here.
+
+use crate::config::Config;
+use crate::cosign_bundle::RawCosignBundle;
+use crate::pinned::Pinned;
+use crate::sign::bundle_file_name;
+use crate::signature_files::SignatureFiles;
+use crate::StatusOptions;
+use anyhow::{Context, Error};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, serde::Serialize)]
+struct DocumentStatus {
+    document: String,
+    pinned: PinnedStatus,
+    roles: BTreeMap<String, RoleStatus>,
+    unmet_requirements: Vec<String>,
+    /// Problems preventing the status of the document from being fully determined.
+    errors: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum PinnedStatus {
+    /// There is no `pinned.toml`, so the document was never signed.
+    Missing,
+    UpToDate,
+    /// The document changed since it was signed, invalidating all signatures.
+    Outdated,
+    /// `pinned.toml` couldn't be checked, see the errors of the document.
+    Unknown,
+}
+
+impl std::fmt::Display for PinnedStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PinnedStatus::Missing => f.write_str("missing"),
+            PinnedStatus::UpToDate => f.write_str("up to date"),
+            PinnedStatus::Outdated => f.write_str("OUTDATED"),
+            PinnedStatus::Unknown => f.write_str("UNKNOWN"),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+enum RoleStatus {
+    Signed {
+        email: String,
+    },
+    Missing,
+    /// Signed by someone who is not one of the identities of the role.
+    WrongSigner {
+        email: String,
+    },
+    /// The signature file couldn't be read.
+    Invalid {
+        error: String,
+    },
+}
+
+impl std::fmt::Display for RoleStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoleStatus::Signed { email } => write!(f, "signed by {email}"),
+            RoleStatus::Missing => f.write_str("MISSING"),
+            RoleStatus::WrongSigner { email } => write!(f, "SIGNED BY {email} (not allowed)"),
+            RoleStatus::Invalid { error } => write!(f, "INVALID: {error}"),
+        }
+    }
+}
+
+pub(crate) fn status(documents: Vec<(&Path, &Path)>, options: &StatusOptions) -> Result<(), Error> {
+    let statuses = documents
+        .into_iter()
+        .map(|(source_dir, output_dir)| document_status(source_dir, output_dir, options))
+        .collect::<Vec<_>>();
+
+    let mut stdout = std::io::stdout().lock();
+    let width = statuses.iter().map(|s| s.document.len()).max().unwrap_or(0).max(8);
+    writeln!(stdout, "{:width$}  {:10}  Roles", "Document", "Pinned")?;
+    for status in &statuses {
+        let mut roles = status
+            .roles
+            .iter()
+            .map(|(role, role_status)| format!("{role}: {role_status}"))
+            .collect::<Vec<_>>();
+        if roles.is_empty() && status.errors.is_empty() {
+            roles.push("(no roles configured)".into());
+        }
+        for requirement in &status.unmet_requirements {
+            roles.push(format!("UNMET: {requirement}"));
+        }
+        for error in &status.errors {
+            roles.push(format!("ERROR: {error}"));
+        }
+        for (i, role) in roles.iter().enumerate() {
+            if i == 0 {
+                write!(stdout, "{:width$}  {:10}", status.document, status.pinned.to_string())?;
+            } else {
+                write!(stdout, "{:width$}  {:10}", "", "")?;
+            }
+            writeln!(stdout, "  {role}")?;
+        }
+    }
+
+    if let Some(path) = &options.json_output {
+        let mut json = serde_json::to_string_pretty(&statuses)?;
+        json.push('\n');
+        std::fs::write(path, json)?;
+    }
+
+    let failed = statuses.iter().filter(|s| !s.errors.is_empty()).count();
+    if failed > 0 {
+        anyhow::bail!("failed to fully check the status of {failed} document(s)");
+    }
+    Ok(())
+}
+
+/// Determines the status of a document. Errors are recorded in the status rather than returned,
+/// so that one broken document doesn't prevent reporting on all the others.
+fn document_status(
+    source_dir: &Path,
+    output_dir: &Path,
+    options: &StatusOptions,
+) -> DocumentStatus {
+    let document = source_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| source_dir.display().to_string());
+    let mut status = DocumentStatus {
+        document,
+        pinned: PinnedStatus::Unknown,
+        roles: BTreeMap::new(),
+        unmet_requirements: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    let signature_files = match SignatureFiles::load_local(source_dir, &options.s3_cache_dir) {
+        Ok(signature_files) => signature_files,
+        Err(err) => {
+            status.errors.push(format!("failed to load the signature files: {err:#}"));
+            return status;
+        }
+    };
+    match pinned_status(&signature_files, output_dir) {
+        Ok(pinned) => status.pinned = pinned,
+        Err(err) => status.errors.push(format!("failed to check pinned.toml: {err:#}")),
+    }
+
+    let config = match Config::load(source_dir) {
+        Ok(config) => config,
+        Err(err) => {
+            status.errors.push(format!("failed to load the signature configuration: {err:#}"));
+            return status;
+        }
+    };
+    for (role_name, role) in &config.roles {
+        let role_status = match signer(&signature_files, role_name) {
+            Ok(None) => RoleStatus::Missing,
+            Ok(Some(email)) if role.accepts(&email) => RoleStatus::Signed { email },
+            Ok(Some(email)) => RoleStatus::WrongSigner { email },
+            Err(err) => RoleStatus::Invalid { error: format!("{err:#}") },
+        };
+        status.roles.insert(role_name.clone(), role_status);
+    }
+    let signed = status
+        .roles
+        .iter()
+        .filter_map(|(role, status)| match status {
+            RoleStatus::Signed { email } => Some((role.as_str(), email.clone())),
+            _ => None,
+        })
+        .collect();
+    status.unmet_requirements = config.unmet_requirements(&signed);
+
+    status
+}
+
+fn pinned_status(
+    signature_files: &SignatureFiles,
+    output_dir: &Path,
+) -> Result<PinnedStatus, Error> {
+    let Some(contents) = signature_files.read("pinned.toml")? else {
+        return Ok(PinnedStatus::Missing);
+    };
+    let existing: Pinned = toml::from_slice(&contents)?;
+    if existing == Pinned::generate(output_dir)? {
+        Ok(PinnedStatus::UpToDate)
+    } else {
+        Ok(PinnedStatus::Outdated)
+    }
+}
+
+/// Returns the email of whoever signed for the role, if anyone did.
+fn signer(signature_files: &SignatureFiles, role_name: &str) -> Result<Option<String>, Error> {
+    let Some(bundle) = signature_files.on_disk_as_tempfile(&bundle_file_name(role_name))? else {
+        return Ok(None);
+    };
+    let raw_bundle = RawCosignBundle::load(bundle.path())
+        .with_context(|| format!("failed to load the signature of role {role_name}"))?;
+    Ok(Some(raw_bundle.parse()?.email()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TestDocument, AUTHOR_BUNDLE};
+    use serde_json::json;
+    use tempfile::NamedTempFile;
+
+    const AUTHOR_CONFIG: &str = r#"
+        [roles.author]
+        email = "jane@ferrous-systems.com"
+    "#;
+
+    #[test]
+    fn test_pinned_status() {
+        let unsigned = TestDocument::new(AUTHOR_CONFIG);
+        assert_eq!("missing", document_status_json(&unsigned)["pinned"]);
+
+        let signed = TestDocument::signed(AUTHOR_CONFIG);
+        assert_eq!("up-to-date", document_status_json(&signed)["pinned"]);
+
+        std::fs::write(signed.output.path().join("index.html"), "changed").unwrap();
+        assert_eq!("outdated", document_status_json(&signed)["pinned"]);
+    }
+
+    #[test]
+    fn test_role_statuses() {
+        let mut document = TestDocument::signed(
+            r#"
+                [roles.author]
+                email = "jane@ferrous-systems.com"
+
+                [roles.reviewer]
+                email = "bob@ferrous-systems.com"
+
+                [roles.approver]
+                email = "carol@ferrous-systems.com"
+
+                [roles.auditor]
+                email = "dave@ferrous-systems.com"
+            "#,
+        );
+        document.add_file("reviewer.cosign-bundle", AUTHOR_BUNDLE);
+        document.add_file("auditor.cosign-bundle", b"not a bundle");
+
+        let status = document_status_json(&document);
+        assert_eq!("up-to-date", status["pinned"]);
+        assert_eq!(
+            json!({"status": "signed", "email": "jane@ferrous-systems.com"}),
+            status["roles"]["author"]
+        );
+        assert_eq!(
+            json!({"status": "wrong-signer", "email": "jane@ferrous-systems.com"}),
+            status["roles"]["reviewer"]
+        );
+        assert_eq!(json!({"status": "missing"}), status["roles"]["approver"]);
+        assert_eq!("invalid", status["roles"]["auditor"]["status"]);
+        let error = status["roles"]["auditor"]["error"].as_str().unwrap();
+        assert!(error.starts_with("failed to load the signature of role auditor: "), "{error}");
+
+        assert_eq!(
+            json!([
+                "missing signature for role approver",
+                "missing signature for role auditor",
+                "missing signature for role reviewer",
+            ]),
+            status["unmet_requirements"]
+        );
+        assert_eq!(json!([]), status["errors"]);
+    }
+
+    #[test]
+    fn test_unmet_quorum() {
+        let document = TestDocument::signed(
+            r#"
+                [roles.author]
+                email = "jane@ferrous-systems.com"
+
+                [roles.reviewer]
+                email = "bob@ferrous-systems.com"
+
+                [[quorum]]
+                roles = ["author", "reviewer"]
+                min-signatures = 2
+            "#,
+        );
+
+        let status = document_status_json(&document);
+        assert_eq!(json!({"status": "missing"}), status["roles"]["reviewer"]);
+        assert_eq!(
+            json!(["only 1 distinct signers for quorum \"2 of author, reviewer\""]),
+            status["unmet_requirements"]
+        );
+    }
+
+    #[test]
+    fn test_status_with_broken_config() {
+        let signed = TestDocument::signed(AUTHOR_CONFIG);
+        let broken = TestDocument::new("roles = 42");
+        let json_output = NamedTempFile::new().unwrap();
+        let options = StatusOptions {
+            s3_cache_dir: signed.signatures.path().into(),
+            json_output: Some(json_output.path().into()),
+        };
+
+        let err = status(
+            vec![
+                (broken.source.path(), broken.output.path()),
+                (signed.source.path(), signed.output.path()),
+            ],
+            &options,
+        )
+        .unwrap_err();
+        assert_eq!("failed to fully check the status of 1 document(s)", err.to_string());
+
+        let statuses: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(json_output.path()).unwrap()).unwrap();
+        let [broken, signed] = statuses.as_array().unwrap().as_slice() else {
+            panic!("expected two documents: {statuses}");
+        };
+        assert_eq!("missing", broken["pinned"]);
+        assert_eq!(json!({}), broken["roles"]);
+        let errors = broken["errors"].as_array().unwrap();
+        assert_eq!(1, errors.len());
+        let error = errors[0].as_str().unwrap();
+        assert!(error.starts_with("failed to load the signature configuration: "), "{error}");
+
+        assert_eq!("up-to-date", signed["pinned"]);
+        assert_eq!(
+            json!({"status": "signed", "email": "jane@ferrous-systems.com"}),
+            signed["roles"]["author"]
+        );
+        assert_eq!(json!([]), signed["errors"]);
+    }
+
+    fn document_status_json(document: &TestDocument) -> serde_json::Value {
+        let options =
+            StatusOptions { s3_cache_dir: document.signatures.path().into(), json_output: None };
+        let status = document_status(document.source.path(), document.output.path(), &options);
+        serde_json::to_value(status).unwrap()
+    }
+}
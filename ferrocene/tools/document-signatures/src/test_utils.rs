@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Helpers to set up documents signed with the files in the `test_data` directory. The signature
+//! was made by jane@ferrous-systems.com, with a certificate issued by the Fulcio and recorded in
+//! the Rekor log in that directory rather than the public-good ones.
+
+use crate::trust_root::TrustRoot;
+use std::collections::BTreeMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use tempfile::TempDir;
+use uuid::Uuid;
+
+pub(crate) const PINNED_TOML: &[u8] = include_bytes!("../test_data/pinned.toml");
+pub(crate) const AUTHOR_BUNDLE: &[u8] = include_bytes!("../test_data/author.cosign-bundle");
+
+pub(crate) fn trust_root() -> TrustRoot {
+    TrustRoot::for_tests(
+        include_bytes!("../test_data/fulcio.pem"),
+        include_bytes!("../test_data/rekor.pub"),
+    )
+    .unwrap()
+}
+
+pub(crate) struct TestDocument {
+    pub(crate) source: TempDir,
+    pub(crate) output: TempDir,
+    /// Directory containing the signature files, named after their UUID.
+    pub(crate) signatures: TempDir,
+    files: BTreeMap<String, Uuid>,
+}
+
+impl TestDocument {
+    /// Creates an unsigned document with the given signature configuration, whose output matches
+    /// the one pinned in `test_data/pinned.toml`.
+    pub(crate) fn new(config: &str) -> Self {
+        let document = Self {
+            source: TempDir::new().unwrap(),
+            output: TempDir::new().unwrap(),
+            signatures: TempDir::new().unwrap(),
+            files: BTreeMap::new(),
+        };
+        std::fs::create_dir(document.source.path().join("signature")).unwrap();
+        std::fs::write(document.source.path().join("signature/config.toml"), config).unwrap();
+
+        // The permissions are part of the hashed tarball, so they can't depend on the umask.
+        let output = document.output.path();
+        std::fs::set_permissions(output, std::fs::Permissions::from_mode(0o755)).unwrap();
+        write_output(output, "document-id.txt", "FERROCENE-DOC-1\n");
+        write_output(output, "index.html", "<html>signed</html>\n");
+
+        document
+    }
+
+    /// Creates a document signed by jane@ferrous-systems.com for the author role.
+    pub(crate) fn signed(config: &str) -> Self {
+        let mut document = Self::new(config);
+        document.add_file("pinned.toml", PINNED_TOML);
+        document.add_file("author.cosign-bundle", AUTHOR_BUNDLE);
+        document
+    }
+
+    /// Stores a signature file and records it in `signature.toml`.
+    pub(crate) fn add_file(&mut self, name: &str, contents: &[u8]) {
+        let uuid = Uuid::new_v4();
+        std::fs::write(self.signatures.path().join(uuid.to_string()), contents).unwrap();
+        self.files.insert(name.into(), uuid);
+
+        let mut signature_toml = String::from("[files]\n");
+        for (name, uuid) in &self.files {
+            signature_toml.push_str(&format!("\"{name}\" = \"{uuid}\"\n"));
+        }
+        std::fs::write(self.source.path().join("signature/signature.toml"), signature_toml)
+            .unwrap();
+    }
+}
+
+fn write_output(output: &Path, name: &str, contents: &str) {
+    let path = output.join(name);
+    std::fs::write(&path, contents).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+}
@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Pinned Sigstore trust material, used to verify signatures without contacting the Sigstore
+//! infrastructure. The trust material is embedded in the binary from the `trust-root` directory,
+//! so that it can't be swapped by whoever runs the verification. It contains:
+//!
+//! * `fulcio.pem`: the Fulcio root certificate, followed by any intermediate certificate.
+//! * `rekor.pub`: the PEM-encoded public key of the Rekor transparency log.
+//!
+//! Both are copied from the trusted root distributed by the Sigstore public-good instance.
+
+use anyhow::{Context, Error};
+use sha2::{Digest, Sha256};
+use std::io::{BufReader, Cursor};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::pem::Pem;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+const MAX_CHAIN_LENGTH: usize = 8;
+
+pub(crate) struct TrustRoot {
+    fulcio: Vec<Pem>,
+    rekor: Pem,
+}
+
+impl TrustRoot {
+    pub(crate) fn pinned() -> Result<Self, Error> {
+        Self::from_pem(
+            include_bytes!("../trust-root/fulcio.pem"),
+            include_bytes!("../trust-root/rekor.pub"),
+        )
+    }
+
+    /// Loads trust material other than the pinned one, which only tests are allowed to do.
+    #[cfg(test)]
+    pub(crate) fn for_tests(fulcio: &[u8], rekor: &[u8]) -> Result<Self, Error> {
+        Self::from_pem(fulcio, rekor)
+    }
+
+    fn from_pem(fulcio: &[u8], rekor: &[u8]) -> Result<Self, Error> {
+        let fulcio = Pem::iter_from_buffer(fulcio)
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to parse fulcio.pem")?;
+        if fulcio.is_empty() {
+            anyhow::bail!("no certificates in fulcio.pem");
+        }
+        let rekor =
+            Pem::read(BufReader::new(Cursor::new(rekor))).context("failed to parse rekor.pub")?.0;
+
+        Ok(Self { fulcio, rekor })
+    }
+
+    /// Checks that the certificate was issued by the pinned Fulcio root (possibly through one of
+    /// the pinned intermediates), and that all the certificates were valid at the given time.
+    pub(crate) fn verify_certificate(
+        &self,
+        certificate: &X509Certificate<'_>,
+        at: ASN1Time,
+    ) -> Result<(), Error> {
+        let pinned = self
+            .fulcio
+            .iter()
+            .map(|pem| pem.parse_x509().context("failed to parse pinned fulcio certificate"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut current = certificate;
+        for _ in 0..MAX_CHAIN_LENGTH {
+            if !current.validity().is_valid_at(at) {
+                anyhow::bail!("{} was not valid at the time of signing", describe(current));
+            }
+
+            let Some(issuer) = pinned.iter().find(|issuer| {
+                issuer.subject() == current.issuer()
+                    && current.verify_signature(Some(issuer.public_key())).is_ok()
+            }) else {
+                anyhow::bail!("{} was not issued by the pinned fulcio", describe(current));
+            };
+            if !issuer.is_ca() {
+                anyhow::bail!("certificate {} is not a certificate authority", issuer.subject());
+            }
+
+            // Pinned self-signed certificates are roots.
+            if issuer.subject() == issuer.issuer() {
+                if !issuer.validity().is_valid_at(at) {
+                    anyhow::bail!(
+                        "root certificate {} was not valid at the time of signing",
+                        issuer.subject()
+                    );
+                }
+                return Ok(());
+            }
+            current = issuer;
+        }
+        anyhow::bail!("certificate chain is longer than {MAX_CHAIN_LENGTH} certificates");
+    }
+
+    pub(crate) fn rekor_key(&self) -> Result<SubjectPublicKeyInfo<'_>, Error> {
+        let (_, spki) = SubjectPublicKeyInfo::from_der(&self.rekor.contents)
+            .context("failed to parse the pinned rekor public key")?;
+        Ok(spki)
+    }
+
+    /// ID of the Rekor log, which is the SHA-256 hash of its DER-encoded public key.
+    pub(crate) fn rekor_log_id(&self) -> String {
+        hex::encode(Sha256::digest(&self.rekor.contents))
+    }
+}
+
+fn describe(certificate: &X509Certificate<'_>) -> String {
+    // Certificates issued by Fulcio to sign artifacts have an empty subject.
+    if certificate.subject().iter().next().is_none() {
+        "the signing certificate".into()
+    } else {
+        format!("certificate {}", certificate.subject())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned() -> Result<(), Error> {
+        let trust_root = TrustRoot::pinned()?;
+        assert_eq!(2, trust_root.fulcio.len());
+        trust_root.rekor_key()?;
+        // ID of the Rekor log of the Sigstore public-good instance.
+        assert_eq!(
+            "c0d23d6ad406973f9559f3ba2d1ca01f84147d8ffc5b8445c224f98b9591801d",
+            trust_root.rekor_log_id()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_tests_without_certificates() {
+        let err = TrustRoot::for_tests(b"", include_bytes!("../trust-root/rekor.pub"))
+            .err()
+            .expect("the trust root should be invalid");
+        assert_eq!("no certificates in fulcio.pem", err.to_string());
+    }
+}
@@ -2,10 +2,13 @@
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
 use crate::config::Config;
+use crate::cosign_bundle::RawCosignBundle;
 use crate::pinned::Pinned;
+use crate::sign::bundle_file_name;
 use crate::signature_files::SignatureFiles;
-use crate::CliOptions;
-use anyhow::{anyhow, Context, Error};
+use crate::{CliOptions, OfflineOptions};
+use anyhow::{Context, Error};
+use std::collections::BTreeMap;
 use std::io::Read;
 use std::path::Path;
 use std::process::Command;
@@ -21,20 +24,7 @@ pub(crate) fn verify(
         let mut contents = Vec::new();
         file.read_to_end(&mut contents)?;
 
-        let existing: Pinned = toml::from_slice(&contents)?;
-        let expected = Pinned::generate(output_dir)?;
-
-        if existing != expected {
-            if existing.document_id != expected.document_id {
-                eprintln!("existing document id: {}", existing.document_id);
-                eprintln!("expected document id: {}", expected.document_id);
-            }
-            if existing.tarball_sha256 != expected.tarball_sha256 {
-                eprintln!("existing tarball sha256: {}", existing.tarball_sha256);
-                eprintln!("expected tarball sha256: {}", expected.tarball_sha256);
-            }
-            anyhow::bail!("pinned documentation file outdated");
-        }
+        check_pinned(&contents, output_dir)?;
 
         file
     } else {
@@ -43,25 +33,183 @@ pub(crate) fn verify(
     };
 
     let config = Config::load(source_dir)?;
+    let mut signed = BTreeMap::new();
     for (role_name, role) in config.roles.iter() {
-        let bundle = signature_files
-            .on_disk_as_tempfile(&format!("{role_name}.cosign-bundle"))
+        // Missing signatures are reported below, as they might not be required by a quorum.
+        let Some(bundle) = signature_files
+            .on_disk_as_tempfile(&bundle_file_name(role_name))
             .with_context(|| format!("failed to read signature for role {role_name}"))?
-            .ok_or_else(|| anyhow!("missing signature file for role {role_name}"))?;
+        else {
+            continue;
+        };
 
         eprintln!("checking role {role_name}");
+        let email = RawCosignBundle::load(bundle.path())?.parse()?.email()?;
+        if !role.accepts(&email) {
+            anyhow::bail!("role {role_name} was signed by {email}, who cannot sign for it");
+        }
         let status = Command::new(&options.cosign_binary)
             .arg("verify-blob")
             .arg(pinned_toml.path())
             .arg("--bundle")
             .arg(bundle.path())
-            .args(["--certificate-identity", &role.email])
-            .args(["--certificate-oidc-issuer", role.idp()?.url])
+            .args(["--certificate-identity", &email])
+            .args(["--certificate-oidc-issuer", &config.idp(&email)?.url])
             .status()?;
         if !status.success() {
             anyhow::bail!("failed to verify signature for role {role_name} (exited with {status})");
         }
+        signed.insert(role_name.as_str(), email);
+    }
+    check_requirements(&config, &signed)
+}
+
+/// Verifies the signatures of the document without any network access, reading the signature
+/// files from a local directory and checking them against the pinned Sigstore trust root.
+pub(crate) fn verify_offline(
+    source_dir: &Path,
+    output_dir: &Path,
+    options: &OfflineOptions,
+) -> Result<(), Error> {
+    let signature_files = SignatureFiles::load_local(source_dir, &options.signatures_dir)?;
+
+    let Some(pinned_toml) = signature_files.read("pinned.toml")? else {
+        // The document was not signed.
+        return Ok(());
+    };
+    check_pinned(&pinned_toml, output_dir)?;
+
+    let config = Config::load(source_dir)?;
+    let mut signed = BTreeMap::new();
+    for (role_name, role) in config.roles.iter() {
+        // Missing signatures are reported below, as they might not be required by a quorum.
+        let Some(bundle_file) =
+            signature_files
+                .on_disk_as_tempfile(&bundle_file_name(role_name))
+                .with_context(|| format!("failed to read signature for role {role_name}"))?
+        else {
+            continue;
+        };
+
+        eprintln!("checking role {role_name}");
+        let raw_bundle = RawCosignBundle::load(bundle_file.path())?;
+        let bundle = raw_bundle.parse()?;
+        bundle
+            .verify_offline(&pinned_toml, &options.trust_root)
+            .with_context(|| format!("failed to verify signature for role {role_name}"))?;
+
+        let email = bundle.email()?;
+        if !role.accepts(&email) {
+            anyhow::bail!("role {role_name} was signed by {email}, who cannot sign for it");
+        }
+        let idp = config.idp(&email)?;
+        if bundle.idp()? != idp.url {
+            anyhow::bail!("role {role_name} was not authenticated with {}", idp.display_name);
+        }
+        signed.insert(role_name.as_str(), email);
+    }
+    check_requirements(&config, &signed)
+}
+
+fn check_requirements(config: &Config, signed: &BTreeMap<&str, String>) -> Result<(), Error> {
+    let unmet = config.unmet_requirements(signed);
+    if !unmet.is_empty() {
+        for requirement in &unmet {
+            eprintln!("error: {requirement}");
+        }
+        anyhow::bail!("the document is missing required signatures");
     }
+    Ok(())
+}
+
+fn check_pinned(contents: &[u8], output_dir: &Path) -> Result<(), Error> {
+    let existing: Pinned = toml::from_slice(contents)?;
+    let expected = Pinned::generate(output_dir)?;
 
+    if existing != expected {
+        if existing.document_id != expected.document_id {
+            eprintln!("existing document id: {}", existing.document_id);
+            eprintln!("expected document id: {}", expected.document_id);
+        }
+        if existing.tarball_sha256 != expected.tarball_sha256 {
+            eprintln!("existing tarball sha256: {}", existing.tarball_sha256);
+            eprintln!("expected tarball sha256: {}", expected.tarball_sha256);
+        }
+        anyhow::bail!("pinned documentation file outdated");
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{trust_root, TestDocument};
+    use crate::trust_root::TrustRoot;
+
+    const AUTHOR_CONFIG: &str = r#"
+        [roles.author]
+        email = "jane@ferrous-systems.com"
+    "#;
+
+    #[test]
+    fn test_verify_offline() -> Result<(), Error> {
+        verify(&TestDocument::signed(AUTHOR_CONFIG), trust_root())
+    }
+
+    #[test]
+    fn test_verify_offline_unsigned() -> Result<(), Error> {
+        verify(&TestDocument::new(AUTHOR_CONFIG), trust_root())
+    }
+
+    #[test]
+    fn test_verify_offline_outdated() {
+        let document = TestDocument::signed(AUTHOR_CONFIG);
+        std::fs::write(document.output.path().join("index.html"), "changed").unwrap();
+        let err = verify(&document, trust_root()).unwrap_err();
+        assert_eq!("pinned documentation file outdated", err.to_string());
+    }
+
+    #[test]
+    fn test_verify_offline_untrusted_signature() -> Result<(), Error> {
+        let document = TestDocument::signed(AUTHOR_CONFIG);
+        let err = verify(&document, TrustRoot::pinned()?).unwrap_err();
+        assert_eq!("failed to verify signature for role author", err.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_offline_wrong_signer() {
+        let document = TestDocument::signed(
+            r#"
+                [roles.author]
+                email = "bob@ferrous-systems.com"
+            "#,
+        );
+        let err = verify(&document, trust_root()).unwrap_err();
+        assert_eq!(
+            "role author was signed by jane@ferrous-systems.com, who cannot sign for it",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_verify_offline_missing_signature() {
+        let document = TestDocument::signed(
+            r#"
+                [roles.author]
+                email = "jane@ferrous-systems.com"
+
+                [roles.reviewer]
+                email = "bob@ferrous-systems.com"
+            "#,
+        );
+        let err = verify(&document, trust_root()).unwrap_err();
+        assert_eq!("the document is missing required signatures", err.to_string());
+    }
+
+    fn verify(document: &TestDocument, trust_root: TrustRoot) -> Result<(), Error> {
+        let options =
+            OfflineOptions { signatures_dir: document.signatures.path().into(), trust_root };
+        verify_offline(document.source.path(), document.output.path(), &options)
+    }
+}
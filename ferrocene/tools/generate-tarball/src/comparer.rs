@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use crate::compression::CompressionFormat;
+use crate::verifier::MANIFESTS_DIR;
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tar::EntryType;
+
+#[derive(Debug, clap::Args)]
+pub struct Comparer {
+    /// The first tarball to compare
+    #[clap(value_name = "PATH")]
+    first: PathBuf,
+    /// The second tarball to compare
+    #[clap(value_name = "PATH")]
+    second: PathBuf,
+}
+
+struct Entry {
+    entry_type: EntryType,
+    mode: u32,
+    uid: u64,
+    gid: u64,
+    mtime: u64,
+    link_name: Option<PathBuf>,
+    contents: Vec<u8>,
+}
+
+impl Comparer {
+    /// Compares the entries of two tarballs, failing if the tarballs are not identical.
+    pub fn run(self) -> Result<()> {
+        if hash_file(&self.first)? == hash_file(&self.second)? {
+            eprintln!("the tarballs are bit-for-bit identical");
+            return Ok(());
+        }
+
+        let first = read_entries(&self.first)?;
+        let second = read_entries(&self.second)?;
+
+        let first_by_path = first.iter().map(|(p, e)| (p.as_str(), e)).collect::<BTreeMap<_, _>>();
+        let second_by_path =
+            second.iter().map(|(p, e)| (p.as_str(), e)).collect::<BTreeMap<_, _>>();
+
+        let mut differences = Vec::new();
+        for (path, entry) in &first_by_path {
+            match second_by_path.get(path) {
+                None => differences.push(format!("{path}: only in {}", self.first.display())),
+                Some(other) => {
+                    let changes = compare_entries(path, entry, other);
+                    if !changes.is_empty() {
+                        differences.push(format!("{path}: {}", changes.join(", ")));
+                    }
+                }
+            }
+        }
+        for path in second_by_path.keys() {
+            if !first_by_path.contains_key(path) {
+                differences.push(format!("{path}: only in {}", self.second.display()));
+            }
+        }
+
+        if differences.is_empty() {
+            let paths = |entries: &[(String, Entry)]| {
+                entries.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>()
+            };
+            if paths(&first) != paths(&second) {
+                differences.push("the entries are stored in a different order".into());
+            } else {
+                differences.push("the entries are identical, but the compression differs".into());
+            }
+        }
+
+        for difference in &differences {
+            println!("{difference}");
+        }
+        bail!("the tarballs differ ({} differences)", differences.len());
+    }
+}
+
+fn compare_entries(path: &str, first: &Entry, second: &Entry) -> Vec<String> {
+    let mut changes = Vec::new();
+    let mut field = |name: &str, first: String, second: String| {
+        if first != second {
+            changes.push(format!("{name} {first} != {second}"));
+        }
+    };
+    field("type", format!("{:?}", first.entry_type), format!("{:?}", second.entry_type));
+    field("mode", format!("{:o}", first.mode), format!("{:o}", second.mode));
+    field("uid", first.uid.to_string(), second.uid.to_string());
+    field("gid", first.gid.to_string(), second.gid.to_string());
+    field("mtime", first.mtime.to_string(), second.mtime.to_string());
+    field("link", format!("{:?}", first.link_name), format!("{:?}", second.link_name));
+
+    if first.contents != second.contents {
+        if is_manifest(path) && same_signed_payload(&first.contents, &second.contents) {
+            // Signatures are not deterministic, so signed manifests always differ.
+            changes.push("only the signatures differ".into());
+        } else {
+            changes.push("contents differ".into());
+        }
+    }
+    changes
+}
+
+/// Returns the entries of the tarball in the order they are stored.
+fn read_entries(path: &Path) -> Result<Vec<(String, Entry)>> {
+    let mut archive = tar::Archive::new(CompressionFormat::decode(path)?);
+
+    let mut entries = Vec::new();
+    for entry in archive.entries().context("failed to read the tarball")? {
+        let mut entry = entry.context("failed to read the tarball")?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let header = entry.header();
+        let mut parsed = Entry {
+            entry_type: header.entry_type(),
+            mode: header.mode()?,
+            uid: header.uid()?,
+            gid: header.gid()?,
+            mtime: header.mtime()?,
+            link_name: entry.link_name()?.map(|link| link.into_owned()),
+            contents: Vec::new(),
+        };
+        entry
+            .read_to_end(&mut parsed.contents)
+            .with_context(|| format!("failed to read {path}"))?;
+        entries.push((path, parsed));
+    }
+    Ok(entries)
+}
+
+fn is_manifest(path: &str) -> bool {
+    path.starts_with(MANIFESTS_DIR) && path.ends_with(".json")
+}
+
+fn same_signed_payload(first: &[u8], second: &[u8]) -> bool {
+    let signed = |contents: &[u8]| {
+        serde_json::from_slice::<BTreeMap<String, serde_json::Value>>(contents)
+            .ok()
+            .and_then(|mut manifest| manifest.remove("signed"))
+            .and_then(|mut signed| signed.get_mut("signed").map(|payload| payload.take()))
+    };
+    match (signed(first), signed(second)) {
+        (Some(first), Some(second)) => first == second,
+        _ => false,
+    }
+}
+
+fn hash_file(path: &Path) -> Result<Vec<u8>> {
+    let mut sha256 = Sha256::new();
+    let mut file = crate::util::open_file(path)?;
+    std::io::copy(&mut file, &mut sha256)?;
+    Ok(sha256.finalize().to_vec())
+}
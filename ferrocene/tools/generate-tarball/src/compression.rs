@@ -3,9 +3,15 @@
 // SPDX-FileCopyrightText: The Rust Project Developers (see https://thanks.rust-lang.org)
 
 use anyhow::{Context, Error};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
 use rayon::prelude::*;
-use std::{convert::TryFrom, fmt, io::Write, path::Path, str::FromStr};
+use std::io::{BufReader, Read, Write};
+use std::{convert::TryFrom, fmt, path::Path, str::FromStr};
+use xz2::read::XzDecoder;
 use xz2::write::XzEncoder;
+use zstd::stream::read::Decoder as ZstdDecoder;
+use zstd::stream::write::Encoder as ZstdEncoder;
 
 #[derive(Default, Debug, Copy, Clone)]
 pub enum CompressionProfile {
@@ -40,13 +46,17 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
 #[derive(Debug, Copy, Clone)]
 pub enum CompressionFormat {
+    Gz,
     Xz,
+    Zstd,
 }
 
 impl CompressionFormat {
     pub(crate) fn extension(&self) -> &'static str {
         match self {
+            CompressionFormat::Gz => "gz",
             CompressionFormat::Xz => "xz",
+            CompressionFormat::Zstd => "zst",
         }
     }
 
@@ -65,28 +75,72 @@ pub(crate) fn encode(
         let file = crate::util::create_new_file(path)?;
 
         Ok(match self {
+            CompressionFormat::Gz => {
+                let compression = match profile {
+                    CompressionProfile::Fast => flate2::Compression::fast(),
+                    CompressionProfile::Balanced => flate2::Compression::new(6),
+                    CompressionProfile::Best => flate2::Compression::best(),
+                };
+                // The gzip format doesn't support compressing with multiple threads.
+                Box::new(GzEncoder::new(std::io::BufWriter::new(file), compression))
+            }
             CompressionFormat::Xz => {
                 let encoder = match profile {
-                    CompressionProfile::Fast => {
-                        xz2::stream::MtStreamBuilder::new().threads(6).preset(1).encoder().unwrap()
-                    }
-                    CompressionProfile::Balanced => {
-                        xz2::stream::MtStreamBuilder::new().threads(6).preset(6).encoder().unwrap()
-                    }
+                    CompressionProfile::Fast => xz_encoder(1, 1 << 20),
+                    CompressionProfile::Balanced => xz_encoder(6, 8 << 20),
                     CompressionProfile::Best => {
                         // Note that this isn't actually the best compression settings for the
                         // produced artifacts, the production artifacts on static.rust-lang.org are
                         // produced by rust-lang/promote-release which hosts recompression logic
                         // and is tuned for optimal compression.
-                        xz2::stream::MtStreamBuilder::new().threads(6).preset(9).encoder().unwrap()
+                        xz_encoder(9, 64 << 20)
                     }
                 };
 
                 let compressor = XzEncoder::new_stream(std::io::BufWriter::new(file), encoder);
                 Box::new(compressor)
             }
+            CompressionFormat::Zstd => {
+                let level = match profile {
+                    CompressionProfile::Fast => 1,
+                    CompressionProfile::Balanced => 3,
+                    CompressionProfile::Best => 19,
+                };
+                let mut encoder = ZstdEncoder::new(std::io::BufWriter::new(file), level)?;
+                // The output of multithreaded zstd doesn't depend on the number of threads, so
+                // tarballs are still reproducible.
+                encoder.multithread(6)?;
+                encoder.include_checksum(true)?;
+                Box::new(encoder)
+            }
         })
     }
+
+    /// Returns a reader decompressing the tarball, detecting the format from its extension.
+    pub(crate) fn decode(path: impl AsRef<Path>) -> Result<Box<dyn Read>, Error> {
+        let path = path.as_ref();
+        let file = BufReader::new(crate::util::open_file(path)?);
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Ok(Box::new(GzDecoder::new(file))),
+            Some("xz") => Ok(Box::new(XzDecoder::new(file))),
+            Some("zst") => Ok(Box::new(ZstdDecoder::with_buffer(file)?)),
+            _ => anyhow::bail!("unknown compression format of {}", path.display()),
+        }
+    }
+}
+
+/// Multithreaded xz compresses blocks of the input independently, so the output depends on the
+/// block size. Use the same block size as liblzma's default for the preset (three times the
+/// dictionary size), but set it explicitly along with the integrity check, so that the output
+/// doesn't depend on the defaults of the liblzma version in use.
+fn xz_encoder(preset: u32, dict_size: u64) -> xz2::stream::Stream {
+    xz2::stream::MtStreamBuilder::new()
+        .threads(6)
+        .preset(preset)
+        .block_size(3 * dict_size)
+        .check(xz2::stream::Check::Crc64)
+        .encoder()
+        .unwrap()
 }
 
 /// This struct wraps Vec<CompressionFormat> in order to parse the value from the command line.
@@ -100,7 +154,9 @@ fn try_from(value: &str) -> Result<Self, Self::Error> {
         let mut parsed = Vec::new();
         for format in value.split(',') {
             match format.trim() {
+                "gz" => parsed.push(CompressionFormat::Gz),
                 "xz" => parsed.push(CompressionFormat::Xz),
+                "zstd" => parsed.push(CompressionFormat::Zstd),
                 other => anyhow::bail!("unknown compression format: {}", other),
             }
         }
@@ -124,7 +180,9 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             }
             fmt::Display::fmt(
                 match format {
+                    CompressionFormat::Gz => "gz",
                     CompressionFormat::Xz => "xz",
+                    CompressionFormat::Zstd => "zstd",
                 },
                 f,
             )?;
@@ -149,6 +207,13 @@ pub(crate) trait Encoder: Send + Write {
     fn finish(self: Box<Self>) -> Result<(), Error>;
 }
 
+impl<W: Send + Write> Encoder for GzEncoder<W> {
+    fn finish(self: Box<Self>) -> Result<(), Error> {
+        GzEncoder::finish(*self).context("failed to finish .gz file")?;
+        Ok(())
+    }
+}
+
 impl<W: Send + Write> Encoder for XzEncoder<W> {
     fn finish(self: Box<Self>) -> Result<(), Error> {
         XzEncoder::finish(*self).context("failed to finish .xz file")?;
@@ -156,6 +221,13 @@ fn finish(self: Box<Self>) -> Result<(), Error> {
     }
 }
 
+impl<W: Send + Write> Encoder for ZstdEncoder<'static, W> {
+    fn finish(self: Box<Self>) -> Result<(), Error> {
+        ZstdEncoder::finish(*self).context("failed to finish .zst file")?;
+        Ok(())
+    }
+}
+
 pub(crate) struct CombinedEncoder {
     encoders: Vec<Box<dyn Encoder>>,
 }
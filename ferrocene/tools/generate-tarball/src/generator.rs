@@ -3,11 +3,13 @@
 // SPDX-FileCopyrightText: The Rust Project Developers (see https://thanks.rust-lang.org)
 
 use crate::compression::{CompressionFormats, CompressionProfile};
-use crate::signatures::{sign_manifest_with_aws_kms, SignatureContext};
+use crate::signatures::{
+    sign_manifest_with_aws_kms, sign_manifest_with_local_key, SignatureContext,
+};
 use crate::tarballer::Tarballer;
 use crate::util::{copy_recursive, create_dir_all, path_to_str, remove_dir_all};
 use anyhow::Result;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, clap::Args)]
 pub struct Generator {
@@ -69,6 +71,9 @@ pub struct Generator {
     /// The ARN of the AWS KMS key used to sign the criticalup manifest
     #[clap(long, value_name = "ARN")]
     ferrocene_signing_kms_key_arn: Option<String>,
+    /// Path of the PKCS#8 ECDSA P-256 private key used to sign the criticalup manifest
+    #[clap(long, value_name = "PATH", conflicts_with = "ferrocene_signing_kms_key_arn")]
+    ferrocene_signing_key_file: Option<PathBuf>,
     /// Path prefix that should only contain Ferrocene files.
     #[clap(long, value_name = "PATH")]
     ferrocene_managed_prefix: Vec<String>,
@@ -103,6 +108,7 @@ pub fn run(self) -> Result<()> {
             compression_formats,
             ferrocene_commit_sha,
             ferrocene_signing_kms_key_arn,
+            ferrocene_signing_key_file,
             ferrocene_managed_prefix,
             ferrocene_proxied_binary,
             ferrocene_component,
@@ -118,20 +124,22 @@ pub fn run(self) -> Result<()> {
         // copy over the image to the working directory
         copy_recursive(image_dir.as_ref(), &package_dir)?;
 
-        if let Some(key_arn) = ferrocene_signing_kms_key_arn {
+        if ferrocene_signing_kms_key_arn.is_some() || ferrocene_signing_key_file.is_some() {
             let Some(commit_sha) = ferrocene_commit_sha else {
                 anyhow::bail!("commit sha not provided, but signing was requested");
             };
-            sign_manifest_with_aws_kms(
-                &SignatureContext {
-                    component: &ferrocene_component,
-                    commit_sha: &commit_sha,
-                    package_dir: &package_dir,
-                    proxied_binaries: ferrocene_proxied_binary.iter().map(String::as_str).collect(),
-                    managed_prefixes: &ferrocene_managed_prefix,
-                },
-                &key_arn,
-            )?;
+            let ctx = SignatureContext {
+                component: &ferrocene_component,
+                commit_sha: &commit_sha,
+                package_dir: &package_dir,
+                proxied_binaries: ferrocene_proxied_binary.iter().map(String::as_str).collect(),
+                managed_prefixes: &ferrocene_managed_prefix,
+            };
+            if let Some(key_arn) = &ferrocene_signing_kms_key_arn {
+                sign_manifest_with_aws_kms(&ctx, key_arn)?;
+            } else if let Some(key_file) = &ferrocene_signing_key_file {
+                sign_manifest_with_local_key(&ctx, key_file)?;
+            }
         }
 
         // Make the tarballs
@@ -2,12 +2,16 @@
 // SPDX-FileCopyrightText: The Ferrocene Developers
 // SPDX-FileCopyrightText: The Rust Project Developers (see https://thanks.rust-lang.org)
 
+mod local_key;
 mod signatures;
 mod util;
 
+mod comparer;
 mod compression;
 mod generator;
+mod signing_keys;
 mod tarballer;
+mod verifier;
 
 use anyhow::Context;
 use clap::{self, Parser};
@@ -29,6 +33,11 @@ pub fn run(self) -> anyhow::Result<()> {
             Subcommand::Tarball(tarballer) => {
                 tarballer.run().context("failed to generate tarballs")
             }
+            Subcommand::GenerateSigningKeys(generator) => {
+                generator.run().context("failed to generate signing keys")
+            }
+            Subcommand::Verify(verifier) => verifier.run().context("failed to verify tarball"),
+            Subcommand::Compare(comparer) => comparer.run().context("failed to compare tarballs"),
         }
     }
 }
@@ -39,4 +48,10 @@ enum Subcommand {
     Combine,
     Script,
     Tarball(tarballer::Tarballer),
+    /// Generates keys to sign tarballs without AWS KMS
+    GenerateSigningKeys(signing_keys::SigningKeysGenerator),
+    /// Verifies the signed package manifests of a tarball
+    Verify(verifier::Verifier),
+    /// Compares the entries of two tarballs, to check whether builds are reproducible
+    Compare(comparer::Comparer),
 }
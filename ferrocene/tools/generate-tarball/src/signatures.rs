@@ -2,6 +2,8 @@
 // SPDX-FileCopyrightText: The Ferrocene Developers
 // SPDX-FileCopyrightText: The Rust Project Developers (see https://thanks.rust-lang.org)
 
+use crate::local_key::LocalKeyPair;
+use crate::util::deterministic_mode;
 use anyhow::{anyhow, Error};
 use criticaltrust::keys::{AwsKmsKeyPair, KeyPair, KeyRole};
 use criticaltrust::manifests::{ManifestVersion, Package, PackageFile, PackageManifest};
@@ -33,6 +35,14 @@ pub(crate) fn sign_manifest_with_aws_kms(
     sign_manifest(ctx, &key)
 }
 
+pub(crate) fn sign_manifest_with_local_key(
+    ctx: &SignatureContext<'_>,
+    key_file: &Path,
+) -> Result<(), Error> {
+    let key = LocalKeyPair::load(key_file, KeyRole::Packages)?;
+    sign_manifest(ctx, &key)
+}
+
 fn sign_manifest(ctx: &SignatureContext<'_>, key_pair: &dyn KeyPair) -> Result<(), Error> {
     let mut package = Package {
         product: "ferrocene".into(),
@@ -78,7 +88,7 @@ fn collect_files(
             package.files.push(PackageFile {
                 path: relative_path.into(),
                 sha256: hash_file(&entry)?,
-                posix_mode: entry.metadata()?.mode(),
+                posix_mode: deterministic_mode(entry.metadata()?.mode()),
                 needs_proxy: ctx.proxied_binaries.contains(&relative_path),
             });
         } else if entry.is_dir() {
@@ -105,6 +115,8 @@ mod tests {
     use std::os::unix::prelude::PermissionsExt;
     use tempfile::TempDir;
 
+    const ALGORITHM: KeyAlgorithm = KeyAlgorithm::EcdsaP256Sha256Asn1SpkiDer;
+
     #[test]
     fn test_sign_manifest() -> Result<(), Error> {
         let package_dir = TempDir::new()?;
@@ -152,9 +164,43 @@ fn test_sign_manifest() -> Result<(), Error> {
         Ok(())
     }
 
-    fn generate_key_and_keychain(role: KeyRole) -> Result<(EphemeralKeyPair, Keychain), Error> {
-        const ALGORITHM: KeyAlgorithm = KeyAlgorithm::EcdsaP256Sha256Asn1SpkiDer;
+    #[test]
+    fn test_sign_manifest_with_local_key() -> Result<(), Error> {
+        let package_dir = TempDir::new()?;
+        std::fs::write(package_dir.path().join("README.md"), b"hello world")?;
+
+        let root_key = EphemeralKeyPair::generate(ALGORITHM, KeyRole::Root, None)?;
+        let mut keychain = Keychain::new(root_key.public())?;
+        let key = LocalKeyPair::generate(KeyRole::Packages)?;
+        let mut signed_key = SignedPayload::new(key.public())?;
+        signed_key.add_signature(&root_key)?;
+        keychain.load(&signed_key)?;
+
+        let key_file = TempDir::new()?;
+        let key_path = key_file.path().join("packages.pem");
+        std::fs::write(&key_path, key.to_pem())?;
+
+        sign_manifest_with_local_key(
+            &SignatureContext {
+                component: "demo-package",
+                commit_sha: "000000",
+                package_dir: package_dir.path(),
+                proxied_binaries: HashSet::new(),
+                managed_prefixes: &[],
+            },
+            &key_path,
+        )?;
+
+        let deserialized: PackageManifest = serde_json::from_slice(&std::fs::read(
+            package_dir.path().join("share/criticaltrust/ferrocene/demo-package.json"),
+        )?)?;
+        let package = deserialized.signed.into_verified(&keychain)?;
+        assert_eq!(vec!["README.md"], package.files.iter().map(|f| &f.path).collect::<Vec<_>>());
 
+        Ok(())
+    }
+
+    fn generate_key_and_keychain(role: KeyRole) -> Result<(EphemeralKeyPair, Keychain), Error> {
         let root_key = EphemeralKeyPair::generate(ALGORITHM, KeyRole::Root, None)?;
         let mut keychain = Keychain::new(root_key.public())?;
 
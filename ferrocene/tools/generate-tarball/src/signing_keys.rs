@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use crate::local_key::{LocalKeyPair, ALGORITHM};
+use crate::util::create_dir_all;
+use anyhow::{Context, Result};
+use criticaltrust::keys::{EphemeralKeyPair, KeyPair, KeyRole};
+use criticaltrust::signatures::SignedPayload;
+use std::path::PathBuf;
+
+const ROOT_KEY_FILE: &str = "root.json";
+const PACKAGES_KEY_FILE: &str = "packages.json";
+const PACKAGES_PRIVATE_KEY_FILE: &str = "packages.pem";
+
+#[derive(Debug, clap::Args)]
+pub struct SigningKeysGenerator {
+    /// The directory to write the keys into
+    #[clap(value_name = "DIR")]
+    #[clap(long)]
+    output_dir: PathBuf,
+}
+
+impl SigningKeysGenerator {
+    /// Generates a new trust root and a packages key signed by it, to sign tarballs locally.
+    ///
+    /// The private key of the root is discarded once the packages key is signed, so a new trust
+    /// root has to be generated to replace the packages key.
+    pub fn run(self) -> Result<()> {
+        let root = EphemeralKeyPair::generate(ALGORITHM, KeyRole::Root, None)?;
+        let packages = LocalKeyPair::generate(KeyRole::Packages)?;
+
+        let mut signed_packages = SignedPayload::new(packages.public())?;
+        signed_packages.add_signature(&root)?;
+
+        create_dir_all(&self.output_dir)?;
+        let write = |name: &str, contents: String| {
+            let path = self.output_dir.join(name);
+            std::fs::write(&path, contents)
+                .with_context(|| format!("failed to write {}", path.display()))
+        };
+        write(ROOT_KEY_FILE, serde_json::to_string_pretty(root.public())? + "\n")?;
+        write(PACKAGES_KEY_FILE, serde_json::to_string_pretty(&signed_packages)? + "\n")?;
+        write(PACKAGES_PRIVATE_KEY_FILE, packages.to_pem())?;
+
+        let display = |name: &str| self.output_dir.join(name).display().to_string();
+        eprintln!("trust root: {}", display(ROOT_KEY_FILE));
+        eprintln!("signed packages key: {}", display(PACKAGES_KEY_FILE));
+        eprintln!("packages private key: {}", display(PACKAGES_PRIVATE_KEY_FILE));
+        Ok(())
+    }
+}
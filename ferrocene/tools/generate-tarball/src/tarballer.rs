@@ -6,7 +6,7 @@
 use std::fs::{read_link, symlink_metadata};
 use std::io::{BufWriter, Write};
 use std::path::Path;
-use tar::{Builder, Header};
+use tar::{Builder, Header, HeaderMode};
 use walkdir::WalkDir;
 
 use crate::{
@@ -14,6 +14,10 @@
     util::{open_file, path_to_str},
 };
 
+/// Modification time used when `SOURCE_DATE_EPOCH` is not set (2020-01-01T00:00:00Z), rather than
+/// the time the files were built, to keep tarballs reproducible.
+const DEFAULT_MTIME: u64 = 1577836800;
+
 #[derive(Debug)]
 #[derive(clap::Args)]
 pub struct Tarballer {
@@ -51,8 +55,12 @@ pub fn run(self) -> Result<()> {
                 .collect::<Result<Vec<_>>>()?,
         );
 
-        let (dirs, mut files) =
+        let mtime = source_date_epoch()?;
+        let (mut dirs, mut files) =
             get_recursive_paths(&work_dir, &input).context("failed to collect file paths")?;
+        // The order in which directories are walked depends on the filesystem, so sort them to
+        // make the tarball reproducible. Parents are always sorted before their children.
+        dirs.sort();
         // Sort files by their suffix, to group files with the same name from
         // different locations (likely identical) and files with the same
         // extension (likely containing similar data).
@@ -68,13 +76,12 @@ pub fn run(self) -> Result<()> {
         pool.install(move || {
             for path in dirs {
                 let src = base.join(&path);
-                builder
-                    .append_dir(&path, &src)
+                append_path(&mut builder, &src, &path, mtime)
                     .with_context(|| format!("failed to tar dir '{}'", src.display()))?;
             }
             for path in files {
                 let src = base.join(&path);
-                append_path(&mut builder, &src, &path)
+                append_path(&mut builder, &src, &path, mtime)
                     .with_context(|| format!("failed to tar file '{}'", src.display()))?;
             }
             builder
@@ -90,13 +97,32 @@ pub fn run(self) -> Result<()> {
     }
 }
 
-fn append_path<W: Write>(builder: &mut Builder<W>, src: &Path, path: &String) -> Result<()> {
+/// Modification time of all the entries in the tarball, as seconds since the UNIX epoch.
+fn source_date_epoch() -> Result<u64> {
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch.parse().with_context(|| format!("invalid SOURCE_DATE_EPOCH: {epoch}")),
+        Err(std::env::VarError::NotPresent) => Ok(DEFAULT_MTIME),
+        Err(err) => Err(err).context("invalid SOURCE_DATE_EPOCH"),
+    }
+}
+
+fn append_path<W: Write>(
+    builder: &mut Builder<W>,
+    src: &Path,
+    path: &String,
+    mtime: u64,
+) -> Result<()> {
     let stat = symlink_metadata(src)?;
     let mut header = Header::new_gnu();
-    header.set_metadata(&stat);
+    // The deterministic mode uses 0 as the numeric owner and group, and only preserves whether
+    // files are executable, so that the tarball doesn't depend on who built it or their umask.
+    header.set_metadata_in_mode(&stat, HeaderMode::Deterministic);
+    header.set_mtime(mtime);
     if stat.file_type().is_symlink() {
         let link = read_link(src)?;
         builder.append_link(&mut header, path, &link)?;
+    } else if stat.is_dir() {
+        builder.append_data(&mut header, path, std::io::empty())?;
     } else {
         if cfg!(windows) {
             // Windows doesn't really have a mode, so `tar` never marks files executable.
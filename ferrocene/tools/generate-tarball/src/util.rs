@@ -17,6 +17,13 @@ pub fn path_to_str(path: &Path) -> Result<&str> {
     path.to_str().ok_or_else(|| format_err!("path is not valid UTF-8 '{}'", path.display()))
 }
 
+/// Normalizes the permissions in a file mode the same way they are stored in tarballs, only
+/// preserving whether the file is executable.
+pub fn deterministic_mode(mode: u32) -> u32 {
+    let permissions = if mode & 0o100 == 0o100 { 0o755 } else { 0o644 };
+    (mode & !0o7777) | permissions
+}
+
 /// Wraps `fs::copy` with a nicer error message.
 fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<u64> {
     if fs::symlink_metadata(&from)?.file_type().is_symlink() {
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use crate::compression::CompressionFormat;
+use anyhow::{bail, Context, Result};
+use criticaltrust::keys::PublicKey;
+use criticaltrust::manifests::PackageManifest;
+use criticaltrust::signatures::{Keychain, SignedPayload};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub(crate) const MANIFESTS_DIR: &str = "share/criticaltrust/ferrocene/";
+
+#[derive(Debug, clap::Args)]
+pub struct Verifier {
+    /// The tarball to verify
+    #[clap(value_name = "PATH")]
+    tarball: PathBuf,
+    /// The public key of the trust root, as a criticaltrust public key
+    #[clap(value_name = "PATH")]
+    #[clap(long)]
+    root_key: PathBuf,
+    /// A key signed by the trust root, as a criticaltrust signed public key
+    #[clap(value_name = "PATH")]
+    #[clap(long = "signed-key")]
+    signed_keys: Vec<PathBuf>,
+}
+
+struct TarballFile {
+    sha256: Vec<u8>,
+    mode: u32,
+}
+
+impl Verifier {
+    /// Verifies the signatures of the package manifests in the tarball, and that the contents
+    /// of the tarball match the manifests.
+    pub fn run(self) -> Result<()> {
+        let root: PublicKey = read_json(&self.root_key)?;
+        let mut keychain = Keychain::new(&root).context("invalid trust root")?;
+        for path in &self.signed_keys {
+            let signed: SignedPayload<PublicKey> = read_json(path)?;
+            keychain
+                .load(&signed)
+                .with_context(|| format!("{} is not signed by the trust root", path.display()))?;
+        }
+
+        let (files, manifests) = read_tarball(&self.tarball)?;
+        if manifests.is_empty() {
+            bail!("no package manifests found in {MANIFESTS_DIR}");
+        }
+
+        let mut unlisted = files.keys().map(String::as_str).collect::<BTreeSet<_>>();
+        for manifest_path in manifests.keys() {
+            unlisted.remove(manifest_path.as_str());
+        }
+        for (manifest_path, contents) in &manifests {
+            let manifest: PackageManifest = serde_json::from_slice(contents)
+                .with_context(|| format!("failed to parse {manifest_path}"))?;
+            let package = manifest
+                .signed
+                .into_verified(&keychain)
+                .with_context(|| format!("invalid signature for {manifest_path}"))?;
+
+            for expected in &package.files {
+                let Some(actual) = files.get(&expected.path) else {
+                    bail!("{} is listed in {manifest_path} but missing", expected.path);
+                };
+                if actual.sha256 != expected.sha256 {
+                    bail!("{} doesn't match the hash in {manifest_path}", expected.path);
+                }
+                if actual.mode & 0o7777 != expected.posix_mode & 0o7777 {
+                    bail!("{} doesn't match the permissions in {manifest_path}", expected.path);
+                }
+                unlisted.remove(expected.path.as_str());
+            }
+
+            eprintln!(
+                "verified {} files of {} (commit {})",
+                package.files.len(),
+                package.package,
+                package.commit
+            );
+        }
+
+        if let Some(path) = unlisted.first() {
+            bail!("{path} is not listed in any package manifest");
+        }
+        Ok(())
+    }
+}
+
+/// Returns the hashes of all regular files in the tarball, and the contents of the manifests.
+fn read_tarball(path: &Path) -> Result<(BTreeMap<String, TarballFile>, BTreeMap<String, Vec<u8>>)> {
+    let mut archive = tar::Archive::new(CompressionFormat::decode(path)?);
+
+    let mut files = BTreeMap::new();
+    let mut manifests = BTreeMap::new();
+    for entry in archive.entries().context("failed to read the tarball")? {
+        let mut entry = entry.context("failed to read the tarball")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mode = entry.header().mode()?;
+
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents).with_context(|| format!("failed to read {path}"))?;
+
+        let sha256 = Sha256::digest(&contents).to_vec();
+        if path.starts_with(MANIFESTS_DIR) && path.ends_with(".json") {
+            manifests.insert(path.clone(), contents);
+        }
+        files.insert(path, TarballFile { sha256, mode });
+    }
+    Ok((files, manifests))
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_slice(&contents).with_context(|| format!("failed to parse {}", path.display()))
+}
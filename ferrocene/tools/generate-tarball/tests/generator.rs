@@ -53,3 +53,53 @@ fn works() -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+#[test]
+fn signs_with_local_key() -> Result<(), anyhow::Error> {
+    let temp_dir = tempfile::tempdir()?;
+    let temp_dir = temp_dir.path().to_str().unwrap();
+
+    let test_data = format!("{}/test_data", env!("CARGO_MANIFEST_DIR"));
+    let data_dir = format!("{test_data}/foo-package/x86_64-unknown-linux-gnu",);
+
+    let run = |args: &[&str]| {
+        let args = std::iter::once("this").chain(args.iter().copied()).map(OsStr::new);
+        generate_tarball::CommandLine::parse_from(args).run()
+    };
+
+    run(&["generate-signing-keys", "--output-dir", &format!("{temp_dir}/keys")])?;
+    run(&["generate-signing-keys", "--output-dir", &format!("{temp_dir}/other-keys")])?;
+    run(&[
+        "generate",
+        "--image-dir",
+        &format!("{data_dir}/image"),
+        "--package-name=foo",
+        "--output-dir",
+        &format!("{temp_dir}/dist"),
+        "--work-dir",
+        &format!("{temp_dir}/work"),
+        "--compression-profile",
+        "fast",
+        "--ferrocene-component",
+        "foo",
+        "--ferrocene-commit-sha",
+        "0000000000000000000000000000000000000000",
+        "--ferrocene-signing-key-file",
+        &format!("{temp_dir}/keys/packages.pem"),
+    ])?;
+
+    let verify = |keys: &str| {
+        run(&[
+            "verify",
+            &format!("{temp_dir}/dist/foo.tar.xz"),
+            "--root-key",
+            &format!("{temp_dir}/{keys}/root.json"),
+            "--signed-key",
+            &format!("{temp_dir}/{keys}/packages.json"),
+        ])
+    };
+    verify("keys")?;
+    assert!(verify("other-keys").is_err());
+
+    Ok(())
+}
@@ -2,8 +2,11 @@
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
 use std::ffi::OsStr;
-use std::fs::{copy, create_dir, create_dir_all};
+use std::fs::{copy, create_dir, create_dir_all, Permissions};
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
+use std::time::{Duration, SystemTime};
 
 use clap::Parser;
 use walkdir::WalkDir;
@@ -37,6 +40,121 @@ fn works() -> Result<(), anyhow::Error> {
     generate_tarball::CommandLine::parse_from(args.into_iter().map(OsStr::new)).run()
 }
 
+#[test]
+fn reproducible() -> Result<(), anyhow::Error> {
+    let temp_dir = tempfile::tempdir()?;
+    let temp_dir = temp_dir.path().to_str().unwrap();
+
+    let test_data = format!("{}/test_data", env!("CARGO_MANIFEST_DIR"));
+    let data_dir = format!("{test_data}/foo-package/x86_64-unknown-linux-gnu",);
+
+    let run = |args: &[&str]| {
+        let args = std::iter::once("this").chain(args.iter().copied()).map(OsStr::new);
+        generate_tarball::CommandLine::parse_from(args).run()
+    };
+    let prepare = |name: &str| {
+        create_dir_all(&format!("{temp_dir}/{name}/image"))?;
+        copy_recursive(
+            format!("{data_dir}/image").as_ref(),
+            format!("{temp_dir}/{name}/image").as_ref(),
+        )
+    };
+    let tarball = |name: &str| {
+        run(&[
+            "tarball",
+            "--input",
+            "image",
+            "--output",
+            &format!("{temp_dir}/{name}"),
+            "--work-dir",
+            &format!("{temp_dir}/{name}"),
+            "--compression-profile",
+            "fast",
+        ])
+    };
+    let compare = |first: &str, second: &str| {
+        run(&[
+            "compare",
+            &format!("{temp_dir}/{first}.tar.xz"),
+            &format!("{temp_dir}/{second}.tar.xz"),
+        ])
+    };
+
+    prepare("first")?;
+    tarball("first")?;
+
+    // Files with a different modification time and umask must produce the same tarball.
+    prepare("second")?;
+    let foo = std::fs::File::options().write(true).open(format!("{temp_dir}/second/image/foo"))?;
+    foo.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000))?;
+    foo.set_permissions(Permissions::from_mode(0o664))?;
+    tarball("second")?;
+
+    assert_eq!(
+        std::fs::read(format!("{temp_dir}/first.tar.xz"))?,
+        std::fs::read(format!("{temp_dir}/second.tar.xz"))?
+    );
+    compare("first", "second")?;
+
+    // Changes in the contents must be detected.
+    prepare("third")?;
+    std::fs::write(format!("{temp_dir}/third/image/foo"), "changed")?;
+    tarball("third")?;
+    assert!(compare("first", "third").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn compression_formats() -> Result<(), anyhow::Error> {
+    let temp_dir = tempfile::tempdir()?;
+    let temp_dir = temp_dir.path().to_str().unwrap();
+
+    let test_data = format!("{}/test_data", env!("CARGO_MANIFEST_DIR"));
+    let data_dir = format!("{test_data}/foo-package/x86_64-unknown-linux-gnu",);
+
+    create_dir_all(&format!("{temp_dir}/work/image"))?;
+    copy_recursive(
+        format!("{data_dir}/image").as_ref(),
+        format!("{temp_dir}/work/image").as_ref(),
+    )?;
+
+    for profile in ["fast", "balanced", "best"] {
+        let args = [
+            "this",
+            "tarball",
+            "--input",
+            "image",
+            "--output",
+            &format!("{temp_dir}/dist"),
+            "--work-dir",
+            &format!("{temp_dir}/work"),
+            "--compression-profile",
+            profile,
+            "--compression-formats",
+            "gz,xz,zstd",
+        ];
+        generate_tarball::CommandLine::parse_from(args.into_iter().map(OsStr::new)).run()?;
+
+        let open = |ext: &str| std::fs::File::open(format!("{temp_dir}/dist.tar.{ext}"));
+        let readers: [Box<dyn Read>; 3] = [
+            Box::new(flate2::read::GzDecoder::new(open("gz")?)),
+            Box::new(xz2::read::XzDecoder::new(open("xz")?)),
+            Box::new(zstd::stream::read::Decoder::new(open("zst")?)?),
+        ];
+        for reader in readers {
+            let mut res = tar::Archive::new(reader)
+                .entries()?
+                .map(|it| anyhow::Ok(it?.path()?.to_string_lossy().to_string()))
+                .collect::<Result<Vec<_>, _>>()?;
+            res.sort();
+            assert_eq!(res, vec!["bar", "bar/bar", "foo"]);
+        }
+    }
+
+    Ok(())
+}
+
 fn copy_recursive(src: &Path, dst: &Path) -> anyhow::Result<()> {
     for entry in WalkDir::new(src).min_depth(1) {
         let entry = entry?;
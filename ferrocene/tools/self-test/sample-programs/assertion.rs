@@ -11,10 +11,75 @@ pub fn main() {
     assert_eq!(2, add(sub(2, 1), 1));
     #[cfg(not(selftest_no_std))]
     print!("123456789");
+    #[cfg(selftest_semihosting)]
+    semihosting::write0(b"123456789\0");
 }
 
-#[cfg(selftest_no_std)]
+#[cfg(all(selftest_no_std, not(selftest_semihosting)))]
 #[panic_handler]
 fn panic_handler(_: &core::panic::PanicInfo) -> ! {
     loop {}
 }
+
+#[cfg(selftest_semihosting)]
+#[panic_handler]
+fn panic_handler(_: &core::panic::PanicInfo) -> ! {
+    semihosting::exit(1)
+}
+
+// Minimal runtime to execute the program on an emulator (like qemu-system-aarch64) with
+// semihosting enabled: the emulator loads the program and jumps to `_start`, which sets up the
+// stack, runs `main` and reports the exit status to the emulator.
+#[cfg(all(selftest_semihosting, target_arch = "aarch64"))]
+mod semihosting {
+    const SYS_WRITE0: u64 = 0x04;
+    const SYS_EXIT: u64 = 0x18;
+    const ADP_STOPPED_APPLICATION_EXIT: u64 = 0x20026;
+
+    core::arch::global_asm!(
+        ".section .text._start, \"ax\"",
+        ".global _start",
+        "_start:",
+        // Allow access to the FP/SIMD registers, as the compiled code might use them.
+        "    mov x0, #(3 << 20)",
+        "    msr cpacr_el1, x0",
+        "    isb",
+        "    ldr x0, =selftest_stack_top",
+        "    mov sp, x0",
+        "    bl selftest_semihosting_start",
+        "1:  b 1b",
+        ".section .bss.selftest_stack, \"aw\", %nobits",
+        ".balign 16",
+        "    .space 0x4000",
+        "selftest_stack_top:",
+    );
+
+    #[no_mangle]
+    extern "C" fn selftest_semihosting_start() -> ! {
+        super::main();
+        exit(0)
+    }
+
+    /// Writes a NUL-terminated string to the standard output of the emulator.
+    pub(crate) fn write0(message: &[u8]) {
+        assert_eq!(Some(&0), message.last());
+        unsafe { call(SYS_WRITE0, message.as_ptr() as u64) };
+    }
+
+    pub(crate) fn exit(code: u64) -> ! {
+        let block = [ADP_STOPPED_APPLICATION_EXIT, code];
+        unsafe { call(SYS_EXIT, block.as_ptr() as u64) };
+        loop {}
+    }
+
+    unsafe fn call(operation: u64, parameter: u64) -> u64 {
+        let result;
+        core::arch::asm!(
+            "hlt #0xf000",
+            inout("x0") operation => result,
+            in("x1") parameter,
+            options(nostack),
+        );
+        result
+    }
+}
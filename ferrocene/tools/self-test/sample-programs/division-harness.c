@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+#include <stdio.h>
+
+// Defined in division.rs
+int selftest_div(int left, int right);
+
+int main(void) {
+    printf("%d", selftest_div(100, 4));
+    return 0;
+}
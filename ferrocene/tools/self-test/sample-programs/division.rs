@@ -0,0 +1,9 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+// Compiled as a `cdylib`, and loaded by division-harness.c.
+
+#[no_mangle]
+pub extern "C" fn selftest_div(left: i32, right: i32) -> i32 {
+    left / right
+}
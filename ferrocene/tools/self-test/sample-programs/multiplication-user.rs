@@ -0,0 +1,8 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use multiplication::mul;
+
+fn main() {
+    print!("{}", mul(6, 7));
+}
@@ -0,0 +1,8 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+// Compiled as a `dylib`, linked dynamically to the standard library.
+
+pub fn mul(left: i32, right: i32) -> i32 {
+    left * right
+}
@@ -1,21 +1,53 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
+use crate::diagnostics::Diagnostics;
 use crate::error::Error;
 use crate::report::Reporter;
+use crate::snapshot::{BinarySnapshot, NullReporter, SnapshotError};
 use crate::utils::run_command;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::process::Command;
 
-pub(crate) fn check(reporter: &dyn Reporter, sysroot: &Path) -> Result<(), Error> {
-    check_binary(reporter, sysroot, "rustc", CommitHashOf::Rust)?;
-    check_binary(reporter, sysroot, "rustdoc", CommitHashOf::Rust)?;
-    check_optional_binary(reporter, sysroot, "cargo", CommitHashOf::Cargo)?;
+/// All the binaries are checked as part of the same group.
+const GROUP: &str = "binaries";
+
+pub(crate) fn check(
+    reporter: &dyn Reporter,
+    diagnostics: &Diagnostics<'_>,
+    sysroot: &Path,
+) -> Result<(), Error> {
+    diagnostics.check(GROUP, || check_binary(reporter, sysroot, "rustc", CommitHashOf::Rust))?;
+    diagnostics.check(GROUP, || check_binary(reporter, sysroot, "rustdoc", CommitHashOf::Rust))?;
+    diagnostics
+        .check(GROUP, || check_optional_binary(reporter, sysroot, "cargo", CommitHashOf::Cargo))?;
 
     Ok(())
 }
 
+/// Gathers what the binary checks look at, for the environment snapshot.
+pub(crate) fn snapshot(sysroot: &Path) -> Vec<BinarySnapshot> {
+    [("rustc", CommitHashOf::Rust), ("rustdoc", CommitHashOf::Rust), ("cargo", CommitHashOf::Cargo)]
+        .into_iter()
+        .map(|(name, hash)| {
+            let path = sysroot.join("bin").join(name);
+            let mode = std::fs::metadata(&path)
+                .ok()
+                .map(|metadata| format!("{:o}", metadata.permissions().mode()));
+            let version = run_command(Command::new(&path).arg("-vV")).ok().map(|out| out.stdout);
+            let error = check_binary(&NullReporter, sysroot, name, hash).err();
+            BinarySnapshot {
+                name,
+                path,
+                mode,
+                version,
+                error: error.as_ref().map(SnapshotError::new),
+            }
+        })
+        .collect()
+}
+
 fn check_binary(
     reporter: &dyn Reporter,
     sysroot: &Path,
@@ -0,0 +1,206 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use crate::error::Error;
+use crate::report::Reporter;
+use crate::targets::Target;
+use crate::utils::run_command;
+use std::path::Path;
+use std::process::Command;
+
+/// Files of the sample workspace built with cargo. It only contains path dependencies, so that
+/// it can be built without access to a registry.
+const SAMPLE_WORKSPACE: &[(&str, &str)] = &[
+    ("Cargo.toml", "[workspace]\nmembers = [\"app\", \"greeting\"]\nresolver = \"2\"\n"),
+    (
+        "app/Cargo.toml",
+        "[package]\nname = \"app\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+         [dependencies]\ngreeting = { path = \"../greeting\" }\n",
+    ),
+    ("app/src/main.rs", "fn main() {\n    print!(\"{}\", greeting::greet());\n}\n"),
+    (
+        "greeting/Cargo.toml",
+        "[package]\nname = \"greeting\"\nversion = \"0.0.0\"\nedition = \"2021\"\n",
+    ),
+    ("greeting/src/lib.rs", "pub fn greet() -> &'static str {\n    \"hello from cargo\"\n}\n"),
+];
+const SAMPLE_WORKSPACE_OUTPUT: &[u8] = b"hello from cargo";
+
+/// Environment variables that could make cargo use something other than the toolchain being
+/// checked, and are thus removed when invoking it.
+const REMOVED_ENV_VARS: &[&str] = &[
+    "RUSTFLAGS",
+    "RUSTDOCFLAGS",
+    "RUSTC_WRAPPER",
+    "RUSTC_WORKSPACE_WRAPPER",
+    "CARGO_BUILD_RUSTFLAGS",
+    "CARGO_BUILD_TARGET",
+];
+
+/// Checks that cargo can build a sample workspace offline, using the rustc of the sysroot.
+pub(crate) fn check(
+    reporter: &dyn Reporter,
+    sysroot: &Path,
+    host: Option<&Target>,
+) -> Result<(), Error> {
+    let cargo = sysroot.join("bin").join("cargo");
+    if !cargo.is_file() {
+        reporter.skipped("building a sample workspace with cargo (cargo not present)");
+        return Ok(());
+    }
+    let Some(host) = host else {
+        reporter.skipped("building a sample workspace with cargo (host target not installed)");
+        return Ok(());
+    };
+
+    let temp = tempfile::Builder::new()
+        .prefix("fst-")
+        .tempdir()
+        .map_err(|error| Error::TemporaryCompilationDirectoryCreationFailed { error })?;
+    let workspace = temp.path().join("workspace");
+    let target_dir = temp.path().join("target");
+
+    for (name, contents) in SAMPLE_WORKSPACE {
+        let dest = workspace.join(name);
+        std::fs::create_dir_all(dest.parent().unwrap())
+            .and_then(|_| std::fs::write(&dest, contents))
+            .map_err(|error| Error::WritingSampleProgramFailed {
+                name: (*name).into(),
+                dest: dest.clone(),
+                error,
+            })?;
+    }
+
+    let mut cmd = Command::new(&cargo);
+    cmd.args(["build", "--offline", "--target", &host.triple]);
+    cmd.current_dir(&workspace);
+    // Use an empty cargo home, to ignore the configuration of the current user.
+    cmd.env("CARGO_HOME", temp.path().join("cargo-home"));
+    cmd.env("CARGO_TARGET_DIR", &target_dir);
+    cmd.env("RUSTC", sysroot.join("bin").join("rustc"));
+    cmd.env("RUSTDOC", sysroot.join("bin").join("rustdoc"));
+    cmd.env("CARGO_ENCODED_RUSTFLAGS", host.rustflags.join("\x1f"));
+    for var in REMOVED_ENV_VARS {
+        cmd.env_remove(var);
+    }
+    run_command(&mut cmd).map_err(|error| Error::CargoBuildFailed { error })?;
+
+    let bin = target_dir.join(&host.triple).join("debug").join("app");
+    if !bin.is_file() {
+        return Err(Error::CargoArtifactMissing { path: bin });
+    }
+
+    let output = Command::new(&bin).output().map_err(|error| {
+        Error::RunningSampleProgramFailed { name: "app (built with cargo)".into(), error }
+    })?;
+    if output.stdout != SAMPLE_WORKSPACE_OUTPUT {
+        return Err(Error::SampleProgramOutputWrong {
+            name: "app (built with cargo)".into(),
+            expected: SAMPLE_WORKSPACE_OUTPUT.to_vec(),
+            found: output.stdout,
+        });
+    }
+
+    reporter.success("built and ran a sample workspace with cargo");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linkers::Linker;
+    use crate::targets::TargetSpec;
+    use crate::test_utils::TestUtils;
+
+    // Fake cargo "building" the workspace by writing a shell script in place of the binary.
+    const CARGO_SOURCE: &str = r##"
+        const OUTPUT: &str = "hello from cargo";
+
+        fn main() {
+            use std::os::unix::fs::PermissionsExt;
+
+            let args = std::env::args().skip(1).collect::<Vec<_>>();
+            assert_eq!(["build", "--offline", "--target", "x86_64-unknown-linux-gnu"], &args[..]);
+            assert!(std::env::var("RUSTC").unwrap().ends_with("/bin/rustc"));
+            assert_eq!("-Clinker=cc", std::env::var("CARGO_ENCODED_RUSTFLAGS").unwrap());
+            assert!(std::path::Path::new("greeting/src/lib.rs").is_file());
+
+            let dir = format!("{}/x86_64-unknown-linux-gnu/debug", std::env::var("CARGO_TARGET_DIR").unwrap());
+            std::fs::create_dir_all(&dir).unwrap();
+            let bin = format!("{dir}/app");
+            std::fs::write(&bin, format!("#!/bin/sh\nprintf '{OUTPUT}'\n")).unwrap();
+            std::fs::set_permissions(&bin, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    "##;
+
+    fn host() -> Target {
+        Target {
+            spec: TargetSpec {
+                triple: "x86_64-unknown-linux-gnu".into(),
+                std: true,
+                linker: Linker::HostCC,
+                runner: None,
+            },
+            builtin_dirs: Vec::new(),
+            rustflags: vec!["-Clinker=cc".into()],
+            c_compiler: None,
+            runner: None,
+        }
+    }
+
+    #[test]
+    fn test_no_cargo() {
+        let utils = TestUtils::new();
+        check(utils.reporter(), utils.sysroot(), Some(&host())).unwrap();
+        utils.assert_report_skipped("building a sample workspace with cargo (cargo not present)");
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_success() {
+        let utils = TestUtils::new();
+        utils.bin("cargo").program_source(CARGO_SOURCE).create();
+
+        check(utils.reporter(), utils.sysroot(), Some(&host())).unwrap();
+        utils.assert_report_success("built and ran a sample workspace with cargo");
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_build_failed() {
+        let utils = TestUtils::new();
+        let cargo = utils.bin("cargo").exit(101).create();
+
+        match check(utils.reporter(), utils.sysroot(), Some(&host())) {
+            Err(Error::CargoBuildFailed { error }) => assert_eq!(cargo, error.path),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_missing_artifact() {
+        let utils = TestUtils::new();
+        utils.bin("cargo").create();
+
+        match check(utils.reporter(), utils.sysroot(), Some(&host())) {
+            Err(Error::CargoArtifactMissing { path }) => {
+                assert!(path.ends_with("target/x86_64-unknown-linux-gnu/debug/app"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_wrong_output() {
+        let utils = TestUtils::new();
+        let source = CARGO_SOURCE.replace("\"hello from cargo\"", "\"goodbye\"");
+        utils.bin("cargo").program_source(&source).create();
+
+        match check(utils.reporter(), utils.sysroot(), Some(&host())) {
+            Err(Error::SampleProgramOutputWrong { found, .. }) => {
+                assert_eq!(b"goodbye", found.as_slice());
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use crate::error::Error;
+use crate::report::ReportDestination;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+pub(crate) const USAGE: &str = "\
+Usage: ferrocene-self-test [OPTIONS]
+       ferrocene-self-test snapshot [SNAPSHOT OPTIONS]
+
+Options:
+    --report <FORMAT>       Also emit a machine-readable report (formats: json, junit)
+    --report-file <PATH>    Write the machine-readable report to PATH instead of stdout
+    --no-fail-fast          Keep checking after an error, and print a summary of the results
+    --runner <TARGET=CMD>   Execute the sample programs for TARGET by invoking CMD (a
+                            whitespace-separated command line) with the program path appended
+    -h, --help              Print this help message
+
+The snapshot subcommand gathers everything the checks look at into a JSON
+document, to be attached to support requests. It doesn't stop at the first
+error.
+
+Snapshot options:
+    --output <PATH>         Write the snapshot to PATH instead of stdout
+    --redact                Replace the home directory and the user name with placeholders
+";
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum CliCommand {
+    Run(Cli),
+    Snapshot(SnapshotCli),
+    Help,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SnapshotCli {
+    pub(crate) output: ReportDestination,
+    pub(crate) redact: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Cli {
+    pub(crate) report: Option<(ReportFormat, ReportDestination)>,
+    pub(crate) runners: Vec<(String, Vec<String>)>,
+    pub(crate) no_fail_fast: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReportFormat {
+    Json,
+    Junit,
+}
+
+pub(crate) fn parse(args: impl Iterator<Item = OsString>) -> Result<CliCommand, Error> {
+    let mut args = args
+        .map(|arg| {
+            arg.into_string().map_err(|arg| Error::InvalidCliArguments {
+                message: format!("argument {} is not valid UTF-8", arg.to_string_lossy()),
+            })
+        })
+        .peekable();
+    if let Some(Ok(subcommand)) = args.peek() {
+        if subcommand == "snapshot" {
+            args.next();
+            return parse_snapshot(args);
+        }
+    }
+
+    let mut report_format = None;
+    let mut report_file = None;
+    let mut runners = Vec::new();
+    let mut no_fail_fast = false;
+
+    while let Some(arg) = args.next() {
+        let arg = arg?;
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || match inline_value.clone() {
+            Some(value) => Ok(value),
+            None => args.next().unwrap_or_else(|| {
+                Err(Error::InvalidCliArguments { message: format!("missing value for {name}") })
+            }),
+        };
+
+        match name {
+            "-h" | "--help" => return Ok(CliCommand::Help),
+            "--report" => {
+                report_format = Some(match value()?.as_str() {
+                    "json" => ReportFormat::Json,
+                    "junit" => ReportFormat::Junit,
+                    other => {
+                        return Err(Error::InvalidCliArguments {
+                            message: format!("unsupported report format {other}"),
+                        });
+                    }
+                })
+            }
+            "--report-file" => report_file = Some(PathBuf::from(value()?)),
+            "--runner" => {
+                let value = value()?;
+                let Some((target, command)) = value.split_once('=') else {
+                    return Err(Error::InvalidCliArguments {
+                        message: format!("--runner {value} is not in the TARGET=CMD format"),
+                    });
+                };
+                let command = command.split_whitespace().map(String::from).collect::<Vec<_>>();
+                if target.is_empty() || command.is_empty() {
+                    return Err(Error::InvalidCliArguments {
+                        message: format!("--runner {value} is not in the TARGET=CMD format"),
+                    });
+                }
+                runners.push((target.to_string(), command));
+            }
+            "--no-fail-fast" => no_fail_fast = true,
+            other => {
+                return Err(Error::InvalidCliArguments {
+                    message: format!("unknown argument {other}"),
+                });
+            }
+        }
+    }
+
+    let report = match (report_format, report_file) {
+        (Some(format), Some(file)) => Some((format, ReportDestination::File(file))),
+        (Some(format), None) => Some((format, ReportDestination::Stdout)),
+        (None, Some(_)) => {
+            return Err(Error::InvalidCliArguments {
+                message: "--report-file requires --report to also be passed".into(),
+            });
+        }
+        (None, None) => None,
+    };
+
+    Ok(CliCommand::Run(Cli { report, runners, no_fail_fast }))
+}
+
+fn parse_snapshot(
+    mut args: impl Iterator<Item = Result<String, Error>>,
+) -> Result<CliCommand, Error> {
+    let mut output = ReportDestination::Stdout;
+    let mut redact = false;
+
+    while let Some(arg) = args.next() {
+        let arg = arg?;
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+
+        match name {
+            "-h" | "--help" => return Ok(CliCommand::Help),
+            "--output" => {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => args.next().unwrap_or_else(|| {
+                        Err(Error::InvalidCliArguments {
+                            message: format!("missing value for {name}"),
+                        })
+                    })?,
+                };
+                output = ReportDestination::File(PathBuf::from(value));
+            }
+            "--redact" => redact = true,
+            other => {
+                return Err(Error::InvalidCliArguments {
+                    message: format!("unknown snapshot argument {other}"),
+                });
+            }
+        }
+    }
+
+    Ok(CliCommand::Snapshot(SnapshotCli { output, redact }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(args: &[&str]) -> Result<CliCommand, Error> {
+        parse(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn test_no_args() {
+        assert_eq!(CliCommand::Run(Cli::default()), parse_str(&[]).unwrap());
+    }
+
+    #[test]
+    fn test_help() {
+        assert_eq!(CliCommand::Help, parse_str(&["--help"]).unwrap());
+        assert_eq!(CliCommand::Help, parse_str(&["--report", "json", "-h"]).unwrap());
+    }
+
+    #[test]
+    fn test_report() {
+        assert_eq!(
+            CliCommand::Run(Cli {
+                report: Some((ReportFormat::Json, ReportDestination::Stdout)),
+                ..Cli::default()
+            }),
+            parse_str(&["--report", "json"]).unwrap()
+        );
+        assert_eq!(
+            CliCommand::Run(Cli {
+                report: Some((ReportFormat::Junit, ReportDestination::File("out.xml".into()))),
+                ..Cli::default()
+            }),
+            parse_str(&["--report=junit", "--report-file=out.xml"]).unwrap()
+        );
+        assert_eq!(
+            CliCommand::Run(Cli {
+                report: Some((ReportFormat::Json, ReportDestination::File("out.json".into()))),
+                ..Cli::default()
+            }),
+            parse_str(&["--report-file", "out.json", "--report", "json"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_runners() {
+        assert_eq!(
+            CliCommand::Run(Cli {
+                runners: vec![
+                    ("aarch64-unknown-linux-gnu".into(), vec!["qemu-aarch64".into()]),
+                    (
+                        "aarch64-unknown-none".into(),
+                        vec!["qemu-system-aarch64".into(), "-semihosting".into()]
+                    ),
+                ],
+                ..Cli::default()
+            }),
+            parse_str(&[
+                "--runner",
+                "aarch64-unknown-linux-gnu=qemu-aarch64",
+                "--runner=aarch64-unknown-none=qemu-system-aarch64  -semihosting",
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_no_fail_fast() {
+        assert_eq!(
+            CliCommand::Run(Cli { no_fail_fast: true, ..Cli::default() }),
+            parse_str(&["--no-fail-fast"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_snapshot() {
+        assert_eq!(
+            CliCommand::Snapshot(SnapshotCli { output: ReportDestination::Stdout, redact: false }),
+            parse_str(&["snapshot"]).unwrap()
+        );
+        assert_eq!(
+            CliCommand::Snapshot(SnapshotCli {
+                output: ReportDestination::File("snapshot.json".into()),
+                redact: true,
+            }),
+            parse_str(&["snapshot", "--redact", "--output", "snapshot.json"]).unwrap()
+        );
+        assert_eq!(
+            CliCommand::Snapshot(SnapshotCli {
+                output: ReportDestination::File("out.json".into()),
+                redact: false,
+            }),
+            parse_str(&["snapshot", "--output=out.json"]).unwrap()
+        );
+        assert_eq!(CliCommand::Help, parse_str(&["snapshot", "--help"]).unwrap());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let assert_invalid = |args: &[&str], expected: &str| match parse_str(args) {
+            Err(Error::InvalidCliArguments { message }) => assert_eq!(expected, message),
+            other => panic!("unexpected result: {other:?}"),
+        };
+
+        assert_invalid(&["--foo"], "unknown argument --foo");
+        assert_invalid(&["--report", "json", "snapshot"], "unknown argument snapshot");
+        assert_invalid(&["snapshot", "--report", "json"], "unknown snapshot argument --report");
+        assert_invalid(&["snapshot", "--output"], "missing value for --output");
+        assert_invalid(&["--report"], "missing value for --report");
+        assert_invalid(&["--report", "yaml"], "unsupported report format yaml");
+        assert_invalid(&["--runner", "qemu"], "--runner qemu is not in the TARGET=CMD format");
+        assert_invalid(&["--runner", "foo= "], "--runner foo=  is not in the TARGET=CMD format");
+        assert_invalid(
+            &["--report-file", "out.json"],
+            "--report-file requires --report to also be passed",
+        );
+    }
+}
@@ -1,12 +1,14 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
+use crate::diagnostics::{target_group, Diagnostics};
 use crate::error::Error;
 use crate::report::Reporter;
-use crate::targets::Target;
+use crate::targets::{BuiltinDir, Target};
 use crate::utils::run_command;
 use std::collections::HashSet;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
+use std::fmt::Write as _;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
@@ -14,28 +16,36 @@
     SampleProgram {
         name: "addition.rs",
         contents: include_bytes!("../sample-programs/addition.rs"),
-        rustflags: &["--crate-type", "lib", "--edition", "2021"],
-        expected_artifacts: &["libaddition.rlib"],
+        compiler: Compiler::Rustc,
+        requires: &[],
+        args: &["--crate-type", "lib", "--edition", "2021"],
+        expected_artifacts: &[Artifact::File("libaddition.rlib")],
         executable_output: None,
     },
     SampleProgram {
         name: "subtraction.rs",
         contents: include_bytes!("../sample-programs/subtraction.rs"),
-        rustflags: &["--crate-type", "staticlib", "--edition", "2021"],
-        expected_artifacts: &["libsubtraction.a"],
+        compiler: Compiler::Rustc,
+        requires: &[],
+        args: &["--crate-type", "staticlib", "--edition", "2021"],
+        expected_artifacts: &[Artifact::File("libsubtraction.a")],
         executable_output: None,
     },
     SampleProgram {
         name: "subtraction-sys.rs",
         contents: include_bytes!("../sample-programs/subtraction-sys.rs"),
-        rustflags: &["--crate-type", "lib", "--edition", "2021", "-l", "subtraction"],
-        expected_artifacts: &["libsubtraction_sys.rlib"],
+        compiler: Compiler::Rustc,
+        requires: &[],
+        args: &["--crate-type", "lib", "--edition", "2021", "-l", "subtraction"],
+        expected_artifacts: &[Artifact::File("libsubtraction_sys.rlib")],
         executable_output: None,
     },
     SampleProgram {
         name: "assertion.rs",
         contents: include_bytes!("../sample-programs/assertion.rs"),
-        rustflags: &[
+        compiler: Compiler::Rustc,
+        requires: &[],
+        args: &[
             "--crate-type",
             "bin",
             "--edition",
@@ -45,19 +55,85 @@
             "--extern",
             "subtraction_sys",
         ],
-        expected_artifacts: &["assertion"],
+        expected_artifacts: &[Artifact::File("assertion")],
         // See assertion.rs
         executable_output: Some(b"123456789"),
     },
+    SampleProgram {
+        name: "multiplication.rs",
+        contents: include_bytes!("../sample-programs/multiplication.rs"),
+        compiler: Compiler::Rustc,
+        requires: &[Requirement::Std],
+        args: &["--crate-type", "dylib", "--edition", "2021", "-C", "prefer-dynamic"],
+        expected_artifacts: &[Artifact::DynamicLibrary("multiplication")],
+        executable_output: None,
+    },
+    SampleProgram {
+        name: "multiplication-user.rs",
+        contents: include_bytes!("../sample-programs/multiplication-user.rs"),
+        compiler: Compiler::Rustc,
+        requires: &[Requirement::Std],
+        args: &[
+            "--crate-type",
+            "bin",
+            "--edition",
+            "2021",
+            "-C",
+            "prefer-dynamic",
+            "--extern",
+            "multiplication",
+        ],
+        expected_artifacts: &[Artifact::File("multiplication-user")],
+        executable_output: Some(b"42"),
+    },
+    SampleProgram {
+        name: "division.rs",
+        contents: include_bytes!("../sample-programs/division.rs"),
+        compiler: Compiler::Rustc,
+        requires: &[Requirement::Std],
+        args: &["--crate-type", "cdylib", "--edition", "2021"],
+        expected_artifacts: &[Artifact::DynamicLibrary("division")],
+        executable_output: None,
+    },
+    SampleProgram {
+        name: "division-harness.c",
+        contents: include_bytes!("../sample-programs/division-harness.c"),
+        compiler: Compiler::C,
+        requires: &[Requirement::Std, Requirement::CCompiler],
+        args: &["-l", "division"],
+        expected_artifacts: &[Artifact::File("division-harness")],
+        executable_output: Some(b"25"),
+    },
+    SampleProgram {
+        name: "answer-macro.rs",
+        contents: include_bytes!("../sample-programs/answer-macro.rs"),
+        compiler: Compiler::Rustc,
+        requires: &[Requirement::Host],
+        args: &["--crate-type", "proc-macro", "--edition", "2021"],
+        expected_artifacts: &[Artifact::DynamicLibrary("answer_macro")],
+        executable_output: None,
+    },
+    SampleProgram {
+        name: "macro-user.rs",
+        contents: include_bytes!("../sample-programs/macro-user.rs"),
+        compiler: Compiler::Rustc,
+        requires: &[Requirement::Host],
+        args: &["--crate-type", "bin", "--edition", "2021", "--extern", "answer_macro"],
+        expected_artifacts: &[Artifact::File("macro-user")],
+        executable_output: Some(b"1337"),
+    },
 ];
 
 pub(crate) fn check(
     reporter: &dyn Reporter,
+    diagnostics: &Diagnostics<'_>,
     sysroot: &Path,
     targets: &[Target],
 ) -> Result<(), Error> {
     for target in targets {
-        check_target(reporter, sysroot, target, SAMPLE_PROGRAMS)?;
+        diagnostics.check(&target_group(&target.triple), || {
+            check_target(reporter, sysroot, target, SAMPLE_PROGRAMS)
+        })?;
     }
     Ok(())
 }
@@ -76,6 +152,7 @@ fn check_target(
     let ctx = Context {
         target,
         rustc: sysroot.join("bin").join("rustc"),
+        target_lib_dir: sysroot.join("lib").join("rustlib").join(&target.triple).join("lib"),
         temp_dir: temp.path().into(),
         source_dir: temp.path().join("src"),
         output_dir: temp.path().join("out"),
@@ -88,12 +165,24 @@ fn check_target(
     let mut expected_artifacts = ExpectedFiles::new(&ctx.output_dir);
 
     for program in programs {
-        let should_run = if ctx.target.triple == env!("SELFTEST_TARGET") {
-            program.executable_output
-        } else {
-            None
-        };
-        expected_artifacts.add(program.expected_artifacts);
+        if let Some(missing) = program.requires.iter().find(|r| !r.is_satisfied_by(target)) {
+            reporter.skipped(&format!(
+                "sample program `{}` for target {} ({})",
+                program.name,
+                target.triple,
+                missing.describe()
+            ));
+            continue;
+        }
+
+        let should_run =
+            if ctx.target.triple == env!("SELFTEST_TARGET") || ctx.target.runner.is_some() {
+                program.executable_output
+            } else {
+                None
+            };
+        expected_artifacts
+            .add(program.expected_artifacts.iter().map(|a| a.file_name(&target.triple)));
         compile(&ctx, program, should_run)?;
         expected_artifacts.check(program.name)?;
 
@@ -104,38 +193,129 @@ fn check_target(
             target.triple
         ));
     }
+
+    for builtin in &target.builtin_dirs {
+        let crate_name = builtin_user_crate_name(builtin);
+        expected_artifacts.add([format!("lib{crate_name}.rlib")]);
+        let name = compile_builtin_user(&ctx, builtin, &crate_name)?;
+        expected_artifacts.check(&name)?;
+
+        reporter.success(&format!(
+            "compiled sample program `{name}` linking to builtin directory {} for target {}",
+            builtin.name, target.triple
+        ));
+    }
     Ok(())
 }
 
-fn compile(
+/// Compiles a library loading all the libraries in a builtin directory, through
+/// `-L ferrocene-temp-builtin:<name>`. Returns the name of the sample program.
+fn compile_builtin_user(
     ctx: &Context<'_>,
-    program: &SampleProgram,
-    expected_output: Option<&[u8]>,
-) -> Result<(), Error> {
-    let program_path = ctx.source_dir.join(program.name);
-    std::fs::write(&program_path, program.contents).map_err(|error| {
-        Error::WritingSampleProgramFailed {
-            name: program.name.into(),
-            dest: program_path.clone(),
-            error,
-        }
+    builtin: &BuiltinDir,
+    crate_name: &str,
+) -> Result<String, Error> {
+    let name = format!("{crate_name}.rs");
+    let mut contents = String::from("#![no_std]\n");
+    for library in &builtin.libraries {
+        writeln!(contents, "extern crate {library} as _;").unwrap();
+    }
+
+    let program_path = ctx.source_dir.join(&name);
+    std::fs::write(&program_path, contents).map_err(|error| Error::WritingSampleProgramFailed {
+        name: name.clone(),
+        dest: program_path.clone(),
+        error,
     })?;
 
+    let search_path = format!("ferrocene-temp-builtin:{}", builtin.name);
+    let args = [
+        "--crate-type",
+        "lib",
+        "--edition",
+        "2021",
+        "--crate-name",
+        crate_name,
+        "-L",
+        &search_path,
+    ];
+    let mut cmd = rustc_command(ctx, args, &program_path);
+    run_command(&mut cmd)
+        .map_err(|error| Error::SampleProgramCompilationFailed { name: name.clone(), error })?;
+
+    Ok(name)
+}
+
+/// Name of the crate loading the libraries of a builtin directory, which can contain characters
+/// not allowed in crate names.
+fn builtin_user_crate_name(builtin: &BuiltinDir) -> String {
+    let sanitized = builtin
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    format!("builtin_{sanitized}")
+}
+
+fn rustc_command<I, S>(ctx: &Context<'_>, args: I, program_path: &Path) -> Command
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
     let mut remap_path_prefix = OsString::new();
     remap_path_prefix.push(&ctx.temp_dir);
     remap_path_prefix.push("=/self-test");
 
     let mut cmd = Command::new(&ctx.rustc);
-    cmd.args(["--target", ctx.target.triple]);
+    cmd.args(["--target", ctx.target.triple.as_str()]);
     cmd.arg("-L").arg(&ctx.output_dir);
     cmd.arg("--out-dir").arg(&ctx.output_dir);
     cmd.arg("--remap-path-prefix").arg(&remap_path_prefix);
     if !ctx.target.std {
         cmd.args(["--cfg", "selftest_no_std"]);
     }
-    cmd.args(program.rustflags);
+    cmd.args(args);
     cmd.args(&ctx.target.rustflags);
-    cmd.arg(&program_path);
+    if let Some(runner) = &ctx.target.runner {
+        cmd.args(&runner.rustflags);
+    }
+    cmd.arg(program_path);
+    cmd
+}
+
+fn compile(
+    ctx: &Context<'_>,
+    program: &SampleProgram,
+    expected_output: Option<&[u8]>,
+) -> Result<(), Error> {
+    let program_path = ctx.source_dir.join(program.name);
+    std::fs::write(&program_path, program.contents).map_err(|error| {
+        Error::WritingSampleProgramFailed {
+            name: program.name.into(),
+            dest: program_path.clone(),
+            error,
+        }
+    })?;
+
+    // where is it
+    let (bin_name, _extension) = program.name.rsplit_once('.').unwrap_or((program.name, ""));
+    let bin_path = ctx.output_dir.join(bin_name);
+
+    let mut cmd = match program.compiler {
+        Compiler::Rustc => rustc_command(ctx, program.args, &program_path),
+        Compiler::C => {
+            // Programs requiring a C compiler are skipped when none was found.
+            let cc = ctx.target.c_compiler.as_ref().expect("no C compiler for the target");
+
+            let mut cmd = Command::new(&cc.path);
+            cmd.args(&cc.args);
+            cmd.arg(&program_path);
+            cmd.arg("-o").arg(&bin_path);
+            cmd.arg("-L").arg(&ctx.output_dir);
+            cmd.args(program.args);
+            cmd
+        }
+    };
 
     run_command(&mut cmd).map_err(|error| Error::SampleProgramCompilationFailed {
         name: program.name.into(),
@@ -143,11 +323,22 @@ fn compile(
     })?;
 
     if let Some(expected_output) = expected_output {
-        // where is it
-        let bin_name = program.name.replace(".rs", "");
-        let bin_path = ctx.output_dir.join(bin_name);
-        // now try and execute it
-        let mut cmd = Command::new(&bin_path);
+        // now try and execute it, through the runner if the host can't execute it natively
+        let mut cmd = match &ctx.target.runner {
+            Some(runner) => {
+                let mut cmd = Command::new(&runner.path);
+                cmd.args(&runner.args).arg(&bin_path);
+                cmd
+            }
+            None => Command::new(&bin_path),
+        };
+        // Programs using dynamic libraries need to find both the sample libraries and the
+        // dynamic standard library shipped with the target.
+        let mut library_path = ctx.output_dir.clone().into_os_string();
+        library_path.push(":");
+        library_path.push(&ctx.target_lib_dir);
+        cmd.env(library_path_env(&ctx.target.triple), library_path);
+
         let output = cmd.output().map_err(|error| Error::RunningSampleProgramFailed {
             name: program.name.into(),
             error,
@@ -164,9 +355,17 @@ fn compile(
     Ok(())
 }
 
+fn library_path_env(target: &str) -> &'static str {
+    if target.contains("-apple-") {
+        "DYLD_LIBRARY_PATH"
+    } else {
+        "LD_LIBRARY_PATH"
+    }
+}
+
 struct ExpectedFiles {
     path: PathBuf,
-    expected: HashSet<&'static str>,
+    expected: HashSet<String>,
 }
 
 impl ExpectedFiles {
@@ -174,8 +373,8 @@ fn new(path: &Path) -> Self {
         Self { path: path.into(), expected: HashSet::new() }
     }
 
-    fn add(&mut self, files: &[&'static str]) {
-        self.expected.extend(files.iter().copied());
+    fn add<S: Into<String>>(&mut self, files: impl IntoIterator<Item = S>) {
+        self.expected.extend(files.into_iter().map(|file| file.into()));
     }
 
     fn check(&self, after_compiling: &str) -> Result<(), Error> {
@@ -202,7 +401,7 @@ fn check(&self, after_compiling: &str) -> Result<(), Error> {
         currently_expected.sort();
         for missing_file in currently_expected {
             return Err(Error::MissingCompilationArtifact {
-                name: missing_file.into(),
+                name: missing_file,
                 after_compiling: after_compiling.into(),
             });
         }
@@ -214,6 +413,7 @@ fn check(&self, after_compiling: &str) -> Result<(), Error> {
 struct Context<'a> {
     target: &'a Target,
     rustc: PathBuf,
+    target_lib_dir: PathBuf,
     temp_dir: PathBuf,
     source_dir: PathBuf,
     output_dir: PathBuf,
@@ -222,15 +422,74 @@ struct Context<'a> {
 struct SampleProgram {
     name: &'static str,
     contents: &'static [u8],
-    rustflags: &'static [&'static str],
-    expected_artifacts: &'static [&'static str],
+    compiler: Compiler,
+    requires: &'static [Requirement],
+    args: &'static [&'static str],
+    expected_artifacts: &'static [Artifact],
     executable_output: Option<&'static [u8]>,
 }
 
+enum Compiler {
+    Rustc,
+    /// The C compiler found for the target by the linkers check.
+    C,
+}
+
+/// What a target needs for a sample program to be compiled for it.
+#[derive(Debug)]
+enum Requirement {
+    /// The target must provide the standard library.
+    Std,
+    /// The target must be the host, for example because it's a procedural macro.
+    Host,
+    /// A C compiler must have been found for the target.
+    CCompiler,
+}
+
+impl Requirement {
+    fn is_satisfied_by(&self, target: &Target) -> bool {
+        match self {
+            Requirement::Std => target.std,
+            Requirement::Host => target.triple == env!("SELFTEST_TARGET"),
+            Requirement::CCompiler => target.c_compiler.is_some(),
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Requirement::Std => "the target does not provide the standard library",
+            Requirement::Host => "only compiled for the host",
+            Requirement::CCompiler => "no C compiler available for the target",
+        }
+    }
+}
+
+enum Artifact {
+    /// A file whose name is the same on every platform.
+    File(&'static str),
+    /// A dynamic library (`dylib`, `cdylib` or `proc-macro`), named after the platform's
+    /// conventions.
+    DynamicLibrary(&'static str),
+}
+
+impl Artifact {
+    fn file_name(&self, target: &str) -> String {
+        match self {
+            Artifact::File(name) => (*name).into(),
+            Artifact::DynamicLibrary(name) if target.contains("-apple-") => {
+                format!("lib{name}.dylib")
+            }
+            Artifact::DynamicLibrary(name) if target.contains("-windows") => format!("{name}.dll"),
+            Artifact::DynamicLibrary(name) => format!("lib{name}.so"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::linkers::Linker;
+    use crate::linkers::{CCompiler, Linker};
+    use crate::runners::Runner;
     use crate::targets::TargetSpec;
     use crate::test_utils::TestUtils;
     use tempfile::TempDir;
@@ -300,26 +559,34 @@ fn main() {
             SampleProgram {
                 name: "foo.rs",
                 contents: b"pub fn foo() {}",
-                rustflags: &["--crate-type", "lib"],
-                expected_artifacts: &["libfoo.rlib"],
+                compiler: Compiler::Rustc,
+                requires: &[],
+                args: &["--crate-type", "lib"],
+                expected_artifacts: &[Artifact::File("libfoo.rlib")],
                 executable_output: None,
             },
             SampleProgram {
                 name: "bar.rs",
                 contents: b"fn main() {}",
-                rustflags: &["--crate-type", "bin"],
-                expected_artifacts: &["bar"],
+                compiler: Compiler::Rustc,
+                requires: &[],
+                args: &["--crate-type", "bin"],
+                expected_artifacts: &[Artifact::File("bar")],
                 executable_output: None,
             },
         ];
 
         let target = Target {
-            spec: &TargetSpec {
-                triple: "x86_64-unknown-linux-gnu",
+            spec: TargetSpec {
+                triple: "x86_64-unknown-linux-gnu".into(),
                 std: true,
                 linker: Linker::BundledLld,
+                runner: None,
             },
+            builtin_dirs: Vec::new(),
             rustflags: vec!["-C linker=rust-lld".into()],
+            c_compiler: None,
+            runner: None,
         };
 
         let utils = TestUtils::new();
@@ -328,6 +595,200 @@ fn main() {
         check_target(utils.reporter(), utils.sysroot(), &target, TEST_PROGRAMS).unwrap();
     }
 
+    #[test]
+    fn test_check_target_builtin_dirs() {
+        const RUSTC_SOURCE: &str = r##"
+            fn main() {
+                let args = std::env::args().skip(1).collect::<Vec<_>>();
+                let args = args.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+                match &args[..] {
+                    [
+                        "--target", "aarch64-unknown-none",
+                        "-L", _,
+                        "--out-dir", out_dir,
+                        "--remap-path-prefix", _,
+                        "--cfg", "selftest_no_std",
+                        "--crate-type", "lib",
+                        "--edition", "2021",
+                        "--crate-name", "builtin_oxidos_debug",
+                        "-L", "ferrocene-temp-builtin:oxidos-debug",
+                        source
+                    ] => {
+                        assert_eq!(
+                            "#![no_std]\nextern crate bitflags as _;\nextern crate oxidos as _;\n",
+                            std::fs::read_to_string(source).unwrap()
+                        );
+                        std::fs::write(format!("{out_dir}/libbuiltin_oxidos_debug.rlib"), b"")
+                            .unwrap();
+                    }
+                    other => panic!("unexpected args: {other:?}"),
+                }
+            }
+        "##;
+
+        let target = Target {
+            spec: TargetSpec {
+                triple: "aarch64-unknown-none".into(),
+                std: false,
+                linker: Linker::BundledLld,
+                runner: None,
+            },
+            builtin_dirs: vec![BuiltinDir {
+                name: "oxidos-debug".into(),
+                libraries: vec!["bitflags".into(), "oxidos".into()],
+            }],
+            rustflags: Vec::new(),
+            c_compiler: None,
+            runner: None,
+        };
+
+        let utils = TestUtils::new();
+        utils.bin("rustc").program_source(RUSTC_SOURCE).create();
+
+        check_target(utils.reporter(), utils.sysroot(), &target, &[]).unwrap();
+        utils.assert_report_success(
+            "compiled sample program `builtin_oxidos_debug.rs` linking to builtin directory \
+             oxidos-debug for target aarch64-unknown-none",
+        );
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_check_target_unsatisfied_requirements() {
+        const TEST_PROGRAMS: &[SampleProgram] = &[
+            SampleProgram {
+                name: "foo.rs",
+                contents: b"",
+                compiler: Compiler::Rustc,
+                requires: &[Requirement::Std],
+                args: &[],
+                expected_artifacts: &[],
+                executable_output: None,
+            },
+            SampleProgram {
+                name: "bar.rs",
+                contents: b"",
+                compiler: Compiler::Rustc,
+                requires: &[Requirement::Host],
+                args: &[],
+                expected_artifacts: &[],
+                executable_output: None,
+            },
+            SampleProgram {
+                name: "baz.c",
+                contents: b"",
+                compiler: Compiler::C,
+                requires: &[Requirement::CCompiler],
+                args: &[],
+                expected_artifacts: &[],
+                executable_output: None,
+            },
+        ];
+
+        let target = Target {
+            spec: TargetSpec {
+                triple: "foo-unknown-none".into(),
+                std: false,
+                linker: Linker::BundledLld,
+                runner: None,
+            },
+            builtin_dirs: Vec::new(),
+            rustflags: Vec::new(),
+            c_compiler: None,
+            runner: None,
+        };
+
+        // No rustc is created, as nothing should be compiled.
+        let utils = TestUtils::new();
+        check_target(utils.reporter(), utils.sysroot(), &target, TEST_PROGRAMS).unwrap();
+
+        utils.assert_report_skipped(
+            "sample program `baz.c` for target foo-unknown-none \
+             (no C compiler available for the target)",
+        );
+        utils.assert_report_skipped(
+            "sample program `bar.rs` for target foo-unknown-none (only compiled for the host)",
+        );
+        utils.assert_report_skipped(
+            "sample program `foo.rs` for target foo-unknown-none \
+             (the target does not provide the standard library)",
+        );
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_compile_c_program() {
+        let tempdir = TempDir::new().unwrap();
+        let source_dir = tempdir.path().join("src");
+        let output_dir = tempdir.path().join("out");
+
+        std::fs::create_dir_all(&source_dir).unwrap();
+        std::fs::create_dir_all(&output_dir).unwrap();
+
+        let source_file = source_dir.join("harness.c");
+        let bin_file = output_dir.join("harness");
+
+        let utils = TestUtils::new();
+        let rustc = utils.bin("rustc").create();
+        let cc = utils
+            .bin("foo-gcc")
+            .expected_args(&[
+                "-fno-lto",
+                source_file.to_str().unwrap(),
+                "-o",
+                bin_file.to_str().unwrap(),
+                "-L",
+                output_dir.to_str().unwrap(),
+                "-l",
+                "foo",
+            ])
+            .create();
+
+        let context = Context {
+            target: &Target {
+                spec: TargetSpec {
+                    triple: "foo-unknown-linux-gnu".into(),
+                    std: true,
+                    linker: Linker::CrossCC(vec!["foo-".into()]),
+                    runner: None,
+                },
+                builtin_dirs: Vec::new(),
+                rustflags: Vec::new(),
+                c_compiler: Some(CCompiler { path: cc, args: vec!["-fno-lto".into()] }),
+                runner: None,
+            },
+            rustc,
+            target_lib_dir: tempdir.path().join("lib"),
+            temp_dir: tempdir.path().into(),
+            source_dir,
+            output_dir,
+        };
+        let program = SampleProgram {
+            name: "harness.c",
+            contents: b"int main(void) { return 0; }\n",
+            compiler: Compiler::C,
+            requires: &[Requirement::CCompiler],
+            args: &["-l", "foo"],
+            expected_artifacts: &[],
+            executable_output: None,
+        };
+
+        compile(&context, &program, None).unwrap();
+    }
+
+    #[test]
+    fn test_artifact_file_name() {
+        let file = Artifact::File("libfoo.rlib");
+        assert_eq!("libfoo.rlib", file.file_name("x86_64-unknown-linux-gnu"));
+        assert_eq!("libfoo.rlib", file.file_name("aarch64-apple-darwin"));
+
+        let dylib = Artifact::DynamicLibrary("foo");
+        assert_eq!("libfoo.so", dylib.file_name("x86_64-unknown-linux-gnu"));
+        assert_eq!("libfoo.so", dylib.file_name("aarch64-unknown-linux-gnu"));
+        assert_eq!("libfoo.dylib", dylib.file_name("aarch64-apple-darwin"));
+        assert_eq!("foo.dll", dylib.file_name("x86_64-pc-windows-msvc"));
+    }
+
     #[test]
     fn test_expected_files() {
         let dir = tempfile::tempdir().unwrap();
@@ -336,7 +797,7 @@ fn test_expected_files() {
         let create = |name| std::fs::write(dir.join(name), b"").unwrap();
 
         let mut expected = ExpectedFiles::new(dir);
-        expected.add(&["foo", "bar"]);
+        expected.add(["foo", "bar"]);
         match expected.check("binary") {
             Err(Error::MissingCompilationArtifact { name, after_compiling }) => {
                 assert_eq!("bar", name);
@@ -368,7 +829,7 @@ fn test_expected_files() {
             other => panic!("unexpected result: {other:?}"),
         }
 
-        expected.add(&["baz"]);
+        expected.add(["baz"]);
         expected.check("binary").unwrap();
     }
 
@@ -382,14 +843,19 @@ fn test_compile_failed_to_write_program() {
 
         let context = Context {
             target: &Target {
-                spec: &TargetSpec {
-                    triple: "x86_64-unknown-linux-gnu",
+                spec: TargetSpec {
+                    triple: "x86_64-unknown-linux-gnu".into(),
                     std: true,
                     linker: Linker::BundledLld,
+                    runner: None,
                 },
+                builtin_dirs: Vec::new(),
                 rustflags: Vec::new(),
+                c_compiler: None,
+                runner: None,
             },
             rustc: rustc.clone(),
+            target_lib_dir: tempdir.path().join("lib"),
             temp_dir: tempdir.path().into(),
             source_dir: tempdir.path().join("missing"),
             output_dir,
@@ -397,7 +863,9 @@ fn test_compile_failed_to_write_program() {
         let program = SampleProgram {
             name: "example.rs",
             contents: b"fn main() { println!(\"Hello world!\"); }\n",
-            rustflags: &[],
+            compiler: Compiler::Rustc,
+            requires: &[],
+            args: &[],
             expected_artifacts: &[],
             executable_output: None,
         };
@@ -427,14 +895,19 @@ fn test_compile_failed_invocation() {
 
         let context = Context {
             target: &Target {
-                spec: &TargetSpec {
-                    triple: "x86_64-unknown-linux-gnu",
+                spec: TargetSpec {
+                    triple: "x86_64-unknown-linux-gnu".into(),
                     std: true,
                     linker: Linker::BundledLld,
+                    runner: None,
                 },
+                builtin_dirs: Vec::new(),
                 rustflags: Vec::new(),
+                c_compiler: None,
+                runner: None,
             },
             rustc: rustc.clone(),
+            target_lib_dir: tempdir.path().join("lib"),
             temp_dir: tempdir.path().into(),
             source_dir,
             output_dir,
@@ -442,7 +915,9 @@ fn test_compile_failed_invocation() {
         let program = SampleProgram {
             name: "example.rs",
             contents: b"fn main() { println!(\"Hello world!\"); }\n",
-            rustflags: &[],
+            compiler: Compiler::Rustc,
+            requires: &[],
+            args: &[],
             expected_artifacts: &[],
             executable_output: None,
         };
@@ -495,24 +970,19 @@ fn compile_success_inner(std: bool, extra_arg: &[&str]) {
 
         let context = Context {
             target: &Target {
-                // An if statement is used here because spec must be &'static, which means we
-                // cannot dynamically set std depending on the function parameter.
-                spec: if std {
-                    &TargetSpec {
-                        triple: "x86_64-unknown-linux-gnu",
-                        linker: Linker::BundledLld,
-                        std: true,
-                    }
-                } else {
-                    &TargetSpec {
-                        triple: "x86_64-unknown-linux-gnu",
-                        linker: Linker::BundledLld,
-                        std: false,
-                    }
+                spec: TargetSpec {
+                    triple: "x86_64-unknown-linux-gnu".into(),
+                    linker: Linker::BundledLld,
+                    runner: None,
+                    std,
                 },
+                builtin_dirs: Vec::new(),
                 rustflags: vec!["-Clinker=rust-lld".into()],
+                c_compiler: None,
+                runner: None,
             },
             rustc,
+            target_lib_dir: tempdir.path().join("lib"),
             temp_dir: tempdir.path().into(),
             source_dir,
             output_dir,
@@ -521,11 +991,78 @@ fn compile_success_inner(std: bool, extra_arg: &[&str]) {
         let program = SampleProgram {
             name: "example.rs",
             contents: b"fn main() { println!(\"Hello world!\"); }\n",
-            rustflags: &["--extern", "foo"],
-            expected_artifacts: &["example"],
+            compiler: Compiler::Rustc,
+            requires: &[],
+            args: &["--extern", "foo"],
+            expected_artifacts: &[Artifact::File("example")],
             executable_output: None,
         };
 
         compile(&context, &program, None).unwrap();
     }
+
+    #[test]
+    fn test_compile_and_run_with_runner() {
+        let tempdir = TempDir::new().unwrap();
+        let source_dir = tempdir.path().join("src");
+        let output_dir = tempdir.path().join("out");
+
+        std::fs::create_dir_all(&source_dir).unwrap();
+        std::fs::create_dir_all(&output_dir).unwrap();
+
+        let bin_path = output_dir.join("example");
+        let bin_path_str = bin_path.to_str().unwrap();
+
+        let utils = TestUtils::new();
+        let rustc = utils.bin("rustc").create();
+        let runner = utils
+            .bin("qemu-foo")
+            .expected_args(&["-L", "/usr/foo", bin_path_str])
+            .stdout("123456789")
+            .create();
+
+        let context = Context {
+            target: &Target {
+                spec: TargetSpec {
+                    triple: "aarch64-unknown-linux-gnu".into(),
+                    std: true,
+                    linker: Linker::BundledLld,
+                    runner: None,
+                },
+                builtin_dirs: Vec::new(),
+                rustflags: Vec::new(),
+                c_compiler: None,
+                runner: Some(Runner {
+                    path: runner,
+                    args: vec!["-L".into(), "/usr/foo".into()],
+                    rustflags: Vec::new(),
+                }),
+            },
+            rustc,
+            target_lib_dir: tempdir.path().join("lib"),
+            temp_dir: tempdir.path().into(),
+            source_dir,
+            output_dir,
+        };
+        let program = SampleProgram {
+            name: "example.rs",
+            contents: b"fn main() { print!(\"123456789\"); }\n",
+            compiler: Compiler::Rustc,
+            requires: &[],
+            args: &[],
+            expected_artifacts: &[],
+            executable_output: None,
+        };
+
+        compile(&context, &program, Some(b"123456789")).unwrap();
+
+        match compile(&context, &program, Some(b"987654321")) {
+            Err(Error::SampleProgramOutputWrong { name, expected, found }) => {
+                assert_eq!("example.rs", name);
+                assert_eq!(b"987654321", expected.as_slice());
+                assert_eq!(b"123456789", found.as_slice());
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
 }
@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use crate::error::Error;
+use crate::report::{GroupResult, Reporter};
+use std::cell::{Cell, RefCell};
+
+/// Decides what happens when a check fails, and tracks the outcome of each group of checks (like
+/// all the checks for a target).
+///
+/// When failing fast (the default) the first error is returned to the caller, stopping the
+/// self-test. Otherwise errors are reported as soon as they happen, and the remaining checks of
+/// the group are skipped while the other groups are still checked.
+pub(crate) struct Diagnostics<'a> {
+    reporter: &'a dyn Reporter,
+    fail_fast: bool,
+    groups: RefCell<Vec<GroupResult>>,
+    first_error_code: Cell<Option<u8>>,
+}
+
+impl<'a> Diagnostics<'a> {
+    pub(crate) fn new(reporter: &'a dyn Reporter, fail_fast: bool) -> Self {
+        Self {
+            reporter,
+            fail_fast,
+            groups: RefCell::new(Vec::new()),
+            first_error_code: Cell::new(None),
+        }
+    }
+
+    /// Runs a check belonging to the given group.
+    ///
+    /// Returns `Ok(None)` if the check failed and the error was already reported, in which case
+    /// the caller should skip the checks depending on this one.
+    pub(crate) fn check<T>(
+        &self,
+        group: &str,
+        check: impl FnOnce() -> Result<T, Error>,
+    ) -> Result<Option<T>, Error> {
+        let result = check();
+
+        let mut groups = self.groups.borrow_mut();
+        let index = match groups.iter().position(|g| g.name == group) {
+            Some(index) => index,
+            None => {
+                groups.push(GroupResult { name: group.into(), error_codes: Vec::new() });
+                groups.len() - 1
+            }
+        };
+
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => {
+                groups[index].error_codes.push(error.code());
+                if self.first_error_code.get().is_none() {
+                    self.first_error_code.set(Some(error.code()));
+                }
+                if self.fail_fast {
+                    Err(error)
+                } else {
+                    self.reporter.error(&error);
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Code of the first error encountered, which determines the exit code of the self-test.
+    pub(crate) fn first_error_code(&self) -> Option<u8> {
+        self.first_error_code.get()
+    }
+
+    /// Results of all the groups, in the order they were first checked.
+    pub(crate) fn groups(&self) -> Vec<GroupResult> {
+        self.groups.borrow().clone()
+    }
+}
+
+/// Name of the group containing all the checks for a target.
+pub(crate) fn target_group(triple: &str) -> String {
+    format!("target {triple}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestUtils;
+
+    fn failure() -> Result<(), Error> {
+        Err(Error::NoSysroot)
+    }
+
+    fn other_failure() -> Result<(), Error> {
+        Err(Error::BundledLinkerMissing)
+    }
+
+    #[test]
+    fn test_fail_fast() {
+        let utils = TestUtils::new();
+        let diagnostics = Diagnostics::new(utils.reporter(), true);
+
+        assert_eq!(Some(42), diagnostics.check("binaries", || Ok(42)).unwrap());
+        match diagnostics.check("target foo", failure) {
+            Err(Error::NoSysroot) => {}
+            other => panic!("unexpected result: {other:?}"),
+        }
+        // The error is returned to the caller rather than being reported.
+        utils.assert_no_reports();
+
+        assert_eq!(Some(1), diagnostics.first_error_code());
+        assert_eq!(
+            vec![
+                GroupResult { name: "binaries".into(), error_codes: vec![] },
+                GroupResult { name: "target foo".into(), error_codes: vec![1] },
+            ],
+            diagnostics.groups()
+        );
+    }
+
+    #[test]
+    fn test_no_fail_fast() {
+        let utils = TestUtils::new();
+        let diagnostics = Diagnostics::new(utils.reporter(), false);
+
+        assert_eq!(None, diagnostics.check("target foo", other_failure).unwrap());
+        assert_eq!(Some(()), diagnostics.check("target bar", || Ok(())).unwrap());
+        assert_eq!(None, diagnostics.check("target bar", failure).unwrap());
+        assert_eq!(None, diagnostics.check("target foo", failure).unwrap());
+        utils.assert_report_error(1);
+        utils.assert_report_error(1);
+        utils.assert_report_error(15);
+        utils.assert_no_reports();
+
+        // The exit code is the one of the first error.
+        assert_eq!(Some(15), diagnostics.first_error_code());
+        assert_eq!(
+            vec![
+                GroupResult { name: "target foo".into(), error_codes: vec![15, 1] },
+                GroupResult { name: "target bar".into(), error_codes: vec![1] },
+            ],
+            diagnostics.groups()
+        );
+    }
+}
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
+use crate::report::ReportDestination;
 use std::ffi::OsString;
 use std::fmt::Display;
 use std::path::PathBuf;
@@ -41,6 +42,31 @@ pub(crate) enum Error {
     LinkerArgsError { target: String, kind: LinkerArgsErrorKind },
     RunningSampleProgramFailed { name: String, error: std::io::Error },
     SampleProgramOutputWrong { name: String, expected: Vec<u8>, found: Vec<u8> },
+    InvalidCliArguments { message: String },
+    WritingReportFailed { dest: ReportDestination, error: std::io::Error },
+    TargetsDataReadFailed { path: PathBuf, error: std::io::Error },
+    TargetsDataParseFailed { path: PathBuf, error: toml::de::Error },
+    InvalidTargetsData { path: PathBuf, target: String, reason: String },
+    RunnerNotFound { target: String, name: String, error: FindBinaryInPathError },
+    ManifestDiscoveryFailed { path: PathBuf, error: std::io::Error },
+    ManifestReadFailed { path: PathBuf, error: std::io::Error },
+    ManifestParseFailed { path: PathBuf, error: serde_json::Error },
+    InvalidManifest { path: PathBuf, reason: String },
+    InstalledFileMissing { package: String, path: String },
+    InstalledFileReadFailed { path: PathBuf, error: std::io::Error },
+    InstalledFileModeMismatch { package: String, path: String, expected: u32, found: u32 },
+    InstalledFileHashMismatch { package: String, path: String },
+    UnmanagedFile { package: String, prefix: String, path: String },
+    RustdocFailed { error: CommandError },
+    RustdocOutputMissing { path: PathBuf },
+    DoctestsFailed { error: CommandError },
+    DoctestsNotExecuted,
+    CargoBuildFailed { error: CommandError },
+    CargoArtifactMissing { path: PathBuf },
+    WritingSnapshotFailed { dest: ReportDestination, error: std::io::Error },
+    EmptyBuiltinDirectory { target: String, name: String },
+    DuplicateBuiltinLibrary { target: String, name: String, library: String },
+    RustdocJsonOutputInvalid { path: PathBuf, error: serde_json::Error },
 }
 
 impl Error {
@@ -69,6 +95,31 @@ pub(crate) fn code(&self) -> u8 {
             Error::LinkerArgsError { .. } => 24,
             Error::RunningSampleProgramFailed { .. } => 25,
             Error::SampleProgramOutputWrong { .. } => 26,
+            Error::InvalidCliArguments { .. } => 27,
+            Error::WritingReportFailed { .. } => 28,
+            Error::TargetsDataReadFailed { .. } => 29,
+            Error::TargetsDataParseFailed { .. } => 30,
+            Error::InvalidTargetsData { .. } => 31,
+            Error::RunnerNotFound { .. } => 32,
+            Error::ManifestDiscoveryFailed { .. } => 33,
+            Error::ManifestReadFailed { .. } => 34,
+            Error::ManifestParseFailed { .. } => 35,
+            Error::InvalidManifest { .. } => 36,
+            Error::InstalledFileMissing { .. } => 37,
+            Error::InstalledFileReadFailed { .. } => 38,
+            Error::InstalledFileModeMismatch { .. } => 39,
+            Error::InstalledFileHashMismatch { .. } => 40,
+            Error::UnmanagedFile { .. } => 41,
+            Error::RustdocFailed { .. } => 42,
+            Error::RustdocOutputMissing { .. } => 43,
+            Error::DoctestsFailed { .. } => 44,
+            Error::DoctestsNotExecuted => 45,
+            Error::CargoBuildFailed { .. } => 46,
+            Error::CargoArtifactMissing { .. } => 47,
+            Error::WritingSnapshotFailed { .. } => 48,
+            Error::EmptyBuiltinDirectory { .. } => 49,
+            Error::DuplicateBuiltinLibrary { .. } => 50,
+            Error::RustdocJsonOutputInvalid { .. } => 51,
         }
     }
 }
@@ -99,6 +150,31 @@ fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
             Error::LinkerArgsError { .. } => None,
             Error::RunningSampleProgramFailed { error, .. } => Some(error),
             Error::SampleProgramOutputWrong { .. } => None,
+            Error::InvalidCliArguments { .. } => None,
+            Error::WritingReportFailed { error, .. } => Some(error),
+            Error::TargetsDataReadFailed { error, .. } => Some(error),
+            Error::TargetsDataParseFailed { error, .. } => Some(error),
+            Error::InvalidTargetsData { .. } => None,
+            Error::RunnerNotFound { error, .. } => Some(error),
+            Error::ManifestDiscoveryFailed { error, .. } => Some(error),
+            Error::ManifestReadFailed { error, .. } => Some(error),
+            Error::ManifestParseFailed { error, .. } => Some(error),
+            Error::InvalidManifest { .. } => None,
+            Error::InstalledFileMissing { .. } => None,
+            Error::InstalledFileReadFailed { error, .. } => Some(error),
+            Error::InstalledFileModeMismatch { .. } => None,
+            Error::InstalledFileHashMismatch { .. } => None,
+            Error::UnmanagedFile { .. } => None,
+            Error::RustdocFailed { error } => Some(error),
+            Error::RustdocOutputMissing { .. } => None,
+            Error::DoctestsFailed { error } => Some(error),
+            Error::DoctestsNotExecuted => None,
+            Error::CargoBuildFailed { error } => Some(error),
+            Error::CargoArtifactMissing { .. } => None,
+            Error::WritingSnapshotFailed { error, .. } => Some(error),
+            Error::EmptyBuiltinDirectory { .. } => None,
+            Error::DuplicateBuiltinLibrary { .. } => None,
+            Error::RustdocJsonOutputInvalid { error, .. } => Some(error),
         }
     }
 }
@@ -194,6 +270,90 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                     "sample program {name} should have produced {expected:?}, actually produced {found:?}"
                 )
             }
+            Error::InvalidCliArguments { message } => {
+                write!(f, "invalid command line arguments: {message}")
+            }
+            Error::WritingReportFailed { dest, .. } => {
+                write!(f, "failed to write the report to {dest}")
+            }
+            Error::TargetsDataReadFailed { path, .. } => {
+                write!(f, "failed to read the target data file {}", path.display())
+            }
+            Error::TargetsDataParseFailed { path, .. } => {
+                write!(f, "failed to parse the target data file {}", path.display())
+            }
+            Error::InvalidTargetsData { path, target, reason } => {
+                write!(f, "invalid data for target {target} in {}: {reason}", path.display())
+            }
+            Error::RunnerNotFound { target, name, .. } => {
+                write!(f, "runner {name} for target {target} not found on the system")
+            }
+            Error::ManifestDiscoveryFailed { path, .. } => {
+                write!(f, "failed to list the contents of {}", path.display())
+            }
+            Error::ManifestReadFailed { path, .. } => {
+                write!(f, "failed to read the package manifest {}", path.display())
+            }
+            Error::ManifestParseFailed { path, .. } => {
+                write!(f, "failed to parse the package manifest {}", path.display())
+            }
+            Error::InvalidManifest { path, reason } => {
+                write!(f, "invalid package manifest {}: {reason}", path.display())
+            }
+            Error::InstalledFileMissing { package, path } => {
+                write!(f, "file {path} of package {package} is not installed")
+            }
+            Error::InstalledFileReadFailed { path, .. } => {
+                write!(f, "failed to read the installed file {}", path.display())
+            }
+            Error::InstalledFileModeMismatch { package, path, expected, found } => {
+                write!(
+                    f,
+                    "file {path} of package {package} should have mode {expected:o}, found {found:o}"
+                )
+            }
+            Error::InstalledFileHashMismatch { package, path } => {
+                write!(f, "file {path} of package {package} was modified after installation")
+            }
+            Error::UnmanagedFile { package, prefix, path } => {
+                write!(
+                    f,
+                    "file {path} is not part of any package, \
+                     but it is inside {prefix} (managed by package {package})"
+                )
+            }
+            Error::RustdocFailed { .. } => {
+                write!(f, "rustdoc failed to document the sample crate")
+            }
+            Error::RustdocOutputMissing { path } => {
+                write!(f, "rustdoc did not generate {} for the sample crate", path.display())
+            }
+            Error::DoctestsFailed { .. } => {
+                write!(f, "rustdoc failed to run the doctests of the sample crate")
+            }
+            Error::DoctestsNotExecuted => {
+                write!(f, "rustdoc did not execute the doctests of the sample crate")
+            }
+            Error::CargoBuildFailed { .. } => {
+                write!(f, "cargo failed to build the sample workspace")
+            }
+            Error::CargoArtifactMissing { path } => {
+                write!(f, "cargo did not produce {} for the sample workspace", path.display())
+            }
+            Error::WritingSnapshotFailed { dest, .. } => {
+                write!(f, "failed to write the environment snapshot to {dest}")
+            }
+            Error::EmptyBuiltinDirectory { target, name } => {
+                write!(f, "builtin directory {name} of target {target} contains no libraries")
+            }
+            Error::DuplicateBuiltinLibrary { target, name, library } => write!(
+                f,
+                "there are conflicting copies of library {library} in builtin directory {name} \
+                 of target {target}"
+            ),
+            Error::RustdocJsonOutputInvalid { path, .. } => {
+                write!(f, "rustdoc generated invalid JSON documentation in {}", path.display())
+            }
         }
     }
 }
@@ -6,9 +6,11 @@
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use crate::diagnostics::{target_group, Diagnostics};
 use crate::error::{Error, LinkerArgsErrorKind};
 use crate::report::Reporter;
-use crate::targets::Target;
+use crate::snapshot::{CCompilerSnapshot, LinkersSnapshot, PathSnapshot, SnapshotError};
+use crate::targets::{Target, TargetSpec};
 use crate::utils::{find_binary_in_path, run_command};
 use crate::Environment;
 
@@ -16,10 +18,25 @@
 
 use argparse::LinkerArg;
 
+/// The group of the checks of the bundled linker.
+const GROUP: &str = "bundled linker";
+
 /// The linker arg we ask the C compiler to add, to check it can add arbitrary
 /// arguments.
 const RANDOM_LINKER_ARG: &str = "--rand456256146871864165842156=xyz";
 
+/// The arguments always given to a bare-metal GCC driver: we provide our own
+/// entry point, and newlib's `nosys` specs stub out the system calls.
+const BARE_METAL_GCC_ARGS: &[&str] = &["-nostartfiles", "--specs=nosys.specs"];
+
+/// The C program we compile to check a C compiler targeting an operating
+/// system.
+const HOSTED_TEST_PROGRAM: &str = r#"int main(void) { return 0; }"#;
+
+/// The C program we compile to check a bare-metal C compiler. There are no
+/// startup files, so the program has to provide the entry point itself.
+const BARE_METAL_TEST_PROGRAM: &str = r#"void _start(void) { for (;;) {} }"#;
+
 /// What kind of C compiler does a target require
 #[derive(Debug)]
 pub enum Linker {
@@ -28,7 +45,19 @@ pub enum Linker {
     /// The system's native C compiler is required
     HostCC,
     /// Some kind of cross compiler, with one of the given target prefixes
-    CrossCC(&'static [&'static str]),
+    CrossCC(Vec<String>),
+    /// An optional bare-metal GCC driver (like `arm-none-eabi-gcc`), with one
+    /// of the given target prefixes and the given target-specific arguments
+    /// (like `-mcpu=cortex-m4`). If none is installed the bundled linker is
+    /// used instead.
+    BareMetalGCC { prefixes: Vec<String>, args: Vec<String> },
+}
+
+/// The C compiler found for a target, along with the extra arguments it needs.
+#[derive(Debug)]
+pub(crate) struct CCompiler {
+    pub(crate) path: PathBuf,
+    pub(crate) args: Vec<String>,
 }
 
 /// Finds a system C compiler for each target and determines what flags should
@@ -38,104 +67,278 @@ pub enum Linker {
 /// each target's rustflags field.
 pub(crate) fn check_and_add_rustflags(
     reporter: &dyn Reporter,
+    diagnostics: &Diagnostics<'_>,
     environment: &Environment,
     sysroot: &Path,
-    targets: &mut [Target],
+    targets: &mut Vec<Target>,
 ) -> Result<(), Error> {
     // Step 1. Check we have ld.lld available
-    let _rust_lld_path = find_bundled_lld(reporter, sysroot)?;
-    let lld_bin = find_bundled_lld_wrapper(reporter, sysroot)?;
+    let lld_bin = diagnostics.check(GROUP, || {
+        let _rust_lld_path = find_bundled_lld(reporter, sysroot)?;
+        find_bundled_lld_wrapper(reporter, sysroot)
+    })?;
+    let Some(lld_bin) = lld_bin else {
+        // No target can be checked without the bundled linker: it's used directly by the targets
+        // not needing a C compiler, and the C compilers are configured to use it.
+        targets.clear();
+        return Ok(());
+    };
     let lld_dir = lld_bin.parent().expect("ld.lld should be a in a directory");
 
-    // Step 2. Check the C compiler works on each target that needs one
+    // Step 2. Check the C compiler works on each target that needs one, skipping the remaining
+    // checks of the targets where it doesn't.
     // 2a. We loop through the targets
-    'target_loop: for target in targets {
-        let prefix_list: &[&str] = match target.linker {
-            Linker::BundledLld => {
-                reporter
-                    .skipped(&format!("Target `{}` does not require a C compiler", target.triple));
-                continue 'target_loop;
-            }
-            Linker::HostCC => &[""],
-            Linker::CrossCC(list) => list,
-        };
-        // 2b. We loop through the prefixes used on this target (e.g. "arm-unknown-none-")
-        for cc_prefix in prefix_list {
-            // 2c. We loop through the things we know C compilers can be called
-            'cc_loop: for compiler_kind in ["cc", "gcc", "clang"] {
-                let mut cc_args = Vec::new();
-                // 2d. We keep trying until we get a set of linker arguments are are happy with
-                //     or we run out of flags to give the C compiler
-                'arg_loop: loop {
-                    let temp_dir = tempfile::tempdir().map_err(|error| {
-                        Error::TemporaryCompilationDirectoryCreationFailed { error }
-                    })?;
-                    let compiler_name = format!("{cc_prefix}{compiler_kind}");
-                    let cc_result = check_system_compiler(
-                        environment,
-                        target.triple,
-                        &compiler_name,
-                        lld_dir,
-                        temp_dir.path(),
-                        &cc_args,
-                    );
-                    match cc_result {
-                        Ok((_path, linker_args)) => {
-                            if std::env::var("FST_PRINT_DETAILED_ARGS").is_ok() {
-                                reporter.note(&format!(
-                                    "Target `{}`, detected args `{:?}`",
-                                    target.triple, &linker_args
-                                ));
-                            }
+    let mut checked = Vec::new();
+    for mut target in targets.drain(..) {
+        let group = target_group(&target.triple);
+        let result = diagnostics
+            .check(&group, || find_c_compiler(reporter, environment, lld_dir, &mut target))?;
+        if result.is_some() {
+            checked.push(target);
+        }
+    }
+    *targets = checked;
 
-                            match linker_args_ok(
-                                target.triple,
-                                linker_args.iter().map(|s| s.as_str()),
-                                &mut cc_args,
-                            ) {
-                                Ok(true) => {
-                                    // Looks good to go
-                                }
-                                Ok(false) => {
-                                    // Give it another go with some new arguments
-                                    continue 'arg_loop;
-                                }
-                                Err(e) => {
-                                    // Try another compiler
-                                    if std::env::var("FST_PRINT_DETAILED_ERRORS").is_ok() {
-                                        reporter
-                                            .note(&format!("`{compiler_name}` failed with {e}"));
-                                    }
-                                    continue 'cc_loop;
-                                }
-                            }
-                            reporter.success(&format!(
-                                "Found C compiler `{}` for target `{}`",
-                                compiler_name, target.triple
+    Ok(())
+}
+
+/// Finds a working C compiler for the target (if it needs one), adding the flags needed to use it
+/// to the target's rustflags.
+fn find_c_compiler(
+    reporter: &dyn Reporter,
+    environment: &Environment,
+    lld_dir: &Path,
+    target: &mut Target,
+) -> Result<(), Error> {
+    let Some(search) = CompilerSearch::new(&target.linker) else {
+        reporter.skipped(&format!("Target `{}` does not require a C compiler", target.triple));
+        return Ok(());
+    };
+    let mut any_compiler_found = false;
+    // 2b. We loop through the prefixes used on this target (e.g. "arm-unknown-none-")
+    for cc_prefix in &search.prefixes {
+        // 2c. We loop through the things we know C compilers can be called
+        'cc_loop: for compiler_kind in search.kinds {
+            let mut cc_args = search.base_args.clone();
+            // 2d. We keep trying until we get a set of linker arguments are are happy with
+            //     or we run out of flags to give the C compiler
+            'arg_loop: loop {
+                let temp_dir = tempfile::tempdir().map_err(|error| {
+                    Error::TemporaryCompilationDirectoryCreationFailed { error }
+                })?;
+                let compiler_name = format!("{cc_prefix}{compiler_kind}");
+                let cc_result = check_system_compiler(
+                    environment,
+                    &target.triple,
+                    &compiler_name,
+                    lld_dir,
+                    temp_dir.path(),
+                    search.test_program,
+                    &cc_args,
+                );
+                if !matches!(cc_result, Err(Error::CCompilerNotFound { .. })) {
+                    any_compiler_found = true;
+                }
+                match cc_result {
+                    Ok((cc_path, linker_args)) => {
+                        if std::env::var("FST_PRINT_DETAILED_ARGS").is_ok() {
+                            reporter.note(&format!(
+                                "Target `{}`, detected args `{:?}`",
+                                target.triple, &linker_args
                             ));
-                            target.rustflags.push(format!("-Clinker={compiler_name}"));
-                            for cc_arg in cc_args {
-                                target.rustflags.push(format!("-Clink-arg={cc_arg}"));
-                            }
-                            // All done with this target
-                            continue 'target_loop;
                         }
-                        Err(e) => {
-                            // Try again until we run out of compilers
-                            if std::env::var("FST_PRINT_DETAILED_ERRORS").is_ok() {
-                                reporter.note(&format!("`{compiler_name}` failed with {e}"));
+
+                        match linker_args_ok(
+                            &target.triple,
+                            linker_args.iter().map(|s| s.as_str()),
+                            &mut cc_args,
+                        ) {
+                            Ok(true) => {
+                                // Looks good to go
+                            }
+                            Ok(false) => {
+                                // Give it another go with some new arguments
+                                continue 'arg_loop;
+                            }
+                            Err(e) => {
+                                // Try another compiler
+                                if std::env::var("FST_PRINT_DETAILED_ERRORS").is_ok() {
+                                    reporter.note(&format!("`{compiler_name}` failed with {e}"));
+                                }
+                                continue 'cc_loop;
                             }
-                            // Try another compiler
-                            continue 'cc_loop;
                         }
+                        reporter.success(&format!(
+                            "Found C compiler `{}` for target `{}`",
+                            compiler_name, target.triple
+                        ));
+                        target.rustflags.push(format!("-Clinker={compiler_name}"));
+                        if let Linker::BareMetalGCC { .. } = target.linker {
+                            // Bare-metal targets use the bundled linker by default, so make
+                            // sure GCC keeps using it rather than the toolchain's own `ld`.
+                            target.rustflags.push("-Clink-arg=-fuse-ld=lld".into());
+                            target.rustflags.push(format!("-Clink-arg=-B{}", lld_dir.display()));
+                        }
+                        for cc_arg in &cc_args {
+                            target.rustflags.push(format!("-Clink-arg={cc_arg}"));
+                        }
+                        target.c_compiler = Some(CCompiler { path: cc_path, args: cc_args });
+                        // All done with this target
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        // Try again until we run out of compilers
+                        if std::env::var("FST_PRINT_DETAILED_ERRORS").is_ok() {
+                            reporter.note(&format!("`{compiler_name}` failed with {e}"));
+                        }
+                        // Try another compiler
+                        continue 'cc_loop;
                     }
                 }
             }
         }
-        return Err(Error::SuitableCCompilerNotFound { target: target.triple.to_owned() });
     }
+    if let (Linker::BareMetalGCC { .. }, false) = (&target.linker, any_compiler_found) {
+        let names = search.compiler_names().map(|n| format!("`{n}`")).collect::<Vec<_>>();
+        reporter.skipped(&format!(
+            "Target `{}` can use a bare-metal GCC toolchain, but none was found (looked for {})",
+            target.triple,
+            names.join(", ")
+        ));
+        return Ok(());
+    }
+    Err(Error::SuitableCCompilerNotFound { target: target.triple.clone() })
+}
 
-    Ok(())
+/// Where to look for the C compiler of a target, and how to check it.
+struct CompilerSearch<'a> {
+    /// The target prefixes of the C compiler (e.g. "arm-none-eabi-")
+    prefixes: Vec<&'a str>,
+    /// The names C compilers can be called, after the prefix
+    kinds: &'static [&'static str],
+    /// The arguments the C compiler always needs
+    base_args: Vec<String>,
+    /// The C program compiled to check the C compiler
+    test_program: &'static str,
+}
+
+impl<'a> CompilerSearch<'a> {
+    /// Returns `None` if the linker doesn't require a C compiler.
+    fn new(linker: &'a Linker) -> Option<Self> {
+        let prefixes = |list: &'a [String]| list.iter().map(|prefix| prefix.as_str()).collect();
+        match linker {
+            Linker::BundledLld => None,
+            Linker::HostCC => Some(CompilerSearch {
+                prefixes: vec![""],
+                kinds: &["cc", "gcc", "clang"],
+                base_args: Vec::new(),
+                test_program: HOSTED_TEST_PROGRAM,
+            }),
+            Linker::CrossCC(list) => Some(CompilerSearch {
+                prefixes: prefixes(list),
+                kinds: &["cc", "gcc", "clang"],
+                base_args: Vec::new(),
+                test_program: HOSTED_TEST_PROGRAM,
+            }),
+            Linker::BareMetalGCC { prefixes: list, args } => Some(CompilerSearch {
+                prefixes: prefixes(list),
+                kinds: &["gcc"],
+                base_args: BARE_METAL_GCC_ARGS
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .chain(args.iter().cloned())
+                    .collect(),
+                test_program: BARE_METAL_TEST_PROGRAM,
+            }),
+        }
+    }
+
+    /// All the C compiler names to try, in order.
+    fn compiler_names(&self) -> impl Iterator<Item = String> + '_ {
+        self.prefixes
+            .iter()
+            .flat_map(|prefix| self.kinds.iter().map(move |kind| format!("{prefix}{kind}")))
+    }
+}
+
+/// Gathers what the linker checks look at, for the environment snapshot.
+///
+/// Every C compiler that could be used for the given targets is probed, rather than stopping at
+/// the first one that works.
+pub(crate) fn snapshot(
+    environment: &Environment,
+    sysroot: &Path,
+    targets: &[TargetSpec],
+) -> LinkersSnapshot {
+    let lld_wrapper = bundled_lld_wrapper_path(sysroot);
+    let lld_dir = lld_wrapper.parent().expect("ld.lld should be a in a directory");
+
+    let mut c_compilers = Vec::new();
+    for target in targets {
+        let Some(search) = CompilerSearch::new(&target.linker) else { continue };
+        for name in search.compiler_names() {
+            c_compilers.push(snapshot_compiler(
+                environment,
+                &target.triple,
+                name,
+                lld_dir,
+                &search,
+            ));
+        }
+    }
+
+    LinkersSnapshot {
+        bundled_lld: PathSnapshot::new(bundled_lld_path(sysroot)),
+        bundled_lld_wrapper: PathSnapshot::new(lld_wrapper.clone()),
+        c_compilers,
+    }
+}
+
+fn snapshot_compiler(
+    environment: &Environment,
+    target: &str,
+    name: String,
+    lld_dir: &Path,
+    search: &CompilerSearch<'_>,
+) -> CCompilerSnapshot {
+    let mut snapshot = CCompilerSnapshot {
+        target: target.into(),
+        path: None,
+        version: None,
+        args: search.base_args.clone(),
+        linker_args: None,
+        error: None,
+        name,
+    };
+    let Ok(path) = find_binary_in_path(environment, &snapshot.name) else { return snapshot };
+    snapshot.version = run_command(Command::new(&path).arg("--version"))
+        .ok()
+        .and_then(|output| output.stdout.lines().next().map(String::from));
+    snapshot.path = Some(path);
+
+    let result = tempfile::tempdir()
+        .map_err(|error| Error::TemporaryCompilationDirectoryCreationFailed { error })
+        .and_then(|temp_dir| {
+            check_system_compiler(
+                environment,
+                target,
+                &snapshot.name,
+                lld_dir,
+                temp_dir.path(),
+                search.test_program,
+                &search.base_args,
+            )
+        });
+    match result {
+        Ok((_, linker_args)) => {
+            let verdict =
+                linker_args_ok(target, linker_args.iter().map(|s| s.as_str()), &mut snapshot.args);
+            snapshot.error = verdict.err().map(|error| SnapshotError::new(&error));
+            snapshot.linker_args = Some(linker_args);
+        }
+        Err(error) => snapshot.error = Some(SnapshotError::new(&error)),
+    }
+    snapshot
 }
 
 /// Look at the arguments given to the linker.
@@ -185,6 +388,8 @@ fn linker_args_ok<'a, I>(
             LinkerArg::PushState => {}
             LinkerArg::PopState => {}
             LinkerArg::FixCortexA53_843419 => {}
+            LinkerArg::StartGroup => {}
+            LinkerArg::EndGroup => {}
             LinkerArg::Unknown(s) if s == RANDOM_LINKER_ARG => {
                 // This one is allowed, because we added it deliberately
             }
@@ -229,6 +434,7 @@ fn check_system_compiler(
     compiler_name: &str,
     lld_dir: &Path,
     temp_dir: &Path,
+    c_source: &str,
     extra_args: &[String],
 ) -> Result<(PathBuf, Vec<String>), Error> {
     let cc_path = find_binary_in_path(environment, &compiler_name)
@@ -236,7 +442,7 @@ fn check_system_compiler(
 
     // Part 1. Check with the real ld.lld - can we make a binary?
 
-    cross_compile_test_program(&cc_path, lld_dir, temp_dir, extra_args)?;
+    cross_compile_test_program(&cc_path, lld_dir, temp_dir, c_source, extra_args)?;
 
     // Part 2. Make a fake linker, and get GCC to try and use it. What arguments
     // does it give our fake linker?
@@ -244,7 +450,7 @@ fn check_system_compiler(
     let args_file = make_fake_linker(temp_dir)?;
 
     let linker_args =
-        check_compiler_linker_args(target, &cc_path, temp_dir, &args_file, extra_args)?;
+        check_compiler_linker_args(target, &cc_path, temp_dir, &args_file, c_source, extra_args)?;
 
     Ok((cc_path, linker_args))
 }
@@ -260,11 +466,9 @@ fn cross_compile_test_program(
     cc_path: &Path,
     lld_dir: &Path,
     temp_dir: &Path,
+    c_source: &str,
     extra_args: &[String],
 ) -> Result<(), Error> {
-    // We need some C source code,
-    let c_source = r#"int main(void) { return 0; }"#;
-
     // We need a temp directory we can save the output file to
     let source_file = temp_dir.join("input.c");
     let object_file = temp_dir.join("output.bin");
@@ -367,6 +571,7 @@ fn check_compiler_linker_args(
     cc_path: &Path,
     temp_dir: &Path,
     args_file_path: &Path,
+    c_source: &str,
     extra_args: &[String],
 ) -> Result<Vec<String>, Error> {
     // Ensure this file doesn't already exist
@@ -377,7 +582,7 @@ fn check_compiler_linker_args(
     extra_args.push(format!("-Wl,{RANDOM_LINKER_ARG}"));
 
     // compile a sample C program, but using our fake linker
-    cross_compile_test_program(cc_path, temp_dir, temp_dir, &extra_args)?;
+    cross_compile_test_program(cc_path, temp_dir, temp_dir, c_source, &extra_args)?;
 
     // see what the fake linker wrote
     let Ok(args_file) = std::fs::read(args_file_path) else {
@@ -415,14 +620,23 @@ fn check_compiler_linker_args(
     Ok(args)
 }
 
-/// Look for the bundled `rust-lld` program in the given sysroot.
-fn find_bundled_lld(reporter: &dyn Reporter, sysroot: &Path) -> Result<PathBuf, Error> {
-    let path = sysroot
+fn bundled_lld_path(sysroot: &Path) -> PathBuf {
+    sysroot.join("lib").join("rustlib").join(env!("SELFTEST_TARGET")).join("bin").join("rust-lld")
+}
+
+fn bundled_lld_wrapper_path(sysroot: &Path) -> PathBuf {
+    sysroot
         .join("lib")
         .join("rustlib")
         .join(env!("SELFTEST_TARGET"))
         .join("bin")
-        .join("rust-lld");
+        .join("gcc-ld")
+        .join("ld.lld")
+}
+
+/// Look for the bundled `rust-lld` program in the given sysroot.
+fn find_bundled_lld(reporter: &dyn Reporter, sysroot: &Path) -> Result<PathBuf, Error> {
+    let path = bundled_lld_path(sysroot);
 
     if path.is_file() {
         reporter.success(&format!("bundled linker detected"));
@@ -434,13 +648,7 @@ fn find_bundled_lld(reporter: &dyn Reporter, sysroot: &Path) -> Result<PathBuf,
 
 /// Look for the bundled `ld.lld` linker wrapper program in the given sysroot.
 fn find_bundled_lld_wrapper(reporter: &dyn Reporter, sysroot: &Path) -> Result<PathBuf, Error> {
-    let path = sysroot
-        .join("lib")
-        .join("rustlib")
-        .join(env!("SELFTEST_TARGET"))
-        .join("bin")
-        .join("gcc-ld")
-        .join("ld.lld");
+    let path = bundled_lld_wrapper_path(sysroot);
 
     if path.is_file() {
         reporter.success(&format!("bundled linker-wrapper detected"));
@@ -521,6 +729,7 @@ fn test_c_compiler() {
             &test_cc,
             Path::new("/some/fake/lld/path"),
             temp_dir.path(),
+            HOSTED_TEST_PROGRAM,
             &[],
         )
         .expect("Working C compiler");
@@ -538,6 +747,7 @@ fn test_c_compiler_missing() {
             "missing-cc",
             Path::new("/some/fake/lld/path"),
             temp_dir.path(),
+            HOSTED_TEST_PROGRAM,
             &[],
         );
         match result {
@@ -556,6 +766,154 @@ fn test_c_compiler_missing() {
         }
     }
 
+    // Fake bare-metal GCC driver, invoking the `ld.lld` it's pointed to like the real one would.
+    const BARE_METAL_GCC_SOURCE: &str = r#"
+        fn main() {
+            let args = std::env::args().skip(1).collect::<Vec<_>>();
+            for expected in ["-fuse-ld=lld", "-nostartfiles", "--specs=nosys.specs", "-mthumb"] {
+                assert!(args.iter().any(|arg| arg == expected), "missing {expected}");
+            }
+            let input = args.iter().find(|arg| arg.ends_with(".c")).unwrap();
+            assert!(std::fs::read_to_string(input).unwrap().contains("_start"));
+
+            let lld_dir = &args[args.iter().position(|arg| arg == "-B").unwrap() + 1];
+            let output = &args[args.iter().position(|arg| arg == "-o").unwrap() + 1];
+            let status = std::process::Command::new(format!("{lld_dir}/ld.lld"))
+                .args(["-X", "-o", output, "--start-group", "-lgcc", "-lc", "--end-group"])
+                .args(args.iter().filter_map(|arg| arg.strip_prefix("-Wl,")))
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+    "#;
+
+    fn bare_metal_target() -> Target {
+        Target {
+            spec: TargetSpec {
+                triple: "thumbv7em-none-eabi".into(),
+                std: false,
+                linker: Linker::BareMetalGCC {
+                    prefixes: vec!["arm-none-eabi-".into()],
+                    args: vec!["-mthumb".into()],
+                },
+                runner: None,
+            },
+            builtin_dirs: Vec::new(),
+            rustflags: Vec::new(),
+            c_compiler: None,
+            runner: None,
+        }
+    }
+
+    #[test]
+    fn test_bare_metal_gcc() {
+        let utils = TestUtils::new();
+        utils.bin("rust-lld").for_target(env!("SELFTEST_TARGET")).create();
+        let lld_wrapper = utils.bin("gcc-ld/ld.lld").for_target(env!("SELFTEST_TARGET")).create();
+        let gcc = utils.bin("arm-none-eabi-gcc").program_source(BARE_METAL_GCC_SOURCE).create();
+
+        let mut targets = vec![bare_metal_target()];
+        let diagnostics = utils.diagnostics();
+        check_and_add_rustflags(
+            utils.reporter(),
+            &diagnostics,
+            utils.env(),
+            utils.sysroot(),
+            &mut targets,
+        )
+        .unwrap();
+
+        let lld_dir = lld_wrapper.parent().unwrap().display().to_string();
+        assert_eq!(
+            [
+                "-Clinker=arm-none-eabi-gcc".to_string(),
+                "-Clink-arg=-fuse-ld=lld".into(),
+                format!("-Clink-arg=-B{lld_dir}"),
+                "-Clink-arg=-nostartfiles".into(),
+                "-Clink-arg=--specs=nosys.specs".into(),
+                "-Clink-arg=-mthumb".into(),
+            ],
+            targets[0].rustflags.as_slice()
+        );
+        let c_compiler = targets[0].c_compiler.as_ref().unwrap();
+        assert_eq!(gcc, c_compiler.path);
+        assert_eq!(["-nostartfiles", "--specs=nosys.specs", "-mthumb"], c_compiler.args.as_slice());
+        utils.assert_report_success(
+            "Found C compiler `arm-none-eabi-gcc` for target `thumbv7em-none-eabi`",
+        );
+    }
+
+    #[test]
+    fn test_bare_metal_gcc_missing() {
+        let utils = TestUtils::new();
+        utils.bin("rust-lld").for_target(env!("SELFTEST_TARGET")).create();
+        utils.bin("gcc-ld/ld.lld").for_target(env!("SELFTEST_TARGET")).create();
+
+        let mut targets = vec![bare_metal_target()];
+        let diagnostics = utils.diagnostics();
+        check_and_add_rustflags(
+            utils.reporter(),
+            &diagnostics,
+            utils.env(),
+            utils.sysroot(),
+            &mut targets,
+        )
+        .unwrap();
+
+        assert!(targets[0].rustflags.is_empty());
+        assert!(targets[0].c_compiler.is_none());
+        utils.assert_report_skipped(
+            "Target `thumbv7em-none-eabi` can use a bare-metal GCC toolchain, but none was found \
+             (looked for `arm-none-eabi-gcc`)",
+        );
+    }
+
+    #[test]
+    fn test_bare_metal_gcc_broken() {
+        let utils = TestUtils::new();
+        utils.bin("rust-lld").for_target(env!("SELFTEST_TARGET")).create();
+        utils.bin("gcc-ld/ld.lld").for_target(env!("SELFTEST_TARGET")).create();
+        utils.bin("arm-none-eabi-gcc").exit(1).create();
+
+        let mut targets = vec![bare_metal_target()];
+        let diagnostics = utils.diagnostics();
+        match check_and_add_rustflags(
+            utils.reporter(),
+            &diagnostics,
+            utils.env(),
+            utils.sysroot(),
+            &mut targets,
+        ) {
+            Err(Error::SuitableCCompilerNotFound { target }) => {
+                assert_eq!("thumbv7em-none-eabi", target);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_bundled_lld_missing() {
+        let utils = TestUtils::new();
+        let mut no_c_compiler = bare_metal_target();
+        no_c_compiler.spec.triple = "aarch64-unknown-none".into();
+        no_c_compiler.spec.linker = Linker::BundledLld;
+
+        let mut targets = vec![no_c_compiler, bare_metal_target()];
+        let diagnostics = Diagnostics::new(utils.reporter(), false);
+        check_and_add_rustflags(
+            utils.reporter(),
+            &diagnostics,
+            utils.env(),
+            utils.sysroot(),
+            &mut targets,
+        )
+        .unwrap();
+
+        assert!(targets.is_empty());
+        utils.assert_report_error(15);
+        utils.assert_no_reports();
+    }
+
     #[test]
     fn test_make_fake_linker() {
         let temp_dir = tempfile::tempdir().expect("making temp dir");
@@ -55,6 +55,10 @@ pub enum LinkerArg<'a> {
     PopState,
     /// The `--fix-cortex-a53-843419` option
     FixCortexA53_843419,
+    /// The `--start-group` / `-(` option
+    StartGroup,
+    /// The `--end-group` / `-)` option
+    EndGroup,
 
     // Anything else goes here
     /// We didn't recognise this option
@@ -189,6 +193,14 @@ pub fn rationalise_linker_args<'a, I>(mut args_iter: I) -> Vec<LinkerArg<'a>>
             output.push(result);
         } else if let Some(result) = long_opt("fix-cortex-a53-843419", arg, || LinkerArg::FixCortexA53_843419) {
             output.push(result);
+        } else if let Some(result) = long_opt("start-group", arg, || LinkerArg::StartGroup) {
+            output.push(result);
+        } else if let Some(result) = short_opt('(', arg, || LinkerArg::StartGroup) {
+            output.push(result);
+        } else if let Some(result) = long_opt("end-group", arg, || LinkerArg::EndGroup) {
+            output.push(result);
+        } else if let Some(result) = short_opt(')', arg, || LinkerArg::EndGroup) {
+            output.push(result);
         } else if arg.starts_with("-") {
             output.push(LinkerArg::Unknown(arg));
         } else {
@@ -2,18 +2,27 @@
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
 mod binaries;
+mod cargo;
+mod cli;
 mod compile;
+mod diagnostics;
 mod error;
 mod linkers;
+mod manifests;
 mod report;
+mod runners;
+mod rustdoc;
+mod snapshot;
 mod targets;
 mod utils;
 
 #[cfg(test)]
 mod test_utils;
 
+use crate::cli::{Cli, CliCommand, ReportFormat};
+use crate::diagnostics::Diagnostics;
 use crate::error::Error;
-use crate::report::{Reporter, StderrReporter};
+use crate::report::{JsonReporter, JunitReporter, MultiReporter, Reporter, StderrReporter};
 use std::ffi::OsString;
 use std::path::PathBuf;
 
@@ -32,16 +41,32 @@ fn get_sysroot() -> Option<PathBuf> {
     Some(current_exe.parent()?.parent()?.to_path_buf())
 }
 
-fn main_inner(reporter: &dyn Reporter) -> Result<(), Error> {
+fn main_inner(
+    reporter: &dyn Reporter,
+    diagnostics: &Diagnostics<'_>,
+    cli: &Cli,
+) -> Result<(), Error> {
     let environment = Environment::gather();
 
     let sysroot = get_sysroot().ok_or(Error::NoSysroot)?;
     reporter.info(&format!("using sysroot {}", sysroot.display()));
 
-    binaries::check(reporter, &sysroot)?;
-    let mut targets = targets::check(reporter, &sysroot)?;
-    linkers::check_and_add_rustflags(reporter, &environment, &sysroot, &mut targets)?;
-    compile::check(reporter, &sysroot, &targets)?;
+    diagnostics.check("installed files", || manifests::check(reporter, &sysroot))?;
+    binaries::check(reporter, diagnostics, &sysroot)?;
+    let mut targets = targets::check(reporter, diagnostics, &sysroot)?;
+    linkers::check_and_add_rustflags(reporter, diagnostics, &environment, &sysroot, &mut targets)?;
+    runners::check_and_add_runners(
+        reporter,
+        diagnostics,
+        &environment,
+        &cli.runners,
+        &mut targets,
+    )?;
+    compile::check(reporter, diagnostics, &sysroot, &targets)?;
+
+    let host = targets.iter().find(|target| target.triple == env!("SELFTEST_TARGET"));
+    diagnostics.check("rustdoc", || rustdoc::check(reporter, &sysroot, host))?;
+    diagnostics.check("cargo", || cargo::check(reporter, &sysroot, host))?;
 
     for target in targets {
         if target.rustflags.is_empty() {
@@ -54,22 +79,70 @@ fn main_inner(reporter: &dyn Reporter) -> Result<(), Error> {
         }
     }
 
-    reporter.success("Ferrocene self-check completed!");
+    if diagnostics.first_error_code().is_none() {
+        reporter.success("Ferrocene self-check completed!");
+    }
     Ok(())
 }
 
 fn main() {
-    let reporter = if atty::is(atty::Stream::Stderr) {
+    let stderr_reporter = if atty::is(atty::Stream::Stderr) {
         StderrReporter::color()
     } else {
         StderrReporter::plain()
     };
 
-    match main_inner(&reporter) {
-        Ok(()) => {}
-        Err(err) => {
-            reporter.error(&err);
-            std::process::exit(err.code() as i32);
+    let cli = match cli::parse(std::env::args_os().skip(1)) {
+        Ok(CliCommand::Run(cli)) => cli,
+        Ok(CliCommand::Snapshot(cli)) => {
+            if let Err(err) = snapshot::run(&cli) {
+                exit_with_error(&stderr_reporter, err);
+            }
+            return;
+        }
+        Ok(CliCommand::Help) => {
+            eprint!("{}", cli::USAGE);
+            return;
         }
+        Err(err) => exit_with_error(&stderr_reporter, err),
+    };
+
+    let structured_reporter: Option<Box<dyn Reporter>> = match cli.report.clone() {
+        Some((format, dest)) => match dest.open() {
+            Ok(writer) => Some(match format {
+                ReportFormat::Json => Box::new(JsonReporter::new(writer, dest)),
+                ReportFormat::Junit => Box::new(JunitReporter::new(writer, dest)),
+            }),
+            Err(err) => exit_with_error(&stderr_reporter, err),
+        },
+        None => None,
+    };
+
+    let mut reporters: Vec<&dyn Reporter> = vec![&stderr_reporter];
+    if let Some(structured_reporter) = &structured_reporter {
+        reporters.push(structured_reporter.as_ref());
     }
+    let reporter = MultiReporter::new(reporters);
+
+    let diagnostics = Diagnostics::new(&reporter, !cli.no_fail_fast);
+    let result = main_inner(&reporter, &diagnostics, &cli);
+    if let Err(err) = &result {
+        reporter.error(err);
+    }
+    if cli.no_fail_fast {
+        reporter.summary(&diagnostics.groups());
+    }
+    if let Err(err) = reporter.finish() {
+        exit_with_error(&stderr_reporter, err);
+    }
+    // The exit code is always the one of the first error, even when the self-test kept going.
+    let exit_code = diagnostics.first_error_code().or(result.err().map(|err| err.code()));
+    if let Some(code) = exit_code {
+        std::process::exit(code as i32);
+    }
+}
+
+fn exit_with_error(reporter: &dyn Reporter, err: Error) -> ! {
+    reporter.error(&err);
+    std::process::exit(err.code() as i32);
 }
@@ -0,0 +1,474 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Verification of the installed files against the criticaltrust package manifests.
+//!
+//! Every Ferrocene package ships a manifest in `share/criticaltrust/ferrocene/<package>.json`,
+//! listing the files that are part of the package along with their hash and permissions. The
+//! manifests also list the "managed prefixes" of the package, directories whose whole content is
+//! owned by Ferrocene packages: any file inside them not listed in a manifest is unexpected.
+//!
+//! The signatures of the manifests are not verified here, only their contents.
+
+use crate::error::Error;
+use crate::report::Reporter;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+
+const SUPPORTED_MANIFEST_VERSION: u32 = 1;
+
+pub(crate) fn check(reporter: &dyn Reporter, sysroot: &Path) -> Result<(), Error> {
+    let manifests_dir = manifests_dir(sysroot);
+    if !manifests_dir.is_dir() {
+        reporter.skipped("verification of the installed files (no package manifests found)");
+        return Ok(());
+    }
+
+    let mut packages = Vec::new();
+    for path in find_manifests(&manifests_dir)? {
+        packages.push(load_manifest(&path)?);
+    }
+
+    for package in &packages {
+        for file in &package.files {
+            check_file(sysroot, package, file)?;
+        }
+        reporter.success(&format!(
+            "installed files of package {} match its manifest ({} files)",
+            package.package,
+            package.files.len()
+        ));
+    }
+
+    let known_files =
+        packages.iter().flat_map(|p| p.files.iter().map(|f| f.path.as_str())).collect();
+    for package in &packages {
+        for prefix in &package.managed_prefixes {
+            check_managed_prefix(sysroot, &known_files, package, prefix)?;
+        }
+    }
+    if packages.iter().any(|p| !p.managed_prefixes.is_empty()) {
+        reporter.success("no unexpected files in the directories managed by packages");
+    }
+
+    Ok(())
+}
+
+fn manifests_dir(sysroot: &Path) -> PathBuf {
+    sysroot.join("share").join("criticaltrust").join("ferrocene")
+}
+
+fn find_manifests(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let map_err = |error| Error::ManifestDiscoveryFailed { path: dir.into(), error };
+
+    let mut manifests = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(map_err)? {
+        let path = entry.map_err(map_err)?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            manifests.push(path);
+        }
+    }
+    manifests.sort();
+    Ok(manifests)
+}
+
+fn load_manifest(path: &Path) -> Result<Package, Error> {
+    let contents = std::fs::read(path)
+        .map_err(|error| Error::ManifestReadFailed { path: path.into(), error })?;
+    parse_manifest(path, &contents)
+}
+
+fn parse_manifest(path: &Path, contents: &[u8]) -> Result<Package, Error> {
+    let parse_err = |error| Error::ManifestParseFailed { path: path.into(), error };
+    let invalid = |reason: String| Error::InvalidManifest { path: path.into(), reason };
+
+    let manifest: PackageManifest = serde_json::from_slice(contents).map_err(parse_err)?;
+    if manifest.version != SUPPORTED_MANIFEST_VERSION {
+        return Err(invalid(format!("unsupported manifest version {}", manifest.version)));
+    }
+
+    // The signed payload is stored as a JSON string inside of the manifest.
+    let package: Package = serde_json::from_str(&manifest.signed.signed).map_err(parse_err)?;
+    for file in &package.files {
+        if !is_relative_to_sysroot(&file.path) {
+            return Err(invalid(format!("file path {} is outside of the sysroot", file.path)));
+        }
+        if BASE64.decode(&file.sha256).map(|hash| hash.len()) != Ok(32) {
+            return Err(invalid(format!("invalid SHA-256 hash for file {}", file.path)));
+        }
+    }
+    for prefix in &package.managed_prefixes {
+        if !is_relative_to_sysroot(prefix) {
+            return Err(invalid(format!("managed prefix {prefix} is outside of the sysroot")));
+        }
+    }
+
+    Ok(package)
+}
+
+fn is_relative_to_sysroot(path: &str) -> bool {
+    Path::new(path).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Checks that the installed file has the permissions and SHA-256 hash listed in the manifest.
+///
+/// The manifests don't record the size of the files, so it can't be compared separately: a file
+/// with a different size is still detected, as its hash won't match.
+fn check_file(sysroot: &Path, package: &Package, file: &PackageFile) -> Result<(), Error> {
+    let path = sysroot.join(&file.path);
+
+    let metadata = match std::fs::metadata(&path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        Ok(_) => {
+            return Err(Error::InstalledFileMissing {
+                package: package.package.clone(),
+                path: file.path.clone(),
+            });
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Err(Error::InstalledFileMissing {
+                package: package.package.clone(),
+                path: file.path.clone(),
+            });
+        }
+        Err(error) => return Err(Error::MetadataFetchFailed { path, error }),
+    };
+
+    if metadata.mode() != file.posix_mode {
+        return Err(Error::InstalledFileModeMismatch {
+            package: package.package.clone(),
+            path: file.path.clone(),
+            expected: file.posix_mode,
+            found: metadata.mode(),
+        });
+    }
+
+    let read_err = |error| Error::InstalledFileReadFailed { path: path.clone(), error };
+    let mut sha256 = Sha256::new();
+    std::io::copy(&mut File::open(&path).map_err(read_err)?, &mut sha256).map_err(read_err)?;
+
+    // The hash was already validated when parsing the manifest.
+    if BASE64.decode(&file.sha256).unwrap() != sha256.finalize().as_slice() {
+        return Err(Error::InstalledFileHashMismatch {
+            package: package.package.clone(),
+            path: file.path.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+fn check_managed_prefix(
+    sysroot: &Path,
+    known_files: &HashSet<&str>,
+    package: &Package,
+    prefix: &str,
+) -> Result<(), Error> {
+    let dir = sysroot.join(prefix);
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    let mut files = Vec::new();
+    list_files_recursive(&dir, &mut files)?;
+    files.sort();
+
+    for file in files {
+        let relative = file.strip_prefix(sysroot).unwrap();
+        let relative =
+            relative.to_str().ok_or_else(|| Error::NonUtf8Path { path: file.clone() })?;
+        if !known_files.contains(relative) {
+            return Err(Error::UnmanagedFile {
+                package: package.package.clone(),
+                prefix: prefix.into(),
+                path: relative.into(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn list_files_recursive(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let map_err = |error| Error::ManifestDiscoveryFailed { path: dir.into(), error };
+    for entry in std::fs::read_dir(dir).map_err(map_err)? {
+        let path = entry.map_err(map_err)?.path();
+        if path.is_dir() {
+            list_files_recursive(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct PackageManifest {
+    version: u32,
+    signed: SignedPayload,
+}
+
+#[derive(Deserialize)]
+struct SignedPayload {
+    signed: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Package {
+    package: String,
+    files: Vec<PackageFile>,
+    managed_prefixes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PackageFile {
+    path: String,
+    posix_mode: u32,
+    sha256: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestUtils;
+    use std::os::unix::fs::PermissionsExt;
+
+    struct ManifestBuilder<'a> {
+        utils: &'a TestUtils,
+        package: &'a str,
+        files: Vec<(&'a str, &'a [u8], u32)>,
+        managed_prefixes: Vec<&'a str>,
+    }
+
+    impl<'a> ManifestBuilder<'a> {
+        fn new(utils: &'a TestUtils, package: &'a str) -> Self {
+            Self { utils, package, files: Vec::new(), managed_prefixes: Vec::new() }
+        }
+
+        fn file(mut self, path: &'a str, contents: &'a [u8], mode: u32) -> Self {
+            self.files.push((path, contents, mode));
+            self
+        }
+
+        fn managed_prefix(mut self, prefix: &'a str) -> Self {
+            self.managed_prefixes.push(prefix);
+            self
+        }
+
+        /// Creates both the files listed in the manifest and the manifest itself.
+        fn create(self) {
+            let sysroot = self.utils.sysroot();
+            let mut files = Vec::new();
+            for (path, contents, mode) in &self.files {
+                let full_path = sysroot.join(path);
+                std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+                std::fs::write(&full_path, contents).unwrap();
+                std::fs::set_permissions(&full_path, PermissionsExt::from_mode(*mode)).unwrap();
+
+                files.push(serde_json::json!({
+                    "path": path,
+                    "posix-mode": 0o100000 | mode,
+                    "sha256": BASE64.encode(Sha256::digest(contents)),
+                    "needs-proxy": false,
+                }));
+            }
+            let package = serde_json::json!({
+                "product": "ferrocene",
+                "package": self.package,
+                "commit": "0000000",
+                "files": files,
+                "managed-prefixes": self.managed_prefixes,
+            });
+            self.utils.package_manifest(
+                self.package,
+                &serde_json::json!({
+                    "version": 1,
+                    "signed": { "signatures": [], "signed": package.to_string() },
+                })
+                .to_string(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_manifests() {
+        let utils = TestUtils::new();
+        check(utils.reporter(), utils.sysroot()).unwrap();
+        utils.assert_report_skipped(
+            "verification of the installed files (no package manifests found)",
+        );
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_valid_manifests() {
+        let utils = TestUtils::new();
+        ManifestBuilder::new(&utils, "rustc")
+            .file("bin/rustc", b"rustc", 0o755)
+            .file("lib/librustc_driver.so", b"driver", 0o644)
+            .create();
+        ManifestBuilder::new(&utils, "rust-std-x86_64-unknown-linux-gnu")
+            .file("lib/rustlib/x86_64-unknown-linux-gnu/lib/libstd.rlib", b"std", 0o644)
+            .managed_prefix("lib/rustlib/x86_64-unknown-linux-gnu/lib/")
+            .create();
+
+        check(utils.reporter(), utils.sysroot()).unwrap();
+        utils.assert_report_success("no unexpected files in the directories managed by packages");
+        utils
+            .assert_report_success("installed files of package rustc match its manifest (2 files)");
+        utils.assert_report_success(
+            "installed files of package rust-std-x86_64-unknown-linux-gnu match its manifest (1 files)",
+        );
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let utils = TestUtils::new();
+        ManifestBuilder::new(&utils, "rustc").file("bin/rustc", b"rustc", 0o755).create();
+        std::fs::remove_file(utils.sysroot().join("bin/rustc")).unwrap();
+
+        match check(utils.reporter(), utils.sysroot()) {
+            Err(Error::InstalledFileMissing { package, path }) => {
+                assert_eq!("rustc", package);
+                assert_eq!("bin/rustc", path);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_mode_mismatch() {
+        let utils = TestUtils::new();
+        ManifestBuilder::new(&utils, "rustc").file("bin/rustc", b"rustc", 0o755).create();
+        let path = utils.sysroot().join("bin/rustc");
+        std::fs::set_permissions(&path, PermissionsExt::from_mode(0o700)).unwrap();
+
+        match check(utils.reporter(), utils.sysroot()) {
+            Err(Error::InstalledFileModeMismatch { package, path, expected, found }) => {
+                assert_eq!("rustc", package);
+                assert_eq!("bin/rustc", path);
+                assert_eq!(0o100755, expected);
+                assert_eq!(0o100700, found);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_hash_mismatch() {
+        let utils = TestUtils::new();
+        ManifestBuilder::new(&utils, "rustc").file("bin/rustc", b"rustc", 0o755).create();
+        std::fs::write(utils.sysroot().join("bin/rustc"), b"tampered").unwrap();
+
+        match check(utils.reporter(), utils.sysroot()) {
+            Err(Error::InstalledFileHashMismatch { package, path }) => {
+                assert_eq!("rustc", package);
+                assert_eq!("bin/rustc", path);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_unmanaged_file() {
+        let utils = TestUtils::new();
+        ManifestBuilder::new(&utils, "rust-std-foo")
+            .file("lib/rustlib/foo/lib/libstd.rlib", b"std", 0o644)
+            .managed_prefix("lib/rustlib/foo/lib/")
+            .create();
+        // Files in managed prefixes owned by other packages are allowed.
+        ManifestBuilder::new(&utils, "rustc-dev-foo")
+            .file("lib/rustlib/foo/lib/librustc_driver.rlib", b"driver", 0o644)
+            .create();
+        std::fs::create_dir_all(utils.sysroot().join("lib/rustlib/foo/lib/nested")).unwrap();
+        std::fs::write(utils.sysroot().join("lib/rustlib/foo/lib/nested/libold.rlib"), b"")
+            .unwrap();
+
+        match check(utils.reporter(), utils.sysroot()) {
+            Err(Error::UnmanagedFile { package, prefix, path }) => {
+                assert_eq!("rust-std-foo", package);
+                assert_eq!("lib/rustlib/foo/lib/", prefix);
+                assert_eq!("lib/rustlib/foo/lib/nested/libold.rlib", path);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_manifest_invalid() {
+        let path = Path::new("rustc.json");
+        let manifest = |version: u32, package: serde_json::Value| {
+            serde_json::json!({
+                "version": version,
+                "signed": { "signatures": [], "signed": package.to_string() },
+            })
+            .to_string()
+        };
+        let package = |file_path: &str, sha256: &str, prefix: &str| {
+            serde_json::json!({
+                "product": "ferrocene",
+                "package": "rustc",
+                "commit": "0000000",
+                "files": [{
+                    "path": file_path,
+                    "posix-mode": 0o100644,
+                    "sha256": sha256,
+                    "needs-proxy": false,
+                }],
+                "managed-prefixes": [prefix],
+            })
+        };
+        let valid_hash = BASE64.encode([0; 32]);
+
+        let assert_invalid =
+            |contents: String, expected: &str| match parse_manifest(path, contents.as_bytes()) {
+                Err(Error::InvalidManifest { reason, .. }) => assert_eq!(expected, reason),
+                Err(other) => panic!("unexpected error: {other:?}"),
+                Ok(_) => panic!("manifest should be invalid"),
+            };
+        assert_invalid(
+            manifest(2, package("bin/rustc", &valid_hash, "lib/")),
+            "unsupported manifest version 2",
+        );
+        assert_invalid(
+            manifest(1, package("../bin/rustc", &valid_hash, "lib/")),
+            "file path ../bin/rustc is outside of the sysroot",
+        );
+        assert_invalid(
+            manifest(1, package("bin/rustc", "not base64!", "lib/")),
+            "invalid SHA-256 hash for file bin/rustc",
+        );
+        assert_invalid(
+            manifest(1, package("bin/rustc", &BASE64.encode([0; 16]), "lib/")),
+            "invalid SHA-256 hash for file bin/rustc",
+        );
+        assert_invalid(
+            manifest(1, package("bin/rustc", &valid_hash, "/lib/")),
+            "managed prefix /lib/ is outside of the sysroot",
+        );
+
+        assert!(parse_manifest(
+            path,
+            manifest(1, package("bin/rustc", &valid_hash, "lib/")).as_bytes()
+        )
+        .is_ok());
+        for contents in ["", "{}", r#"{"version": 1, "signed": {"signed": "not json"}}"#] {
+            match parse_manifest(path, contents.as_bytes()) {
+                Err(Error::ManifestParseFailed { path: error_path, .. }) => {
+                    assert_eq!(path, error_path);
+                }
+                Err(other) => panic!("unexpected error: {other:?}"),
+                Ok(_) => panic!("manifest should fail to parse"),
+            }
+        }
+    }
+}
@@ -1,17 +1,171 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
+mod json;
+mod junit;
+
 use crate::error::{CommandError, CommandErrorKind, Error};
 use std::error::Error as _;
+use std::fmt::Display;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Output;
+
+pub(crate) use json::JsonReporter;
+pub(crate) use junit::JunitReporter;
 
 const TERMINAL_WIDTH: usize = 79;
 
+/// Version of the machine-readable report formats. Bump this whenever a change to the JSON or
+/// JUnit output could break consumers of the reports.
+pub(crate) const REPORT_FORMAT_VERSION: u32 = 1;
+
 pub(crate) trait Reporter {
     fn success(&self, message: &str);
     fn skipped(&self, message: &str);
     fn note(&self, message: &str);
     fn info(&self, message: &str);
     fn error(&self, error: &Error);
+
+    /// Called once after all the checks were executed when not failing fast, with the outcome of
+    /// each group of checks (like all the checks for a target).
+    fn summary(&self, _groups: &[GroupResult]) {}
+
+    /// Called once after all the checks were executed, to let reporters that buffer their output
+    /// write it out.
+    fn finish(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Outcome of a group of checks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GroupResult {
+    pub(crate) name: String,
+    /// Codes of the errors encountered in the group, empty if all the checks passed.
+    pub(crate) error_codes: Vec<u8>,
+}
+
+impl GroupResult {
+    pub(crate) fn passed(&self) -> bool {
+        self.error_codes.is_empty()
+    }
+
+    pub(crate) fn error_codes(&self) -> Vec<String> {
+        self.error_codes.iter().map(|code| format_error_code(*code)).collect()
+    }
+}
+
+/// Reporter forwarding every event to multiple other reporters.
+pub(crate) struct MultiReporter<'a> {
+    reporters: Vec<&'a dyn Reporter>,
+}
+
+impl<'a> MultiReporter<'a> {
+    pub(crate) fn new(reporters: Vec<&'a dyn Reporter>) -> Self {
+        Self { reporters }
+    }
+}
+
+impl Reporter for MultiReporter<'_> {
+    fn success(&self, message: &str) {
+        self.reporters.iter().for_each(|r| r.success(message));
+    }
+
+    fn skipped(&self, message: &str) {
+        self.reporters.iter().for_each(|r| r.skipped(message));
+    }
+
+    fn note(&self, message: &str) {
+        self.reporters.iter().for_each(|r| r.note(message));
+    }
+
+    fn info(&self, message: &str) {
+        self.reporters.iter().for_each(|r| r.info(message));
+    }
+
+    fn error(&self, error: &Error) {
+        self.reporters.iter().for_each(|r| r.error(error));
+    }
+
+    fn summary(&self, groups: &[GroupResult]) {
+        self.reporters.iter().for_each(|r| r.summary(groups));
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        for reporter in &self.reporters {
+            reporter.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Information about an error, extracted from the chain of its sources.
+pub(crate) struct ErrorDetails<'a> {
+    pub(crate) causes: Vec<String>,
+    pub(crate) command: Option<&'a CommandError>,
+}
+
+impl<'a> ErrorDetails<'a> {
+    pub(crate) fn new(error: &'a Error) -> Self {
+        let mut causes = Vec::new();
+        let mut command = None;
+
+        let mut source = error.source();
+        while let Some(s) = source {
+            causes.push(s.to_string());
+            if let Some(command_error) = s.downcast_ref::<CommandError>() {
+                command = Some(command_error);
+            }
+            source = s.source();
+        }
+
+        Self { causes, command }
+    }
+
+    /// Output of the failed command, if the error was caused by a command exiting unsuccessfully.
+    pub(crate) fn command_output(&self) -> Option<&'a Output> {
+        match self.command {
+            Some(CommandError { kind: CommandErrorKind::Failure { output }, .. }) => Some(output),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn error_code(error: &Error) -> String {
+    format_error_code(error.code())
+}
+
+fn format_error_code(code: u8) -> String {
+    format!("FST_{code:0>3}")
+}
+
+/// Where a machine-readable report should be written to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ReportDestination {
+    Stdout,
+    File(PathBuf),
+}
+
+impl ReportDestination {
+    pub(crate) fn open(&self) -> Result<Box<dyn Write>, Error> {
+        match self {
+            ReportDestination::Stdout => Ok(Box::new(std::io::stdout())),
+            ReportDestination::File(path) => match std::fs::File::create(path) {
+                Ok(file) => Ok(Box::new(std::io::BufWriter::new(file))),
+                Err(error) => Err(Error::WritingReportFailed { dest: self.clone(), error }),
+            },
+        }
+    }
+}
+
+impl Display for ReportDestination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportDestination::Stdout => write!(f, "the standard output"),
+            ReportDestination::File(path) => write!(f, "{}", path.display()),
+        }
+    }
 }
 
 pub(crate) struct StderrReporter {
@@ -46,6 +200,26 @@ pub(crate) fn color() -> Self {
         }
     }
 
+    fn render_summary(&self, groups: &[GroupResult]) -> String {
+        let width = groups.iter().map(|g| g.name.len()).max().unwrap_or(0);
+
+        let mut out = format!("{}Summary:{}\n", self.color_bold, self.color_reset);
+        for group in groups {
+            let outcome = if group.passed() {
+                format!("{}passed{}", self.color_bold_green, self.color_reset)
+            } else {
+                format!(
+                    "{}FAILED{} ({})",
+                    self.color_bold_red,
+                    self.color_reset,
+                    group.error_codes().join(", ")
+                )
+            };
+            out.push_str(&format!("    {:<width$}  {outcome}\n", group.name));
+        }
+        out
+    }
+
     fn render_command_output(&self, heading: &str, content: &[u8]) {
         let padding_left = (TERMINAL_WIDTH - heading.len() - 2) / 2;
         let padding_right = TERMINAL_WIDTH - heading.len() - 2 - padding_left;
@@ -86,22 +260,14 @@ fn info(&self, message: &str) {
     fn error(&self, error: &Error) {
         eprintln!("{}   Error:{} {error}", self.color_bold_red, self.color_reset);
 
-        let mut source = error.source();
-        let mut command_output = None;
-        while let Some(s) = source {
-            eprintln!("{}   Cause:{} {s}", self.color_bold, self.color_reset);
-
-            if let Some(CommandError { kind: CommandErrorKind::Failure { output }, .. }) =
-                s.downcast_ref()
-            {
-                command_output = Some(output);
-            }
-            source = s.source();
+        let details = ErrorDetails::new(error);
+        for cause in &details.causes {
+            eprintln!("{}   Cause:{} {cause}", self.color_bold, self.color_reset);
         }
 
-        self.note(&format!("the error code is FST_{:0>3}", error.code()));
+        self.note(&format!("the error code is {}", error_code(error)));
 
-        if let Some(output) = command_output {
+        if let Some(output) = details.command_output() {
             if !output.stdout.is_empty() || !output.stderr.is_empty() {
                 eprintln!();
             }
@@ -117,4 +283,30 @@ fn error(&self, error: &Error) {
             }
         }
     }
+
+    fn summary(&self, groups: &[GroupResult]) {
+        eprintln!();
+        eprint!("{}", self.render_summary(groups));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_summary() {
+        let groups = [
+            GroupResult { name: "binaries".into(), error_codes: vec![] },
+            GroupResult { name: "target x86_64-unknown-linux-gnu".into(), error_codes: vec![9] },
+            GroupResult { name: "target aarch64-unknown-none".into(), error_codes: vec![23, 19] },
+        ];
+        assert_eq!(
+            "Summary:\n\
+             \x20   binaries                         passed\n\
+             \x20   target x86_64-unknown-linux-gnu  FAILED (FST_009)\n\
+             \x20   target aarch64-unknown-none      FAILED (FST_023, FST_019)\n",
+            StderrReporter::plain().render_summary(&groups)
+        );
+    }
 }
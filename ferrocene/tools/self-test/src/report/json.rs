@@ -0,0 +1,355 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Reporter emitting one JSON object per line for every event.
+//!
+//! Every line contains the `version` of the report format and the `event` kind. The first line is
+//! always a `start` event and the last line is always a `finish` event.
+
+use crate::error::{CommandErrorKind, Error};
+use crate::report::{
+    error_code, ErrorDetails, GroupResult, ReportDestination, Reporter, REPORT_FORMAT_VERSION,
+};
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::io::Write;
+
+pub(crate) struct JsonReporter<W: Write> {
+    writer: RefCell<W>,
+    dest: ReportDestination,
+    failed: Cell<bool>,
+    write_error: RefCell<Option<std::io::Error>>,
+}
+
+impl<W: Write> JsonReporter<W> {
+    pub(crate) fn new(writer: W, dest: ReportDestination) -> Self {
+        let reporter = Self {
+            writer: RefCell::new(writer),
+            dest,
+            failed: Cell::new(false),
+            write_error: RefCell::new(None),
+        };
+        reporter.write(Event::Start {
+            tool: "ferrocene-self-test",
+            release: env!("CFG_RELEASE"),
+            host: env!("SELFTEST_TARGET"),
+        });
+        reporter
+    }
+
+    #[cfg(test)]
+    pub(crate) fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    fn write(&self, event: Event<'_>) {
+        // Only the first error is stored, as it's the one causing all the others.
+        if self.write_error.borrow().is_some() {
+            return;
+        }
+        let line = Line { version: REPORT_FORMAT_VERSION, event };
+        let mut writer = self.writer.borrow_mut();
+        let result = serde_json::to_writer(&mut *writer, &line)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writeln!(writer));
+        if let Err(err) = result {
+            *self.write_error.borrow_mut() = Some(err);
+        }
+    }
+}
+
+impl<W: Write> Reporter for JsonReporter<W> {
+    fn success(&self, message: &str) {
+        self.write(Event::Success { message });
+    }
+
+    fn skipped(&self, message: &str) {
+        self.write(Event::Skipped { message });
+    }
+
+    fn note(&self, message: &str) {
+        self.write(Event::Note { message });
+    }
+
+    fn info(&self, message: &str) {
+        self.write(Event::Info { message });
+    }
+
+    fn error(&self, error: &Error) {
+        self.failed.set(true);
+
+        let details = ErrorDetails::new(error);
+        let command = details.command.map(|command| Command {
+            program: command.path.to_string_lossy().into_owned(),
+            args: command.args.iter().map(|a| a.to_string_lossy().into_owned()).collect(),
+            output: match &command.kind {
+                CommandErrorKind::Failure { output } => Some(CommandOutput {
+                    exit_code: output.status.code(),
+                    stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+                    stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+                }),
+                _ => None,
+            },
+        });
+
+        self.write(Event::Error {
+            code: error_code(error),
+            message: error.to_string(),
+            causes: details.causes,
+            command,
+        });
+    }
+
+    fn summary(&self, groups: &[GroupResult]) {
+        let groups = groups
+            .iter()
+            .map(|group| Group {
+                name: &group.name,
+                outcome: Outcome::new(group.passed()),
+                codes: group.error_codes(),
+            })
+            .collect();
+        self.write(Event::Summary { groups });
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        self.write(Event::Finish { outcome: Outcome::new(!self.failed.get()) });
+        if let Err(err) = self.writer.borrow_mut().flush() {
+            self.write_error.borrow_mut().get_or_insert(err);
+        }
+
+        match self.write_error.borrow_mut().take() {
+            Some(error) => Err(Error::WritingReportFailed { dest: self.dest.clone(), error }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A single line of the report. Fields can be added to the events without changing
+/// `REPORT_FORMAT_VERSION`, but removing or changing existing fields requires bumping it.
+#[derive(Serialize)]
+struct Line<'a> {
+    version: u32,
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Start {
+        tool: &'a str,
+        release: &'a str,
+        host: &'a str,
+    },
+    Success {
+        message: &'a str,
+    },
+    Skipped {
+        message: &'a str,
+    },
+    Note {
+        message: &'a str,
+    },
+    Info {
+        message: &'a str,
+    },
+    Error {
+        code: String,
+        message: String,
+        causes: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        command: Option<Command>,
+    },
+    Summary {
+        groups: Vec<Group<'a>>,
+    },
+    Finish {
+        outcome: Outcome,
+    },
+}
+
+#[derive(Serialize)]
+struct Command {
+    program: String,
+    args: Vec<String>,
+    /// Only present if the command was executed and failed.
+    #[serde(flatten)]
+    output: Option<CommandOutput>,
+}
+
+#[derive(Serialize)]
+struct CommandOutput {
+    /// `null` if the command was terminated by a signal.
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+#[derive(Serialize)]
+struct Group<'a> {
+    name: &'a str,
+    outcome: Outcome,
+    codes: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Passed,
+    Failed,
+}
+
+impl Outcome {
+    fn new(passed: bool) -> Self {
+        if passed { Outcome::Passed } else { Outcome::Failed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CommandError;
+    use std::os::unix::process::ExitStatusExt;
+    use std::path::PathBuf;
+    use std::process::{ExitStatus, Output};
+
+    #[test]
+    fn test_escape() {
+        let reporter = JsonReporter::new(Vec::new(), ReportDestination::Stdout);
+        reporter.note(r#"a "quoted" \ string"#);
+        reporter.note("line\nbreak\ttab\r");
+        reporter.note("bell\x07");
+        reporter.note("ünicode");
+        reporter.finish().unwrap();
+
+        let output = String::from_utf8(reporter.into_inner()).unwrap();
+        let lines = output.lines().skip(1).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                r#"{"version":1,"event":"note","message":"a \"quoted\" \\ string"}"#,
+                r#"{"version":1,"event":"note","message":"line\nbreak\ttab\r"}"#,
+                r#"{"version":1,"event":"note","message":"bell\u0007"}"#,
+                r#"{"version":1,"event":"note","message":"ünicode"}"#,
+                r#"{"version":1,"event":"finish","outcome":"passed"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_messages() {
+        let reporter = JsonReporter::new(Vec::new(), ReportDestination::Stdout);
+        reporter.success("binary rustc is valid");
+        reporter.skipped("optional binary cargo (not present)");
+        reporter.note("a \"note\"");
+        reporter.info("some info");
+        reporter.finish().unwrap();
+
+        let output = String::from_utf8(reporter.into_inner()).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                format!(
+                    r#"{{"version":1,"event":"start","tool":"ferrocene-self-test","release":"{}","host":"{}"}}"#,
+                    env!("CFG_RELEASE"),
+                    env!("SELFTEST_TARGET"),
+                )
+                .as_str(),
+                r#"{"version":1,"event":"success","message":"binary rustc is valid"}"#,
+                r#"{"version":1,"event":"skipped","message":"optional binary cargo (not present)"}"#,
+                r#"{"version":1,"event":"note","message":"a \"note\""}"#,
+                r#"{"version":1,"event":"info","message":"some info"}"#,
+                r#"{"version":1,"event":"finish","outcome":"passed"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error() {
+        let reporter = JsonReporter::new(Vec::new(), ReportDestination::Stdout);
+        reporter.error(&Error::SampleProgramCompilationFailed {
+            name: "addition.rs".into(),
+            error: CommandError {
+                path: PathBuf::from("/sysroot/bin/rustc"),
+                args: vec!["--crate-type".into(), "lib".into()],
+                kind: CommandErrorKind::Failure {
+                    output: Output {
+                        status: ExitStatus::from_raw(1 << 8),
+                        stdout: Vec::new(),
+                        stderr: b"error: boom\n".to_vec(),
+                    },
+                },
+            },
+        });
+        reporter.finish().unwrap();
+
+        let output = String::from_utf8(reporter.into_inner()).unwrap();
+        let lines = output.lines().skip(1).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                concat!(
+                    r#"{"version":1,"event":"error","code":"FST_019","#,
+                    r#""message":"compilation of sample program `addition.rs` failed","#,
+                    r#""causes":["invoking `/sysroot/bin/rustc --crate-type lib` failed with exit status: 1"],"#,
+                    r#""command":{"program":"/sysroot/bin/rustc","args":["--crate-type","lib"],"#,
+                    r#""exit_code":1,"stdout":"","stderr":"error: boom\n"}}"#,
+                ),
+                r#"{"version":1,"event":"finish","outcome":"failed"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let reporter = JsonReporter::new(Vec::new(), ReportDestination::Stdout);
+        reporter.summary(&[
+            GroupResult { name: "binaries".into(), error_codes: vec![] },
+            GroupResult { name: "target aarch64-unknown-none".into(), error_codes: vec![23] },
+        ]);
+        reporter.finish().unwrap();
+
+        let output = String::from_utf8(reporter.into_inner()).unwrap();
+        let lines = output.lines().skip(1).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                concat!(
+                    r#"{"version":1,"event":"summary","groups":["#,
+                    r#"{"name":"binaries","outcome":"passed","codes":[]},"#,
+                    r#"{"name":"target aarch64-unknown-none","outcome":"failed","codes":["FST_023"]}]}"#,
+                ),
+                r#"{"version":1,"event":"finish","outcome":"passed"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_failure() {
+        struct FailingWriter;
+
+        impl Write for FailingWriter {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk full"))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let dest = ReportDestination::File("report.jsonl".into());
+        let reporter = JsonReporter::new(FailingWriter, dest.clone());
+        reporter.success("this will not be written");
+
+        match reporter.finish() {
+            Err(Error::WritingReportFailed { dest: error_dest, error }) => {
+                assert_eq!(dest, error_dest);
+                assert_eq!("disk full", error.to_string());
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,293 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Reporter emitting a JUnit XML document once all checks are executed.
+//!
+//! Every successful or skipped check is rendered as a test case, and so is the error (if any).
+//! Notes and informational messages are included in the `<system-out>` of the test suite.
+
+use crate::error::{CommandErrorKind, Error};
+use crate::report::{error_code, ErrorDetails, ReportDestination, Reporter, REPORT_FORMAT_VERSION};
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::io::Write;
+
+const SUITE_NAME: &str = "ferrocene-self-test";
+
+pub(crate) struct JunitReporter<W: Write> {
+    writer: RefCell<W>,
+    dest: ReportDestination,
+    test_cases: RefCell<Vec<TestCase>>,
+    system_out: RefCell<String>,
+}
+
+impl<W: Write> JunitReporter<W> {
+    pub(crate) fn new(writer: W, dest: ReportDestination) -> Self {
+        Self {
+            writer: RefCell::new(writer),
+            dest,
+            test_cases: RefCell::new(Vec::new()),
+            system_out: RefCell::new(String::new()),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    fn render(&self) -> String {
+        let test_cases = self.test_cases.borrow();
+        let count = |f: fn(&TestCase) -> bool| test_cases.iter().filter(|c| f(c)).count();
+        let counters = format!(
+            "tests=\"{}\" failures=\"{}\" skipped=\"{}\"",
+            test_cases.len(),
+            count(|c| matches!(c.outcome, TestCaseOutcome::Failure { .. })),
+            count(|c| matches!(c.outcome, TestCaseOutcome::Skipped)),
+        );
+
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        writeln!(out, "<testsuites name=\"{SUITE_NAME}\" {counters}>").unwrap();
+        writeln!(out, "  <testsuite name=\"{SUITE_NAME}\" {counters}>").unwrap();
+
+        out.push_str("    <properties>\n");
+        for (name, value) in [
+            ("report-version", REPORT_FORMAT_VERSION.to_string().as_str()),
+            ("release", env!("CFG_RELEASE")),
+            ("host", env!("SELFTEST_TARGET")),
+        ] {
+            writeln!(out, "      <property name=\"{name}\" value=\"{}\"/>", escape(value)).unwrap();
+        }
+        out.push_str("    </properties>\n");
+
+        for case in test_cases.iter() {
+            let name = escape(&case.name);
+            match &case.outcome {
+                TestCaseOutcome::Success => {
+                    writeln!(out, "    <testcase classname=\"{SUITE_NAME}\" name=\"{name}\"/>")
+                        .unwrap();
+                }
+                TestCaseOutcome::Skipped => {
+                    writeln!(out, "    <testcase classname=\"{SUITE_NAME}\" name=\"{name}\">")
+                        .unwrap();
+                    out.push_str("      <skipped/>\n");
+                    out.push_str("    </testcase>\n");
+                }
+                TestCaseOutcome::Failure { code, message, details, stdout, stderr } => {
+                    writeln!(out, "    <testcase classname=\"{SUITE_NAME}\" name=\"{name}\">")
+                        .unwrap();
+                    writeln!(
+                        out,
+                        "      <failure type=\"{code}\" message=\"{}\">{}</failure>",
+                        escape(message),
+                        escape(details)
+                    )
+                    .unwrap();
+                    if let Some(stdout) = stdout {
+                        writeln!(out, "      <system-out>{}</system-out>", escape(stdout)).unwrap();
+                    }
+                    if let Some(stderr) = stderr {
+                        writeln!(out, "      <system-err>{}</system-err>", escape(stderr)).unwrap();
+                    }
+                    out.push_str("    </testcase>\n");
+                }
+            }
+        }
+
+        let system_out = self.system_out.borrow();
+        if !system_out.is_empty() {
+            writeln!(out, "    <system-out>{}</system-out>", escape(&system_out)).unwrap();
+        }
+
+        out.push_str("  </testsuite>\n");
+        out.push_str("</testsuites>\n");
+        out
+    }
+}
+
+impl<W: Write> Reporter for JunitReporter<W> {
+    fn success(&self, message: &str) {
+        self.test_cases
+            .borrow_mut()
+            .push(TestCase { name: message.into(), outcome: TestCaseOutcome::Success });
+    }
+
+    fn skipped(&self, message: &str) {
+        self.test_cases
+            .borrow_mut()
+            .push(TestCase { name: message.into(), outcome: TestCaseOutcome::Skipped });
+    }
+
+    fn note(&self, message: &str) {
+        writeln!(self.system_out.borrow_mut(), "Note: {message}").unwrap();
+    }
+
+    fn info(&self, message: &str) {
+        writeln!(self.system_out.borrow_mut(), "Info: {message}").unwrap();
+    }
+
+    fn error(&self, error: &Error) {
+        let code = error_code(error);
+        let details = ErrorDetails::new(error);
+
+        let mut description = String::new();
+        for cause in &details.causes {
+            writeln!(description, "Cause: {cause}").unwrap();
+        }
+
+        let (stdout, stderr) = match &details.command {
+            Some(command) => {
+                if let CommandErrorKind::Failure { output } = &command.kind {
+                    (
+                        Some(String::from_utf8_lossy(&output.stdout).into_owned()),
+                        Some(String::from_utf8_lossy(&output.stderr).into_owned()),
+                    )
+                } else {
+                    (None, None)
+                }
+            }
+            None => (None, None),
+        };
+
+        // With --no-fail-fast the same error can happen multiple times (for example on different
+        // targets), so the message is included to give every test case a distinct name.
+        let message = error.to_string();
+        self.test_cases.borrow_mut().push(TestCase {
+            name: format!("{code}: {message}"),
+            outcome: TestCaseOutcome::Failure {
+                code,
+                message,
+                details: description,
+                stdout,
+                stderr,
+            },
+        });
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        let rendered = self.render();
+
+        let mut writer = self.writer.borrow_mut();
+        writer
+            .write_all(rendered.as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(|error| Error::WritingReportFailed { dest: self.dest.clone(), error })
+    }
+}
+
+struct TestCase {
+    name: String,
+    outcome: TestCaseOutcome,
+}
+
+enum TestCaseOutcome {
+    Success,
+    Skipped,
+    Failure {
+        code: String,
+        message: String,
+        details: String,
+        stdout: Option<String>,
+        stderr: Option<String>,
+    },
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tabs and newlines are not allowed in XML 1.0.
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!("hello world", escape("hello world"));
+        assert_eq!("&lt;a href=&quot;x&quot;&gt;&amp;&apos;", escape("<a href=\"x\">&'"));
+        assert_eq!("line\nbreak\t\u{fffd}", escape("line\nbreak\t\x07"));
+    }
+
+    #[test]
+    fn test_render() {
+        let reporter = JunitReporter::new(Vec::new(), ReportDestination::Stdout);
+        reporter.info("using sysroot /sysroot");
+        reporter.success("binary rustc is valid");
+        reporter.skipped("optional binary cargo (not present)");
+        reporter.note("some <note>");
+        reporter.error(&Error::TargetLibraryMissing {
+            target: "aarch64-unknown-none".into(),
+            library: "core".into(),
+        });
+        reporter.finish().unwrap();
+
+        let expected = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="ferrocene-self-test" tests="3" failures="1" skipped="1">
+  <testsuite name="ferrocene-self-test" tests="3" failures="1" skipped="1">
+    <properties>
+      <property name="report-version" value="1"/>
+      <property name="release" value="{}"/>
+      <property name="host" value="{}"/>
+    </properties>
+    <testcase classname="ferrocene-self-test" name="binary rustc is valid"/>
+    <testcase classname="ferrocene-self-test" name="optional binary cargo (not present)">
+      <skipped/>
+    </testcase>
+    <testcase classname="ferrocene-self-test" name="FST_008: library core is missing from target aarch64-unknown-none">
+      <failure type="FST_008" message="library core is missing from target aarch64-unknown-none"></failure>
+    </testcase>
+    <system-out>Info: using sysroot /sysroot
+Note: some &lt;note&gt;
+</system-out>
+  </testsuite>
+</testsuites>
+"#,
+            env!("CFG_RELEASE"),
+            env!("SELFTEST_TARGET"),
+        );
+        assert_eq!(expected, String::from_utf8(reporter.into_inner()).unwrap());
+    }
+
+    #[test]
+    fn test_render_errors_with_the_same_code() {
+        let reporter = JunitReporter::new(Vec::new(), ReportDestination::Stdout);
+        for target in ["aarch64-unknown-none", "thumbv7em-none-eabihf"] {
+            reporter.error(&Error::TargetLibraryMissing {
+                target: target.into(),
+                library: "core".into(),
+            });
+        }
+        reporter.finish().unwrap();
+
+        let rendered = String::from_utf8(reporter.into_inner()).unwrap();
+        let names = rendered
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("<testcase "))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "classname=\"ferrocene-self-test\" name=\"FST_008: library core is missing from \
+                 target aarch64-unknown-none\">",
+                "classname=\"ferrocene-self-test\" name=\"FST_008: library core is missing from \
+                 target thumbv7em-none-eabihf\">",
+            ]
+        );
+        assert_eq!(2, rendered.matches("<failure type=\"FST_008\"").count());
+    }
+}
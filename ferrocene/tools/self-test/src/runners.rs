@@ -0,0 +1,306 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use crate::diagnostics::{target_group, Diagnostics};
+use crate::error::{Error, FindBinaryInPathError};
+use crate::report::Reporter;
+use crate::targets::Target;
+use crate::utils::find_binary_in_path;
+use crate::Environment;
+use std::path::PathBuf;
+
+/// How to execute the sample programs compiled for a target the host can't natively run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RunnerSpec {
+    /// The command (and its arguments) to invoke. The path of the program to run is appended to
+    /// the end of it.
+    pub(crate) command: Vec<String>,
+    /// Additional flags to pass to rustc when compiling the sample programs, needed to make the
+    /// program runnable with this runner (for example to enable semihosting).
+    pub(crate) rustflags: Vec<String>,
+}
+
+/// A runner whose binary was found on the system.
+#[derive(Debug)]
+pub(crate) struct Runner {
+    pub(crate) path: PathBuf,
+    pub(crate) args: Vec<String>,
+    pub(crate) rustflags: Vec<String>,
+}
+
+/// Finds the runner for each target that is not the host.
+///
+/// Runners passed on the command line take precedence over the ones in the target data file, and
+/// must be present on the system. Runners from the target data file are instead optional: if they
+/// are not present the sample programs are only compiled.
+pub(crate) fn check_and_add_runners(
+    reporter: &dyn Reporter,
+    diagnostics: &Diagnostics<'_>,
+    environment: &Environment,
+    overrides: &[(String, Vec<String>)],
+    targets: &mut Vec<Target>,
+) -> Result<(), Error> {
+    let mut checked = Vec::new();
+    for mut target in targets.drain(..) {
+        let group = target_group(&target.triple);
+        let result = diagnostics
+            .check(&group, || find_target_runner(reporter, environment, overrides, &mut target))?;
+        if result.is_some() {
+            checked.push(target);
+        }
+    }
+    *targets = checked;
+    Ok(())
+}
+
+fn find_target_runner(
+    reporter: &dyn Reporter,
+    environment: &Environment,
+    overrides: &[(String, Vec<String>)],
+    target: &mut Target,
+) -> Result<(), Error> {
+    if target.triple == env!("SELFTEST_TARGET") {
+        // Programs for the host are executed natively.
+        return Ok(());
+    }
+
+    let overridden = overrides.iter().rev().find(|(triple, _)| *triple == target.triple);
+    let (command, rustflags) = match (overridden, &target.spec.runner) {
+        (Some((_, command)), spec) => {
+            (command, spec.as_ref().map(|s| s.rustflags.clone()).unwrap_or_default())
+        }
+        (None, Some(spec)) => (&spec.command, spec.rustflags.clone()),
+        (None, None) => {
+            reporter.skipped(&format!(
+                "running sample programs for target {} (no runner available)",
+                target.triple
+            ));
+            return Ok(());
+        }
+    };
+
+    // Empty commands are rejected when parsing the CLI and the target data file.
+    let (name, args) = command.split_first().expect("empty runner command");
+    match find_runner(environment, name) {
+        Ok(path) => {
+            reporter.success(&format!("found runner `{name}` for target {}", target.triple));
+            target.runner = Some(Runner { path, args: args.to_vec(), rustflags });
+        }
+        Err(error) if overridden.is_some() => {
+            return Err(Error::RunnerNotFound {
+                target: target.triple.clone(),
+                name: name.clone(),
+                error,
+            });
+        }
+        Err(_) => {
+            reporter.skipped(&format!(
+                "running sample programs for target {} (runner `{name}` not found)",
+                target.triple
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn find_runner(environment: &Environment, name: &str) -> Result<PathBuf, FindBinaryInPathError> {
+    if name.contains('/') {
+        let path = PathBuf::from(name);
+        if path.is_file() {
+            Ok(path)
+        } else {
+            Err(FindBinaryInPathError::MissingBinary { name: name.into() })
+        }
+    } else {
+        find_binary_in_path(environment, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linkers::Linker;
+    use crate::targets::TargetSpec;
+    use crate::test_utils::TestUtils;
+
+    fn target(triple: &str, runner: Option<RunnerSpec>) -> Target {
+        Target {
+            spec: TargetSpec {
+                triple: triple.into(),
+                std: true,
+                linker: Linker::BundledLld,
+                runner,
+            },
+            builtin_dirs: Vec::new(),
+            rustflags: Vec::new(),
+            c_compiler: None,
+            runner: None,
+        }
+    }
+
+    fn spec(command: &[&str], rustflags: &[&str]) -> Option<RunnerSpec> {
+        Some(RunnerSpec {
+            command: command.iter().map(|s| s.to_string()).collect(),
+            rustflags: rustflags.iter().map(|s| s.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn test_host_has_no_runner() {
+        let utils = TestUtils::new();
+        utils.bin("qemu-foo").create();
+
+        let mut targets = vec![target(env!("SELFTEST_TARGET"), spec(&["qemu-foo"], &[]))];
+        check_and_add_runners(
+            utils.reporter(),
+            &utils.diagnostics(),
+            utils.env(),
+            &[],
+            &mut targets,
+        )
+        .unwrap();
+
+        assert!(targets[0].runner.is_none());
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_runner_from_target_data() {
+        let utils = TestUtils::new();
+        let qemu = utils.bin("qemu-foo").create();
+
+        let mut targets = vec![target(
+            "foo-unknown-none",
+            spec(&["qemu-foo", "-L", "/usr/foo"], &["--cfg", "x"]),
+        )];
+        check_and_add_runners(
+            utils.reporter(),
+            &utils.diagnostics(),
+            utils.env(),
+            &[],
+            &mut targets,
+        )
+        .unwrap();
+
+        let runner = targets[0].runner.as_ref().unwrap();
+        assert_eq!(qemu, runner.path);
+        assert_eq!(["-L", "/usr/foo"], runner.args.as_slice());
+        assert_eq!(["--cfg", "x"], runner.rustflags.as_slice());
+        utils.assert_report_success("found runner `qemu-foo` for target foo-unknown-none");
+    }
+
+    #[test]
+    fn test_runner_from_target_data_missing() {
+        let utils = TestUtils::new();
+
+        let mut targets = vec![target("foo-unknown-none", spec(&["qemu-foo"], &[]))];
+        check_and_add_runners(
+            utils.reporter(),
+            &utils.diagnostics(),
+            utils.env(),
+            &[],
+            &mut targets,
+        )
+        .unwrap();
+
+        assert!(targets[0].runner.is_none());
+        utils.assert_report_skipped(
+            "running sample programs for target foo-unknown-none (runner `qemu-foo` not found)",
+        );
+    }
+
+    #[test]
+    fn test_no_runner() {
+        let utils = TestUtils::new();
+
+        let mut targets = vec![target("foo-unknown-none", None)];
+        check_and_add_runners(
+            utils.reporter(),
+            &utils.diagnostics(),
+            utils.env(),
+            &[],
+            &mut targets,
+        )
+        .unwrap();
+
+        assert!(targets[0].runner.is_none());
+        utils.assert_report_skipped(
+            "running sample programs for target foo-unknown-none (no runner available)",
+        );
+    }
+
+    #[test]
+    fn test_runner_override() {
+        let utils = TestUtils::new();
+        let custom = utils.bin("custom-runner").create();
+
+        let mut targets = vec![target("foo-unknown-none", spec(&["qemu-foo"], &["--cfg", "x"]))];
+        let overrides = [(
+            "foo-unknown-none".to_string(),
+            vec![custom.to_str().unwrap().to_string(), "--flag".into()],
+        )];
+        check_and_add_runners(
+            utils.reporter(),
+            &utils.diagnostics(),
+            utils.env(),
+            &overrides,
+            &mut targets,
+        )
+        .unwrap();
+
+        let runner = targets[0].runner.as_ref().unwrap();
+        assert_eq!(custom, runner.path);
+        assert_eq!(["--flag"], runner.args.as_slice());
+        // The rustflags from the target data are still used.
+        assert_eq!(["--cfg", "x"], runner.rustflags.as_slice());
+    }
+
+    #[test]
+    fn test_runner_override_missing() {
+        let utils = TestUtils::new();
+
+        let mut targets = vec![target("foo-unknown-none", None)];
+        let overrides = [("foo-unknown-none".to_string(), vec!["missing-runner".to_string()])];
+        match check_and_add_runners(
+            utils.reporter(),
+            &utils.diagnostics(),
+            utils.env(),
+            &overrides,
+            &mut targets,
+        ) {
+            Err(Error::RunnerNotFound { target, name, .. }) => {
+                assert_eq!("foo-unknown-none", target);
+                assert_eq!("missing-runner", name);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_runner_override_missing_no_fail_fast() {
+        let utils = TestUtils::new();
+        utils.bin("qemu-bar").create();
+
+        let mut targets = vec![
+            target("foo-unknown-none", None),
+            target("bar-unknown-none", spec(&["qemu-bar"], &[])),
+        ];
+        let overrides = [("foo-unknown-none".to_string(), vec!["missing-runner".to_string()])];
+        let diagnostics = Diagnostics::new(utils.reporter(), false);
+        check_and_add_runners(
+            utils.reporter(),
+            &diagnostics,
+            utils.env(),
+            &overrides,
+            &mut targets,
+        )
+        .unwrap();
+
+        // The target with the missing runner is not checked any further.
+        assert_eq!(1, targets.len());
+        assert_eq!("bar-unknown-none", targets[0].triple);
+        utils.assert_report_success("found runner `qemu-bar` for target bar-unknown-none");
+        utils.assert_report_error(32);
+        assert_eq!(Some(32), diagnostics.first_error_code());
+    }
+}
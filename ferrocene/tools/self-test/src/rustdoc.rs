@@ -0,0 +1,297 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use crate::error::Error;
+use crate::report::Reporter;
+use crate::targets::Target;
+use crate::utils::run_command;
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::Command;
+
+const SAMPLE_CRATE_NAME: &str = "documented";
+const SAMPLE_CRATE_SOURCE: &str = r#"
+//! Sample crate used by ferrocene-self-test to check rustdoc.
+
+/// Adds two numbers together.
+///
+/// ```
+/// assert_eq!(4, documented::add(2, 2));
+/// ```
+pub fn add(left: i32, right: i32) -> i32 {
+    left + right
+}
+"#;
+
+/// Pages rustdoc is expected to generate for the sample crate.
+const EXPECTED_PAGES: &[&str] = &["index.html", "fn.add.html"];
+
+/// Checks that rustdoc can document a sample crate (both as HTML and as JSON) and run its
+/// doctests.
+///
+/// Documentation is only generated for the host, as that's the only target doctests can be
+/// executed on.
+pub(crate) fn check(
+    reporter: &dyn Reporter,
+    sysroot: &Path,
+    host: Option<&Target>,
+) -> Result<(), Error> {
+    let Some(host) = host else {
+        reporter.skipped("documenting a sample crate with rustdoc (host target not installed)");
+        return Ok(());
+    };
+
+    let temp = tempfile::Builder::new()
+        .prefix("fst-")
+        .tempdir()
+        .map_err(|error| Error::TemporaryCompilationDirectoryCreationFailed { error })?;
+    let source = temp.path().join(format!("{SAMPLE_CRATE_NAME}.rs"));
+    let lib_dir = temp.path().join("lib");
+    let doc_dir = temp.path().join("doc");
+    let json_dir = temp.path().join("doc-json");
+
+    std::fs::write(&source, SAMPLE_CRATE_SOURCE).map_err(|error| {
+        Error::WritingSampleProgramFailed {
+            name: format!("{SAMPLE_CRATE_NAME}.rs"),
+            dest: source.clone(),
+            error,
+        }
+    })?;
+
+    // The doctests link to the sample crate, so it needs to be compiled first.
+    let mut rustc = Command::new(sysroot.join("bin").join("rustc"));
+    rustc.args(["--target", &host.triple, "--crate-type", "lib", "--edition", "2021"]);
+    rustc.args(["--crate-name", SAMPLE_CRATE_NAME]);
+    rustc.arg("--out-dir").arg(&lib_dir);
+    rustc.args(&host.rustflags);
+    rustc.arg(&source);
+    run_command(&mut rustc).map_err(|error| Error::SampleProgramCompilationFailed {
+        name: format!("{SAMPLE_CRATE_NAME}.rs"),
+        error,
+    })?;
+
+    let rustdoc = sysroot.join("bin").join("rustdoc");
+
+    let mut doc = Command::new(&rustdoc);
+    doc.args(["--target", &host.triple, "--crate-type", "lib", "--edition", "2021"]);
+    doc.args(["--crate-name", SAMPLE_CRATE_NAME]);
+    doc.arg("-o").arg(&doc_dir);
+    doc.arg(&source);
+    run_command(&mut doc).map_err(|error| Error::RustdocFailed { error })?;
+
+    for page in EXPECTED_PAGES {
+        let path = doc_dir.join(SAMPLE_CRATE_NAME).join(page);
+        if !path.is_file() {
+            return Err(Error::RustdocOutputMissing { path });
+        }
+    }
+    reporter.success("documented a sample crate with rustdoc");
+
+    // The JSON output is still unstable, and there is no stable flag to enable it.
+    let mut json = Command::new(&rustdoc);
+    json.env("RUSTC_BOOTSTRAP", "1");
+    json.args(["--target", &host.triple, "--crate-type", "lib", "--edition", "2021"]);
+    json.args(["--crate-name", SAMPLE_CRATE_NAME]);
+    json.args(["-Z", "unstable-options", "--output-format", "json"]);
+    json.arg("-o").arg(&json_dir);
+    json.arg(&source);
+    run_command(&mut json).map_err(|error| Error::RustdocFailed { error })?;
+
+    let path = json_dir.join(format!("{SAMPLE_CRATE_NAME}.json"));
+    let Ok(contents) = std::fs::read(&path) else {
+        return Err(Error::RustdocOutputMissing { path });
+    };
+    if let Err(error) = serde_json::from_slice::<serde_json::Value>(&contents) {
+        return Err(Error::RustdocJsonOutputInvalid { path, error });
+    }
+    reporter.success("documented a sample crate as JSON with rustdoc");
+
+    let mut extern_crate = OsString::from(format!("{SAMPLE_CRATE_NAME}="));
+    extern_crate.push(lib_dir.join(format!("lib{SAMPLE_CRATE_NAME}.rlib")));
+
+    let mut test = Command::new(&rustdoc);
+    test.args(["--test", "--target", &host.triple, "--edition", "2021"]);
+    test.args(["--crate-name", SAMPLE_CRATE_NAME]);
+    test.arg("--extern").arg(extern_crate);
+    test.args(&host.rustflags);
+    test.arg(&source);
+    let output = run_command(&mut test).map_err(|error| Error::DoctestsFailed { error })?;
+
+    // Ensure the doctest was actually executed, rather than silently ignored.
+    if !output.stdout.contains("test result: ok. 1 passed") {
+        return Err(Error::DoctestsNotExecuted);
+    }
+    reporter.success("ran the doctests of a sample crate with rustdoc");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linkers::Linker;
+    use crate::targets::TargetSpec;
+    use crate::test_utils::TestUtils;
+
+    // Fake rustc and rustdoc creating the expected files. Tests needing a different behavior
+    // replace the constants at the top of the fake rustdoc.
+    const RUSTC_SOURCE: &str = r#"
+        fn main() {
+            let args = std::env::args().skip(1).collect::<Vec<_>>();
+            let out_dir = &args[args.iter().position(|a| a == "--out-dir").unwrap() + 1];
+            std::fs::create_dir_all(out_dir).unwrap();
+            std::fs::write(format!("{out_dir}/libdocumented.rlib"), b"").unwrap();
+        }
+    "#;
+    const RUSTDOC_SOURCE: &str = r#"
+        const GENERATE_FN_PAGE: bool = true;
+        const GENERATE_JSON: bool = true;
+        const JSON_OUTPUT: &str = "{\"format_version\":28}";
+        const TEST_OUTPUT: &str = "test result: ok. 1 passed";
+
+        fn main() {
+            let args = std::env::args().skip(1).collect::<Vec<_>>();
+            if args[0] == "--test" {
+                assert!(args.iter().any(|a| a.starts_with("documented=")));
+                print!("{TEST_OUTPUT}");
+                return;
+            }
+            let out_dir = &args[args.iter().position(|a| a == "-o").unwrap() + 1];
+            if args.iter().any(|a| a == "--output-format") {
+                assert_eq!(Ok("1"), std::env::var("RUSTC_BOOTSTRAP").as_deref());
+                std::fs::create_dir_all(out_dir).unwrap();
+                if GENERATE_JSON {
+                    std::fs::write(format!("{out_dir}/documented.json"), JSON_OUTPUT).unwrap();
+                }
+                return;
+            }
+            let crate_dir = format!("{out_dir}/documented");
+            std::fs::create_dir_all(&crate_dir).unwrap();
+            std::fs::write(format!("{crate_dir}/index.html"), b"").unwrap();
+            if GENERATE_FN_PAGE {
+                std::fs::write(format!("{crate_dir}/fn.add.html"), b"").unwrap();
+            }
+        }
+    "#;
+
+    fn host() -> Target {
+        Target {
+            spec: TargetSpec {
+                triple: "x86_64-unknown-linux-gnu".into(),
+                std: true,
+                linker: Linker::HostCC,
+                runner: None,
+            },
+            builtin_dirs: Vec::new(),
+            rustflags: vec!["-Clinker=cc".into()],
+            c_compiler: None,
+            runner: None,
+        }
+    }
+
+    #[test]
+    fn test_no_host() {
+        let utils = TestUtils::new();
+        check(utils.reporter(), utils.sysroot(), None).unwrap();
+        utils.assert_report_skipped(
+            "documenting a sample crate with rustdoc (host target not installed)",
+        );
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_success() {
+        let utils = TestUtils::new();
+        utils.bin("rustc").program_source(RUSTC_SOURCE).create();
+        utils.bin("rustdoc").program_source(RUSTDOC_SOURCE).create();
+
+        check(utils.reporter(), utils.sysroot(), Some(&host())).unwrap();
+        utils.assert_report_success("ran the doctests of a sample crate with rustdoc");
+        utils.assert_report_success("documented a sample crate as JSON with rustdoc");
+        utils.assert_report_success("documented a sample crate with rustdoc");
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_rustdoc_failed() {
+        let utils = TestUtils::new();
+        utils.bin("rustc").program_source(RUSTC_SOURCE).create();
+        utils.bin("rustdoc").exit(1).create();
+
+        match check(utils.reporter(), utils.sysroot(), Some(&host())) {
+            Err(Error::RustdocFailed { error }) => {
+                assert_eq!(utils.sysroot().join("bin").join("rustdoc"), error.path);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_missing_page() {
+        let utils = TestUtils::new();
+        utils.bin("rustc").program_source(RUSTC_SOURCE).create();
+        utils
+            .bin("rustdoc")
+            .program_source(
+                &RUSTDOC_SOURCE
+                    .replace("GENERATE_FN_PAGE: bool = true", "GENERATE_FN_PAGE: bool = false"),
+            )
+            .create();
+
+        match check(utils.reporter(), utils.sysroot(), Some(&host())) {
+            Err(Error::RustdocOutputMissing { path }) => {
+                assert!(path.ends_with("doc/documented/fn.add.html"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_missing_json() {
+        let utils = TestUtils::new();
+        utils.bin("rustc").program_source(RUSTC_SOURCE).create();
+        utils
+            .bin("rustdoc")
+            .program_source(
+                &RUSTDOC_SOURCE
+                    .replace("GENERATE_JSON: bool = true", "GENERATE_JSON: bool = false"),
+            )
+            .create();
+
+        match check(utils.reporter(), utils.sysroot(), Some(&host())) {
+            Err(Error::RustdocOutputMissing { path }) => {
+                assert!(path.ends_with("doc-json/documented.json"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let utils = TestUtils::new();
+        utils.bin("rustc").program_source(RUSTC_SOURCE).create();
+        utils.bin("rustdoc").program_source(&RUSTDOC_SOURCE.replace(":28}", ":28")).create();
+
+        match check(utils.reporter(), utils.sysroot(), Some(&host())) {
+            Err(Error::RustdocJsonOutputInvalid { path, .. }) => {
+                assert!(path.ends_with("doc-json/documented.json"));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_doctests_not_executed() {
+        let utils = TestUtils::new();
+        utils.bin("rustc").program_source(RUSTC_SOURCE).create();
+        utils
+            .bin("rustdoc")
+            .program_source(&RUSTDOC_SOURCE.replace("ok. 1 passed", "ok. 0 passed"))
+            .create();
+
+        match check(utils.reporter(), utils.sysroot(), Some(&host())) {
+            Err(Error::DoctestsNotExecuted) => {}
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,412 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Snapshot of everything the checks look at, to be attached to support requests.
+//!
+//! Unlike the checks, gathering the snapshot never stops at the first error: errors are recorded
+//! next to the data they relate to, and gathering continues with the rest of the environment.
+
+use crate::cli::SnapshotCli;
+use crate::error::{CommandErrorKind, Error};
+use crate::report::{error_code, ErrorDetails, ReportDestination, Reporter};
+use crate::{binaries, linkers, targets, Environment};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Version of the snapshot format. Bump this whenever a change could break tools processing it.
+const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// Environment variables influencing how the toolchain behaves, included in the snapshot when set.
+/// `PATH` is recorded separately, split into its entries.
+const ENV_VARS: &[&str] = &[
+    "LD_LIBRARY_PATH",
+    "RUSTFLAGS",
+    "RUSTDOCFLAGS",
+    "RUSTC_WRAPPER",
+    "RUSTC_WORKSPACE_WRAPPER",
+    "CARGO_HOME",
+    "CARGO_BUILD_TARGET",
+    "CARGO_BUILD_RUSTFLAGS",
+    "CARGO_ENCODED_RUSTFLAGS",
+    "FST_PRINT_DETAILED_ARGS",
+    "FST_PRINT_DETAILED_ERRORS",
+];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Snapshot {
+    version: u32,
+    release: &'static str,
+    host: &'static str,
+    sysroot: PathBuf,
+    path: Vec<PathBuf>,
+    environment: BTreeMap<&'static str, String>,
+    binaries: Vec<BinarySnapshot>,
+    targets: TargetsSnapshot,
+    linkers: LinkersSnapshot,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct BinarySnapshot {
+    pub(crate) name: &'static str,
+    pub(crate) path: PathBuf,
+    /// Permissions of the binary in octal, or `None` if it doesn't exist.
+    pub(crate) mode: Option<String>,
+    /// Output of `-vV`, or `None` if it couldn't be executed.
+    pub(crate) version: Option<String>,
+    pub(crate) error: Option<SnapshotError>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct TargetsSnapshot {
+    pub(crate) data_file: PathBuf,
+    pub(crate) data_error: Option<SnapshotError>,
+    pub(crate) installed: Vec<InstalledTargetSnapshot>,
+    pub(crate) discovery_error: Option<SnapshotError>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct InstalledTargetSnapshot {
+    pub(crate) triple: String,
+    /// Whether the target is described in the target data file.
+    pub(crate) described: bool,
+    pub(crate) libraries: Vec<LibrarySnapshot>,
+    /// The libraries in each builtin directory, keyed by the name of the directory.
+    pub(crate) builtin: BTreeMap<String, Vec<LibrarySnapshot>>,
+    pub(crate) error: Option<SnapshotError>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct LibrarySnapshot {
+    pub(crate) file: String,
+    /// Hex-encoded SHA-256 of the file, or `None` if it couldn't be read.
+    pub(crate) sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct LinkersSnapshot {
+    pub(crate) bundled_lld: PathSnapshot,
+    pub(crate) bundled_lld_wrapper: PathSnapshot,
+    pub(crate) c_compilers: Vec<CCompilerSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct PathSnapshot {
+    pub(crate) path: PathBuf,
+    pub(crate) present: bool,
+}
+
+impl PathSnapshot {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        let present = path.is_file();
+        Self { path, present }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct CCompilerSnapshot {
+    pub(crate) target: String,
+    pub(crate) name: String,
+    /// Where the C compiler was found in the `PATH`, or `None` if it wasn't found.
+    pub(crate) path: Option<PathBuf>,
+    /// First line of the output of `--version`.
+    pub(crate) version: Option<String>,
+    /// Arguments the C compiler needs, as determined by probing the linker arguments.
+    pub(crate) args: Vec<String>,
+    /// Arguments the C compiler passed to the linker while probing.
+    pub(crate) linker_args: Option<Vec<String>>,
+    pub(crate) error: Option<SnapshotError>,
+}
+
+/// An error encountered while gathering the snapshot, along with its causes.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct SnapshotError {
+    code: String,
+    message: String,
+    causes: Vec<String>,
+    /// Standard error of the command that failed, if the error was caused by one.
+    stderr: Option<String>,
+}
+
+impl SnapshotError {
+    pub(crate) fn new(error: &Error) -> Self {
+        let details = ErrorDetails::new(error);
+        let stderr = match details.command.map(|c| &c.kind) {
+            Some(CommandErrorKind::Failure { output }) => {
+                Some(String::from_utf8_lossy(&output.stderr).into_owned())
+            }
+            _ => None,
+        };
+        Self { code: error_code(error), message: error.to_string(), causes: details.causes, stderr }
+    }
+}
+
+/// Reporter discarding all events, used when invoking the checks while gathering the snapshot.
+pub(crate) struct NullReporter;
+
+impl Reporter for NullReporter {
+    fn success(&self, _: &str) {}
+    fn skipped(&self, _: &str) {}
+    fn note(&self, _: &str) {}
+    fn info(&self, _: &str) {}
+    fn error(&self, _: &Error) {}
+}
+
+pub(crate) fn run(cli: &SnapshotCli) -> Result<(), Error> {
+    let environment = Environment::gather();
+    let sysroot = crate::get_sysroot().ok_or(Error::NoSysroot)?;
+
+    let snapshot = gather(&environment, &sysroot);
+    let mut value = serde_json::to_value(&snapshot).expect("failed to serialize the snapshot");
+    if cli.redact {
+        redact(&mut value, &redactions());
+    }
+    let mut serialized = serde_json::to_string_pretty(&value).expect("failed to serialize");
+    serialized.push('\n');
+
+    let result = match &cli.output {
+        ReportDestination::Stdout => std::io::stdout().write_all(serialized.as_bytes()),
+        ReportDestination::File(path) => std::fs::write(path, serialized),
+    };
+    result.map_err(|error| Error::WritingSnapshotFailed { dest: cli.output.clone(), error })
+}
+
+fn gather(environment: &Environment, sysroot: &Path) -> Snapshot {
+    let (targets, described_targets) = targets::snapshot(sysroot);
+    Snapshot {
+        version: SNAPSHOT_FORMAT_VERSION,
+        release: env!("CFG_RELEASE"),
+        host: env!("SELFTEST_TARGET"),
+        sysroot: sysroot.into(),
+        path: environment
+            .path
+            .as_ref()
+            .map(|p| std::env::split_paths(p).collect())
+            .unwrap_or_default(),
+        environment: ENV_VARS
+            .iter()
+            .filter_map(|var| Some((*var, std::env::var_os(var)?.to_string_lossy().into_owned())))
+            .collect(),
+        binaries: binaries::snapshot(sysroot),
+        linkers: linkers::snapshot(environment, sysroot, &described_targets),
+        targets,
+    }
+}
+
+/// Strings replaced when redacting the snapshot, along with their placeholders.
+///
+/// The home directory is replaced before the user name, as the former usually contains the latter.
+fn redactions() -> Vec<(String, &'static str)> {
+    let mut redactions = Vec::new();
+    if let Some(home) = std::env::var("HOME").ok().filter(|h| h.len() > 1) {
+        redactions.push((home.trim_end_matches('/').to_string(), "<home>"));
+    }
+    if let Ok(user) = std::env::var("USER").or_else(|_| std::env::var("LOGNAME")) {
+        if !user.is_empty() {
+            redactions.push((user, "<user>"));
+        }
+    }
+    redactions
+}
+
+fn redact(value: &mut Value, redactions: &[(String, &str)]) {
+    match value {
+        Value::String(string) => {
+            for (sensitive, placeholder) in redactions {
+                if string.contains(sensitive.as_str()) {
+                    *string = replace_whole_words(string, sensitive, placeholder);
+                }
+            }
+        }
+        Value::Array(array) => array.iter_mut().for_each(|v| redact(v, redactions)),
+        Value::Object(object) => object.values_mut().for_each(|v| redact(v, redactions)),
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+/// Replaces the occurrences of `needle` not surrounded by other characters of the same word, so
+/// that short user names (like `ci`) only match whole path components rather than parts of
+/// versions, hashes or target names.
+fn replace_whole_words(haystack: &str, needle: &str, placeholder: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '.');
+
+    let mut result = String::with_capacity(haystack.len());
+    let mut rest = haystack;
+    while let Some(start) = rest.find(needle) {
+        let end = start + needle.len();
+        let before = rest[..start].chars().next_back().or_else(|| result.chars().next_back());
+        let after = rest[end..].chars().next();
+
+        result.push_str(&rest[..start]);
+        if before.is_some_and(is_word) || after.is_some_and(is_word) {
+            let skipped = rest[start..].chars().next().unwrap();
+            result.push(skipped);
+            rest = &rest[start + skipped.len_utf8()..];
+        } else {
+            result.push_str(placeholder);
+            rest = &rest[end..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestUtils;
+
+    #[test]
+    fn test_gather() {
+        let utils = TestUtils::new();
+        utils.bin("rustc").behaves_like_vV().create();
+        utils.bin("rust-lld").for_target(env!("SELFTEST_TARGET")).create();
+        utils.targets_data(
+            r#"
+                [x86_64-unknown-none]
+                std = false
+                linker = "bundled-lld"
+            "#,
+        );
+        utils
+            .target("x86_64-unknown-none")
+            .lib("core", "0123456789abcdef")
+            .builtin_lib("oxidos", "oxidos", "0123456789abcdef")
+            .create();
+        utils.target("riscv64gc-unknown-none-elf").lib("core", "0123456789abcdef").create();
+
+        let snapshot = gather(utils.env(), utils.sysroot());
+        let value = serde_json::to_value(&snapshot).unwrap();
+
+        assert_eq!(SNAPSHOT_FORMAT_VERSION, value["version"]);
+        assert_eq!(vec![utils.sysroot().join("bin")], snapshot.path);
+
+        let rustc = &value["binaries"][0];
+        assert_eq!("rustc", rustc["name"]);
+        assert!(rustc["version"].as_str().unwrap().contains("release: "));
+        assert!(rustc["error"].is_null());
+        // A missing binary doesn't stop the snapshot from being gathered.
+        let rustdoc = &value["binaries"][1];
+        assert!(rustdoc["mode"].is_null());
+        assert_eq!("FST_002", rustdoc["error"]["code"]);
+
+        let installed = &value["targets"]["installed"];
+        assert_eq!("riscv64gc-unknown-none-elf", installed[0]["triple"]);
+        assert_eq!(false, installed[0]["described"]);
+        assert_eq!("x86_64-unknown-none", installed[1]["triple"]);
+        assert_eq!(true, installed[1]["described"]);
+        assert_eq!("libcore-0123456789abcdef.rlib", installed[1]["libraries"][0]["file"]);
+        assert_eq!(
+            // SHA-256 of an empty file.
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            installed[1]["libraries"][0]["sha256"]
+        );
+        assert_eq!("liboxidos-0123456789abcdef.rlib", installed[1]["builtin"]["oxidos"][0]["file"]);
+        assert_eq!("FST_008", installed[1]["error"]["code"]);
+
+        let linkers = &value["linkers"];
+        assert_eq!(true, linkers["bundled-lld"]["present"]);
+        assert_eq!(false, linkers["bundled-lld-wrapper"]["present"]);
+        assert!(linkers["c-compilers"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_gather_c_compilers() {
+        let utils = TestUtils::new();
+        utils.bin("cc").stdout("cc (Fake) 1.0\nCopyright\n").create();
+        utils.targets_data(
+            r#"
+                [x86_64-unknown-linux-gnu]
+                std = true
+                linker = "host-cc"
+            "#,
+        );
+        utils.target("x86_64-unknown-linux-gnu").lib("core", "0123456789abcdef").create();
+
+        let snapshot = gather(utils.env(), utils.sysroot());
+        let compilers = &snapshot.linkers.c_compilers;
+
+        assert_eq!(
+            ["cc", "gcc", "clang"],
+            compilers.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().as_slice()
+        );
+        assert_eq!(Some(utils.sysroot().join("bin").join("cc")), compilers[0].path);
+        assert_eq!(Some("cc (Fake) 1.0"), compilers[0].version.as_deref());
+        // The fake compiler doesn't invoke the linker, so the probe fails.
+        assert!(compilers[0].error.is_some());
+        assert!(compilers[1].path.is_none());
+        assert!(compilers[1].error.is_none());
+    }
+
+    #[test]
+    fn test_redact() {
+        let mut value = serde_json::json!({
+            "sysroot": "/home/jdoe/ferrocene",
+            "path": ["/home/jdoe/bin", "/usr/bin"],
+            "environment": { "USER": "jdoe" },
+            "version": 1,
+        });
+        redact(&mut value, &[("/home/jdoe".into(), "<home>"), ("jdoe".into(), "<user>")]);
+
+        assert_eq!(
+            serde_json::json!({
+                "sysroot": "<home>/ferrocene",
+                "path": ["<home>/bin", "/usr/bin"],
+                "environment": { "USER": "<user>" },
+                "version": 1,
+            }),
+            value
+        );
+    }
+
+    #[test]
+    fn test_redact_short_user_name() {
+        let mut value = serde_json::json!({
+            "sysroot": "/home/a/ferrocene",
+            "path": ["/home/a/bin", "/opt/a", "/usr/local/bin"],
+            "environment": { "USER": "a", "CARGO_HOME": "/cache/a/.cargo" },
+            "binaries": {
+                "rustc": "rustc 1.77.0-ferrocene.a (abc1234a 2024-01-01)",
+                "host": "aarch64-unknown-linux-gnu",
+            },
+        });
+        redact(&mut value, &[("/home/a".into(), "<home>"), ("a".into(), "<user>")]);
+
+        assert_eq!(
+            serde_json::json!({
+                "sysroot": "<home>/ferrocene",
+                "path": ["<home>/bin", "/opt/<user>", "/usr/local/bin"],
+                "environment": { "USER": "<user>", "CARGO_HOME": "/cache/<user>/.cargo" },
+                "binaries": {
+                    "rustc": "rustc 1.77.0-ferrocene.a (abc1234a 2024-01-01)",
+                    "host": "aarch64-unknown-linux-gnu",
+                },
+            }),
+            value
+        );
+    }
+
+    #[test]
+    fn test_replace_whole_words() {
+        assert_eq!("<user>", replace_whole_words("ci", "ci", "<user>"));
+        assert_eq!("/srv/<user>/bin", replace_whole_words("/srv/ci/bin", "ci", "<user>"));
+        assert_eq!("<user>:<user>", replace_whole_words("ci:ci", "ci", "<user>"));
+        assert_eq!("owned by <user>", replace_whole_words("owned by ci", "ci", "<user>"));
+        assert_eq!("specific", replace_whole_words("specific", "ci", "<user>"));
+        assert_eq!("1.77.0-ci.1", replace_whole_words("1.77.0-ci.1", "ci", "<user>"));
+        assert_eq!("decided_ci", replace_whole_words("decided_ci", "ci", "<user>"));
+        assert_eq!("<home>/x", replace_whole_words("/home/ci/x", "/home/ci", "<home>"));
+        assert_eq!("/home/ci2", replace_whole_words("/home/ci2", "/home/ci", "<home>"));
+    }
+}
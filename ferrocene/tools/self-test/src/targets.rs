@@ -1,39 +1,50 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
+use crate::diagnostics::{target_group, Diagnostics};
 use crate::error::Error;
-use crate::linkers::Linker;
+use crate::linkers::{CCompiler, Linker};
 use crate::report::Reporter;
-use std::collections::{HashMap, HashSet};
+use crate::runners::{Runner, RunnerSpec};
+use crate::snapshot::{
+    InstalledTargetSnapshot, LibrarySnapshot, NullReporter, SnapshotError, TargetsSnapshot,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::Deref;
-use std::path::Path;
-
-static SUPPORTED_TARGETS: &[TargetSpec] = &[
-    TargetSpec { triple: "x86_64-unknown-linux-gnu", std: true, linker: Linker::HostCC },
-    TargetSpec {
-        triple: "aarch64-unknown-linux-gnu",
-        std: true,
-        linker: Linker::CrossCC(&["aarch64-linux-gnu-"]),
-    },
-    TargetSpec { triple: "aarch64-unknown-none", std: false, linker: Linker::BundledLld },
-];
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub(crate) struct TargetSpec {
     /// The rustc triple for the target
-    pub(crate) triple: &'static str,
+    pub(crate) triple: String,
     /// Indicates if the target provides libstd.
     pub(crate) std: bool,
     /// Indicates if the target requires a system C compiler as a linker driver
     /// to ensure system libs are on the library path, and if so, what possible
     /// filename prefixes should be checked to find a suitable compiler.
     pub(crate) linker: Linker,
+    /// How to execute programs for this target, if the host can't execute them natively.
+    pub(crate) runner: Option<RunnerSpec>,
 }
 
 #[derive(Debug)]
 pub(crate) struct Target {
-    pub(crate) spec: &'static TargetSpec,
+    pub(crate) spec: TargetSpec,
+    pub(crate) builtin_dirs: Vec<BuiltinDir>,
     pub(crate) rustflags: Vec<String>,
+    pub(crate) c_compiler: Option<CCompiler>,
+    pub(crate) runner: Option<Runner>,
+}
+
+/// A directory inside `lib/rustlib/<triple>/lib/builtin`, containing pre-built libraries that can
+/// be added to the library search path with `-L ferrocene-temp-builtin:<name>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BuiltinDir {
+    pub(crate) name: String,
+    /// The names of the libraries in the directory, sorted.
+    pub(crate) libraries: Vec<String>,
 }
 
 impl Deref for Target {
@@ -44,17 +55,120 @@ fn deref(&self) -> &Self::Target {
     }
 }
 
-pub(crate) fn check(reporter: &dyn Reporter, sysroot: &Path) -> Result<Vec<Target>, Error> {
+/// Checks the installed targets, returning the ones that passed the checks.
+pub(crate) fn check(
+    reporter: &dyn Reporter,
+    diagnostics: &Diagnostics<'_>,
+    sysroot: &Path,
+) -> Result<Vec<Target>, Error> {
+    // Without the target data and the list of installed targets no target can be checked.
+    let Some(mut specs) = diagnostics.check("targets", || load_target_specs(sysroot))? else {
+        return Ok(Vec::new());
+    };
+    let Some(installed) = diagnostics.check("targets", || find_installed_targets(sysroot))? else {
+        return Ok(Vec::new());
+    };
+
     let mut found = Vec::new();
-    for target in SUPPORTED_TARGETS {
-        match check_target(reporter, sysroot, target)? {
-            CheckTargetOutcome::Missing => {}
-            CheckTargetOutcome::Found => found.push(Target { spec: target, rustflags: Vec::new() }),
+    for triple in installed {
+        let Some(spec) = specs.remove(&triple) else {
+            reporter.skipped(&format!(
+                "target {triple} is installed but not described in {}",
+                targets_data_path(sysroot).display()
+            ));
+            continue;
+        };
+        let outcome =
+            diagnostics.check(&target_group(&triple), || check_target(reporter, sysroot, &spec))?;
+        match outcome {
+            Some(CheckTargetOutcome::Missing) | None => {}
+            Some(CheckTargetOutcome::Found(builtin_dirs)) => found.push(Target {
+                spec,
+                builtin_dirs,
+                rustflags: Vec::new(),
+                c_compiler: None,
+                runner: None,
+            }),
         }
     }
     Ok(found)
 }
 
+/// Gathers what the target checks look at, for the environment snapshot.
+///
+/// Also returns the specs of the installed targets described in the target data file.
+pub(crate) fn snapshot(sysroot: &Path) -> (TargetsSnapshot, Vec<TargetSpec>) {
+    let (mut specs, data_error) = match load_target_specs(sysroot) {
+        Ok(specs) => (specs, None),
+        Err(error) => (HashMap::new(), Some(SnapshotError::new(&error))),
+    };
+
+    let mut installed = Vec::new();
+    let mut described = Vec::new();
+    let discovery_error = match find_installed_targets(sysroot) {
+        Ok(triples) => {
+            for triple in triples {
+                let lib_dir = sysroot.join("lib").join("rustlib").join(&triple).join("lib");
+                let spec = specs.remove(&triple);
+                let error = spec
+                    .as_ref()
+                    .and_then(|spec| check_target(&NullReporter, sysroot, spec).err())
+                    .map(|error| SnapshotError::new(&error));
+                installed.push(InstalledTargetSnapshot {
+                    triple,
+                    described: spec.is_some(),
+                    libraries: snapshot_libraries(&lib_dir),
+                    builtin: snapshot_builtin_dirs(&lib_dir),
+                    error,
+                });
+                described.extend(spec);
+            }
+            None
+        }
+        Err(error) => Some(SnapshotError::new(&error)),
+    };
+
+    let snapshot = TargetsSnapshot {
+        data_file: targets_data_path(sysroot),
+        data_error,
+        installed,
+        discovery_error,
+    };
+    (snapshot, described)
+}
+
+/// Lists the files in a target library directory along with their hashes, sorted by name.
+fn snapshot_libraries(lib_dir: &Path) -> Vec<LibrarySnapshot> {
+    let Ok(entries) = lib_dir.read_dir() else { return Vec::new() };
+
+    let mut libraries = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| LibrarySnapshot {
+            file: entry.file_name().to_string_lossy().into_owned(),
+            sha256: std::fs::read(entry.path())
+                .ok()
+                .map(|contents| format!("{:x}", Sha256::digest(contents))),
+        })
+        .collect::<Vec<_>>();
+    libraries.sort_by(|a, b| a.file.cmp(&b.file));
+    libraries
+}
+
+/// Lists the libraries of each builtin directory inside a target library directory.
+fn snapshot_builtin_dirs(lib_dir: &Path) -> BTreeMap<String, Vec<LibrarySnapshot>> {
+    let Ok(entries) = lib_dir.join("builtin").read_dir() else { return BTreeMap::new() };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            (name, snapshot_libraries(&entry.path()))
+        })
+        .collect()
+}
+
 fn check_target(
     reporter: &dyn Reporter,
     sysroot: &Path,
@@ -75,15 +189,142 @@ fn check_target(
             &["core", "alloc"]
         },
     )?;
+    let builtin_dirs = find_builtin_dirs(target, &target_dir)?;
 
     reporter.success(&format!("target installed correctly: {}", target.triple));
-    Ok(CheckTargetOutcome::Found)
+    for builtin in &builtin_dirs {
+        reporter.success(&format!(
+            "builtin directory {} installed correctly for target {}",
+            builtin.name, target.triple
+        ));
+    }
+    Ok(CheckTargetOutcome::Found(builtin_dirs))
 }
 
 #[derive(Debug, PartialEq, Eq)]
 enum CheckTargetOutcome {
     Missing,
-    Found,
+    Found(Vec<BuiltinDir>),
+}
+
+/// Path of the data file describing how the self-test should check each target. The file is
+/// shipped alongside the `ferrocene-self-test` binary.
+fn targets_data_path(sysroot: &Path) -> PathBuf {
+    sysroot.join("share").join("ferrocene").join("self-test").join("targets.toml")
+}
+
+fn load_target_specs(sysroot: &Path) -> Result<HashMap<String, TargetSpec>, Error> {
+    let path = targets_data_path(sysroot);
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|error| Error::TargetsDataReadFailed { path: path.clone(), error })?;
+    parse_target_specs(&path, &contents)
+}
+
+fn parse_target_specs(path: &Path, contents: &str) -> Result<HashMap<String, TargetSpec>, Error> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case", deny_unknown_fields)]
+    struct TargetData {
+        std: bool,
+        linker: LinkerKind,
+        #[serde(default)]
+        cross_cc_prefixes: Vec<String>,
+        #[serde(default)]
+        bare_metal_gcc_args: Vec<String>,
+        runner: Option<RunnerData>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case", deny_unknown_fields)]
+    struct RunnerData {
+        command: Vec<String>,
+        #[serde(default)]
+        rustflags: Vec<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    enum LinkerKind {
+        BundledLld,
+        HostCc,
+        CrossCc,
+        BareMetalGcc,
+    }
+
+    let data: BTreeMap<String, TargetData> = toml::from_str(contents)
+        .map_err(|error| Error::TargetsDataParseFailed { path: path.into(), error })?;
+
+    let mut specs = HashMap::new();
+    for (triple, data) in data {
+        let invalid = |reason: &str| Error::InvalidTargetsData {
+            path: path.into(),
+            target: triple.clone(),
+            reason: reason.into(),
+        };
+        if !data.bare_metal_gcc_args.is_empty() && !matches!(data.linker, LinkerKind::BareMetalGcc)
+        {
+            return Err(invalid(
+                "bare-metal-gcc-args is only allowed with linker = bare-metal-gcc",
+            ));
+        }
+        let linker = match data.linker {
+            LinkerKind::CrossCc if data.cross_cc_prefixes.is_empty() => {
+                return Err(invalid("cross-cc-prefixes must not be empty with linker = cross-cc"));
+            }
+            LinkerKind::CrossCc => Linker::CrossCC(data.cross_cc_prefixes),
+            LinkerKind::BareMetalGcc if data.cross_cc_prefixes.is_empty() => {
+                return Err(invalid(
+                    "cross-cc-prefixes must not be empty with linker = bare-metal-gcc",
+                ));
+            }
+            LinkerKind::BareMetalGcc => Linker::BareMetalGCC {
+                prefixes: data.cross_cc_prefixes,
+                args: data.bare_metal_gcc_args,
+            },
+            _ if !data.cross_cc_prefixes.is_empty() => {
+                return Err(invalid(
+                    "cross-cc-prefixes is only allowed with linker = cross-cc or bare-metal-gcc",
+                ));
+            }
+            LinkerKind::BundledLld => Linker::BundledLld,
+            LinkerKind::HostCc => Linker::HostCC,
+        };
+        let runner = match data.runner {
+            Some(runner) if runner.command.is_empty() => {
+                return Err(invalid("the runner command must not be empty"));
+            }
+            Some(runner) => {
+                Some(RunnerSpec { command: runner.command, rustflags: runner.rustflags })
+            }
+            None => None,
+        };
+        specs.insert(triple.clone(), TargetSpec { triple, std: data.std, linker, runner });
+    }
+    Ok(specs)
+}
+
+/// Lists the targets installed in the sysroot, sorted by name.
+///
+/// All the directories inside `lib/rustlib` containing a `lib` subdirectory are considered to be
+/// installed targets. Other content of `lib/rustlib` (like the `etc` and `src` directories, or
+/// the installer metadata) is ignored.
+fn find_installed_targets(sysroot: &Path) -> Result<Vec<String>, Error> {
+    let rustlib = sysroot.join("lib").join("rustlib");
+    let map_err = |e| Error::TargetLibraryDiscoveryFailed { path: rustlib.clone(), error: e };
+
+    let mut found = Vec::new();
+    for entry in rustlib.read_dir().map_err(map_err)? {
+        let path = entry.map_err(map_err)?.path();
+        if !path.join("lib").is_dir() {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return Err(Error::NonUtf8Path { path });
+        };
+        found.push(name.to_string());
+    }
+
+    found.sort();
+    Ok(found)
 }
 
 fn check_libraries(target: &TargetSpec, target_dir: &Path, expected: &[&str]) -> Result<(), Error> {
@@ -92,14 +333,14 @@ fn check_libraries(target: &TargetSpec, target_dir: &Path, expected: &[&str]) ->
     let mut expected_to_find = expected.into_iter().map(|s| s.to_string()).collect::<HashSet<_>>();
     for (library, count) in find_libraries_in(&lib_dir)?.into_iter() {
         if count > 1 {
-            return Err(Error::DuplicateTargetLibrary { target: target.triple.into(), library });
+            return Err(Error::DuplicateTargetLibrary { target: target.triple.clone(), library });
         }
         expected_to_find.remove(&library);
     }
 
     if let Some(library) = expected_to_find.drain().next() {
         return Err(Error::TargetLibraryMissing {
-            target: target.triple.into(),
+            target: target.triple.clone(),
             library: (*library).into(),
         });
     }
@@ -107,6 +348,50 @@ fn check_libraries(target: &TargetSpec, target_dir: &Path, expected: &[&str]) ->
     Ok(())
 }
 
+/// Lists the builtin directories of a target, sorted by name, checking the libraries inside them.
+fn find_builtin_dirs(target: &TargetSpec, target_dir: &Path) -> Result<Vec<BuiltinDir>, Error> {
+    let builtin_root = target_dir.join("lib").join("builtin");
+    if !builtin_root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let map_err = |e| Error::TargetLibraryDiscoveryFailed { path: builtin_root.clone(), error: e };
+
+    let mut found = Vec::new();
+    for entry in builtin_root.read_dir().map_err(map_err)? {
+        let path = entry.map_err(map_err)?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return Err(Error::NonUtf8Path { path });
+        };
+
+        let mut libraries = Vec::new();
+        for (library, count) in find_libraries_in(&path)? {
+            if count > 1 {
+                return Err(Error::DuplicateBuiltinLibrary {
+                    target: target.triple.clone(),
+                    name: name.into(),
+                    library,
+                });
+            }
+            libraries.push(library);
+        }
+        if libraries.is_empty() {
+            return Err(Error::EmptyBuiltinDirectory {
+                target: target.triple.clone(),
+                name: name.into(),
+            });
+        }
+        libraries.sort();
+
+        found.push(BuiltinDir { name: name.into(), libraries });
+    }
+
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(found)
+}
+
 fn find_libraries_in(path: &Path) -> Result<HashMap<String, usize>, Error> {
     let map_err = |e| Error::TargetLibraryDiscoveryFailed { path: path.into(), error: e };
 
@@ -146,8 +431,12 @@ mod tests {
 
     #[test]
     fn test_check_target_std() {
-        let target =
-            TargetSpec { triple: "x86_64-unknown-linux-gnu", std: true, linker: Linker::HostCC };
+        let target = TargetSpec {
+            triple: "x86_64-unknown-linux-gnu".into(),
+            std: true,
+            linker: Linker::HostCC,
+            runner: None,
+        };
 
         let utils = TestUtils::new();
         utils
@@ -161,7 +450,7 @@ fn test_check_target_std() {
             .create();
 
         assert_eq!(
-            CheckTargetOutcome::Found,
+            CheckTargetOutcome::Found(Vec::new()),
             check_target(utils.reporter(), utils.sysroot(), &target).unwrap()
         );
         utils.assert_report_success("target installed correctly: x86_64-unknown-linux-gnu");
@@ -169,8 +458,12 @@ fn test_check_target_std() {
 
     #[test]
     fn test_check_target_no_std() {
-        let target =
-            TargetSpec { triple: "x86_64-unknown-none", std: false, linker: Linker::BundledLld };
+        let target = TargetSpec {
+            triple: "x86_64-unknown-none".into(),
+            std: false,
+            linker: Linker::BundledLld,
+            runner: None,
+        };
 
         let utils = TestUtils::new();
         utils
@@ -181,7 +474,7 @@ fn test_check_target_no_std() {
             .create();
 
         assert_eq!(
-            CheckTargetOutcome::Found,
+            CheckTargetOutcome::Found(Vec::new()),
             check_target(utils.reporter(), utils.sysroot(), &target).unwrap()
         );
         utils.assert_report_success("target installed correctly: x86_64-unknown-none");
@@ -189,8 +482,12 @@ fn test_check_target_no_std() {
 
     #[test]
     fn test_check_target_missing_library() {
-        let target =
-            TargetSpec { triple: "x86_64-unknown-none", std: false, linker: Linker::BundledLld };
+        let target = TargetSpec {
+            triple: "x86_64-unknown-none".into(),
+            std: false,
+            linker: Linker::BundledLld,
+            runner: None,
+        };
 
         let utils = TestUtils::new();
         utils.target("x86_64-unknown-none").lib("core", "0123456789abcdef").create();
@@ -207,8 +504,12 @@ fn test_check_target_missing_library() {
 
     #[test]
     fn test_check_target_duplicate_required_library() {
-        let target =
-            TargetSpec { triple: "x86_64-unknown-none", std: false, linker: Linker::BundledLld };
+        let target = TargetSpec {
+            triple: "x86_64-unknown-none".into(),
+            std: false,
+            linker: Linker::BundledLld,
+            runner: None,
+        };
 
         let utils = TestUtils::new();
         utils
@@ -230,8 +531,12 @@ fn test_check_target_duplicate_required_library() {
 
     #[test]
     fn test_check_target_duplicate_other_library() {
-        let target =
-            TargetSpec { triple: "x86_64-unknown-none", std: false, linker: Linker::BundledLld };
+        let target = TargetSpec {
+            triple: "x86_64-unknown-none".into(),
+            std: false,
+            linker: Linker::BundledLld,
+            runner: None,
+        };
 
         let utils = TestUtils::new();
         utils
@@ -252,6 +557,330 @@ fn test_check_target_duplicate_other_library() {
         utils.assert_no_reports();
     }
 
+    #[test]
+    fn test_check_target_builtin_dirs() {
+        let target = TargetSpec {
+            triple: "aarch64-unknown-none".into(),
+            std: false,
+            linker: Linker::BundledLld,
+            runner: None,
+        };
+
+        let utils = TestUtils::new();
+        utils
+            .target("aarch64-unknown-none")
+            .lib("core", "0123456789abcdef")
+            .lib("alloc", "0123456789abcdef")
+            .builtin_lib("oxidos", "oxidos", "0123456789abcdef")
+            .builtin_lib("oxidos", "bitflags", "0123456789abcdef")
+            .builtin_lib("oxidos-debug", "oxidos", "abcdef0123456789")
+            .create();
+        // Files directly inside the builtin directory are ignored.
+        let builtin_root = utils.sysroot().join("lib/rustlib/aarch64-unknown-none/lib/builtin");
+        std::fs::write(builtin_root.join("README"), b"").unwrap();
+
+        assert_eq!(
+            CheckTargetOutcome::Found(vec![
+                BuiltinDir {
+                    name: "oxidos".into(),
+                    libraries: vec!["bitflags".into(), "oxidos".into()],
+                },
+                BuiltinDir { name: "oxidos-debug".into(), libraries: vec!["oxidos".into()] },
+            ]),
+            check_target(utils.reporter(), utils.sysroot(), &target).unwrap()
+        );
+        utils.assert_report_success(
+            "builtin directory oxidos-debug installed correctly for target aarch64-unknown-none",
+        );
+        utils.assert_report_success(
+            "builtin directory oxidos installed correctly for target aarch64-unknown-none",
+        );
+        utils.assert_report_success("target installed correctly: aarch64-unknown-none");
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_check_target_empty_builtin_dir() {
+        let target = TargetSpec {
+            triple: "aarch64-unknown-none".into(),
+            std: false,
+            linker: Linker::BundledLld,
+            runner: None,
+        };
+
+        let utils = TestUtils::new();
+        utils
+            .target("aarch64-unknown-none")
+            .lib("core", "0123456789abcdef")
+            .lib("alloc", "0123456789abcdef")
+            .create();
+        let builtin_dir =
+            utils.sysroot().join("lib/rustlib/aarch64-unknown-none/lib/builtin/oxidos");
+        std::fs::create_dir_all(&builtin_dir).unwrap();
+        std::fs::write(builtin_dir.join("liboxidos.rlib.partial"), b"").unwrap();
+
+        match check_target(utils.reporter(), utils.sysroot(), &target) {
+            Err(Error::EmptyBuiltinDirectory { target, name }) => {
+                assert_eq!("aarch64-unknown-none", target);
+                assert_eq!("oxidos", name);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_check_target_duplicate_builtin_library() {
+        let target = TargetSpec {
+            triple: "aarch64-unknown-none".into(),
+            std: false,
+            linker: Linker::BundledLld,
+            runner: None,
+        };
+
+        let utils = TestUtils::new();
+        utils
+            .target("aarch64-unknown-none")
+            .lib("core", "0123456789abcdef")
+            .lib("alloc", "0123456789abcdef")
+            .builtin_lib("oxidos", "oxidos", "0123456789abcdef")
+            .builtin_lib("oxidos", "oxidos", "abcdef0123456789")
+            .create();
+
+        match check_target(utils.reporter(), utils.sysroot(), &target) {
+            Err(Error::DuplicateBuiltinLibrary { target, name, library }) => {
+                assert_eq!("aarch64-unknown-none", target);
+                assert_eq!("oxidos", name);
+                assert_eq!("oxidos", library);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_check() {
+        let utils = TestUtils::new();
+        utils.targets_data(
+            r#"
+                [x86_64-unknown-linux-gnu]
+                std = true
+                linker = "host-cc"
+
+                [aarch64-unknown-none]
+                std = false
+                linker = "bundled-lld"
+
+                [thumbv7em-none-eabi]
+                std = false
+                linker = "bundled-lld"
+            "#,
+        );
+        utils
+            .target("x86_64-unknown-linux-gnu")
+            .lib("core", "0123456789abcdef")
+            .lib("alloc", "0123456789abcdef")
+            .lib("std", "0123456789abcdef")
+            .lib("test", "0123456789abcdef")
+            .lib("proc_macro", "0123456789abcdef")
+            .create();
+        utils
+            .target("aarch64-unknown-none")
+            .lib("core", "0123456789abcdef")
+            .lib("alloc", "0123456789abcdef")
+            .create();
+        utils.target("riscv64gc-unknown-none-elf").lib("core", "0123456789abcdef").create();
+        // Other directories inside rustlib are not targets.
+        std::fs::create_dir_all(utils.sysroot().join("lib/rustlib/etc")).unwrap();
+
+        let targets = check(utils.reporter(), &utils.diagnostics(), utils.sysroot()).unwrap();
+        assert_eq!(
+            ["aarch64-unknown-none", "x86_64-unknown-linux-gnu"],
+            targets.iter().map(|t| t.triple.as_str()).collect::<Vec<_>>().as_slice()
+        );
+
+        utils.assert_report_success("target installed correctly: x86_64-unknown-linux-gnu");
+        utils.assert_report_skipped(&format!(
+            "target riscv64gc-unknown-none-elf is installed but not described in {}",
+            targets_data_path(utils.sysroot()).display()
+        ));
+        utils.assert_report_success("target installed correctly: aarch64-unknown-none");
+        utils.assert_no_reports();
+    }
+
+    #[test]
+    fn test_check_no_fail_fast() {
+        let utils = TestUtils::new();
+        utils.targets_data(
+            r#"
+                [aarch64-unknown-none]
+                std = false
+                linker = "bundled-lld"
+
+                [thumbv7em-none-eabi]
+                std = false
+                linker = "bundled-lld"
+
+                [x86_64-unknown-none]
+                std = false
+                linker = "bundled-lld"
+            "#,
+        );
+        utils.target("aarch64-unknown-none").lib("core", "0123456789abcdef").create();
+        utils
+            .target("thumbv7em-none-eabi")
+            .lib("core", "0123456789abcdef")
+            .lib("alloc", "0123456789abcdef")
+            .create();
+        utils
+            .target("x86_64-unknown-none")
+            .lib("core", "0123456789abcdef")
+            .lib("core", "abcdef0123456789")
+            .lib("alloc", "0123456789abcdef")
+            .create();
+
+        // All the targets are checked, even after the first one fails.
+        let diagnostics = Diagnostics::new(utils.reporter(), false);
+        let targets = check(utils.reporter(), &diagnostics, utils.sysroot()).unwrap();
+        assert_eq!(
+            ["thumbv7em-none-eabi"],
+            targets.iter().map(|t| t.triple.as_str()).collect::<Vec<_>>().as_slice()
+        );
+
+        utils.assert_report_error(9);
+        utils.assert_report_success("target installed correctly: thumbv7em-none-eabi");
+        utils.assert_report_error(8);
+        utils.assert_no_reports();
+        assert_eq!(Some(8), diagnostics.first_error_code());
+    }
+
+    #[test]
+    fn test_check_missing_targets_data() {
+        let utils = TestUtils::new();
+
+        match check(utils.reporter(), &utils.diagnostics(), utils.sysroot()) {
+            Err(Error::TargetsDataReadFailed { path, error }) => {
+                assert_eq!(targets_data_path(utils.sysroot()), path);
+                assert_eq!(std::io::ErrorKind::NotFound, error.kind());
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_target_specs() {
+        let specs = parse_target_specs(
+            Path::new("targets.toml"),
+            r#"
+                [x86_64-unknown-linux-gnu]
+                std = true
+                linker = "host-cc"
+
+                [aarch64-unknown-linux-gnu]
+                std = true
+                linker = "cross-cc"
+                cross-cc-prefixes = ["aarch64-linux-gnu-", "aarch64-none-linux-gnu-"]
+
+                [aarch64-unknown-linux-gnu.runner]
+                command = ["qemu-aarch64", "-L", "/usr/aarch64-linux-gnu"]
+
+                [aarch64-unknown-none]
+                std = false
+                linker = "bundled-lld"
+
+                [thumbv7em-none-eabihf]
+                std = false
+                linker = "bare-metal-gcc"
+                cross-cc-prefixes = ["arm-none-eabi-"]
+                bare-metal-gcc-args = ["-mcpu=cortex-m4", "-mfloat-abi=hard"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(4, specs.len());
+
+        let spec = &specs["x86_64-unknown-linux-gnu"];
+        assert_eq!("x86_64-unknown-linux-gnu", spec.triple);
+        assert!(spec.std);
+        assert!(matches!(spec.linker, Linker::HostCC));
+
+        let spec = &specs["aarch64-unknown-linux-gnu"];
+        assert!(spec.std);
+        assert_eq!(
+            Some(RunnerSpec {
+                command: vec!["qemu-aarch64".into(), "-L".into(), "/usr/aarch64-linux-gnu".into()],
+                rustflags: Vec::new(),
+            }),
+            spec.runner
+        );
+        match &spec.linker {
+            Linker::CrossCC(prefixes) => {
+                assert_eq!(&["aarch64-linux-gnu-", "aarch64-none-linux-gnu-"], prefixes.as_slice())
+            }
+            other => panic!("unexpected linker: {other:?}"),
+        }
+
+        let spec = &specs["aarch64-unknown-none"];
+        assert!(!spec.std);
+        assert!(matches!(spec.linker, Linker::BundledLld));
+
+        let spec = &specs["thumbv7em-none-eabihf"];
+        match &spec.linker {
+            Linker::BareMetalGCC { prefixes, args } => {
+                assert_eq!(&["arm-none-eabi-"], prefixes.as_slice());
+                assert_eq!(&["-mcpu=cortex-m4", "-mfloat-abi=hard"], args.as_slice());
+            }
+            other => panic!("unexpected linker: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_target_specs_invalid() {
+        let assert_invalid = |contents: &str, expected_reason: &str| match parse_target_specs(
+            Path::new("targets.toml"),
+            contents,
+        ) {
+            Err(Error::InvalidTargetsData { target, reason, .. }) => {
+                assert_eq!("foo", target);
+                assert_eq!(expected_reason, reason);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        };
+
+        assert_invalid(
+            "[foo]\nstd = true\nlinker = \"cross-cc\"",
+            "cross-cc-prefixes must not be empty with linker = cross-cc",
+        );
+        assert_invalid(
+            "[foo]\nstd = true\nlinker = \"host-cc\"\ncross-cc-prefixes = [\"foo-\"]",
+            "cross-cc-prefixes is only allowed with linker = cross-cc or bare-metal-gcc",
+        );
+        assert_invalid(
+            "[foo]\nstd = false\nlinker = \"bare-metal-gcc\"",
+            "cross-cc-prefixes must not be empty with linker = bare-metal-gcc",
+        );
+        assert_invalid(
+            "[foo]\nstd = false\nlinker = \"bundled-lld\"\nbare-metal-gcc-args = [\"-mthumb\"]",
+            "bare-metal-gcc-args is only allowed with linker = bare-metal-gcc",
+        );
+        assert_invalid(
+            "[foo]\nstd = true\nlinker = \"host-cc\"\nrunner = { command = [] }",
+            "the runner command must not be empty",
+        );
+
+        for contents in [
+            "[foo]\nstd = true",
+            "[foo]\nstd = true\nlinker = \"unknown\"",
+            "[foo]\nstd = true\nlinker = \"host-cc\"\nunknown-key = 1",
+        ] {
+            match parse_target_specs(Path::new("targets.toml"), contents) {
+                Err(Error::TargetsDataParseFailed { path, .. }) => {
+                    assert_eq!(Path::new("targets.toml"), path);
+                }
+                other => panic!("unexpected result: {other:?}"),
+            }
+        }
+    }
+
     #[test]
     fn test_find_libraries_in() {
         let dir = tempfile::tempdir().unwrap();
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
+use crate::diagnostics::Diagnostics;
 use crate::error::Error;
 use crate::report::Reporter;
 use crate::Environment;
@@ -49,13 +50,30 @@ pub(crate) fn bin<'a>(&'a self, name: &'a str) -> BinBuilder<'a> {
     }
 
     pub(crate) fn target<'a>(&'a self, name: &'a str) -> TargetBuilder<'a> {
-        TargetBuilder { utils: self, name, libraries: Vec::new() }
+        TargetBuilder { utils: self, name, libraries: Vec::new(), builtin_libraries: Vec::new() }
+    }
+
+    pub(crate) fn targets_data(&self, contents: &str) {
+        let path = self.sysroot().join("share").join("ferrocene").join("self-test");
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("targets.toml"), contents).unwrap();
+    }
+
+    pub(crate) fn package_manifest(&self, package: &str, contents: &str) {
+        let path = self.sysroot().join("share").join("criticaltrust").join("ferrocene");
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join(format!("{package}.json")), contents).unwrap();
     }
 
     pub(crate) fn reporter(&self) -> &dyn Reporter {
         &self.reports
     }
 
+    /// Diagnostics failing fast, like the self-test does by default.
+    pub(crate) fn diagnostics(&self) -> Diagnostics<'_> {
+        Diagnostics::new(self.reporter(), true)
+    }
+
     pub(crate) fn env(&self) -> &Environment {
         &self.environment
     }
@@ -76,6 +94,14 @@ pub(crate) fn assert_report_skipped(&self, message: &str) {
         );
     }
 
+    #[track_caller]
+    pub(crate) fn assert_report_error(&self, code: u8) {
+        assert_eq!(
+            Report::Error(code),
+            self.reports.reports.borrow_mut().pop().expect("no reports left")
+        );
+    }
+
     #[track_caller]
     pub(crate) fn assert_no_reports(&self) {
         assert!(self.reports.reports.borrow_mut().is_empty());
@@ -92,7 +118,7 @@ pub(crate) struct BinBuilder<'a> {
     exit: Option<i32>,
     expected_args: Option<&'a [&'a str]>,
     dest: BinaryDestinaton,
-    program: &'static str,
+    program: &'a str,
 }
 
 impl<'a> BinBuilder<'a> {
@@ -150,7 +176,7 @@ pub(crate) fn expected_args(mut self, args: &'a [&'a str]) -> Self {
     }
 
     #[must_use]
-    pub(crate) fn program_source(mut self, source: &'static str) -> Self {
+    pub(crate) fn program_source(mut self, source: &'a str) -> Self {
         self.program = source;
         self
     }
@@ -249,6 +275,7 @@ pub(crate) struct TargetBuilder<'a> {
     utils: &'a TestUtils,
     name: &'a str,
     libraries: Vec<(&'a str, &'a str)>,
+    builtin_libraries: Vec<(&'a str, &'a str, &'a str)>,
 }
 
 impl<'a> TargetBuilder<'a> {
@@ -258,6 +285,11 @@ pub(crate) fn lib(mut self, name: &'a str, hash: &'a str) -> Self {
         self
     }
 
+    pub(crate) fn builtin_lib(mut self, dir: &'a str, name: &'a str, hash: &'a str) -> Self {
+        self.builtin_libraries.push((dir, name, hash));
+        self
+    }
+
     pub(crate) fn create(self) {
         let target_dir = self.utils.sysroot().join("lib").join("rustlib").join(self.name);
         std::fs::create_dir_all(&target_dir).unwrap();
@@ -268,6 +300,12 @@ pub(crate) fn create(self) {
         for (name, hash) in self.libraries {
             std::fs::write(lib_dir.join(format!("lib{name}-{hash}.rlib")), b"").unwrap();
         }
+
+        for (dir, name, hash) in self.builtin_libraries {
+            let builtin_dir = lib_dir.join("builtin").join(dir);
+            std::fs::create_dir_all(&builtin_dir).unwrap();
+            std::fs::write(builtin_dir.join(format!("lib{name}-{hash}.rlib")), b"").unwrap();
+        }
     }
 }
 
@@ -292,8 +330,8 @@ fn info(&self, message: &str) {
         self.reports.borrow_mut().push(Report::Info(message.into()));
     }
 
-    fn error(&self, _: &Error) {
-        self.reports.borrow_mut().push(Report::Error);
+    fn error(&self, error: &Error) {
+        self.reports.borrow_mut().push(Report::Error(error.code()));
     }
 }
 
@@ -303,7 +341,7 @@ enum Report {
     Skipped(String),
     Note(String),
     Info(String),
-    Error,
+    Error(u8),
 }
 
 const BIN_PROGRAM: &str = r#"
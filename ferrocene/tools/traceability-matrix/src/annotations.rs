@@ -1,7 +1,8 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
-use crate::test_outcomes::TestOutcomes;
+use crate::libtest_annotations::{self, LibtestTest};
+use crate::test_outcomes::{self, IgnoredOn, TestOutcomes};
 use anyhow::Error;
 use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
@@ -21,6 +22,12 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             AnnotationSource::ParentDirectory { .. } => {
                 write!(f, "{} (from its parent directory)", self.test.display())
             }
+            AnnotationSource::TestFunction { name } => {
+                write!(f, "{} (test function {name})", self.test.display())
+            }
+            AnnotationSource::Doctest { line } => {
+                write!(f, "{} (doctest at line {line})", self.test.display())
+            }
         }
     }
 }
@@ -28,7 +35,8 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub(crate) struct Targets {
     pub(crate) executed: DisplayCommaSeparatedSet,
-    pub(crate) ignored: DisplayCommaSeparatedSet,
+    /// Targets the test was ignored on while being executed on others, with the reasons why.
+    pub(crate) ignored: IgnoredOn,
 }
 
 // created only so as to impl Display for Targets fields
@@ -42,17 +50,28 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub(crate) enum AnnotationSource {
     TestItself,
-    ParentDirectory { bulk_file: PathBuf },
+    ParentDirectory {
+        bulk_file: PathBuf,
+    },
     Makefile,
+    /// `#[test]` function of a Cargo package, with its full path inside of the crate.
+    TestFunction {
+        name: String,
+    },
+    /// Doctest of a Cargo package, with the line its code block starts at.
+    Doctest {
+        line: usize,
+    },
 }
 
 #[derive(Debug)]
 pub(crate) struct Annotations {
     pub(crate) ids: BTreeMap<String, BTreeSet<AnnotatedFile>>,
-    pub(crate) ignored_tests: BTreeMap<String, BTreeSet<String>>,
+    pub(crate) ignored_tests: BTreeMap<String, IgnoredOn>,
     pub(crate) considers_ignored_tests: bool,
 }
 
@@ -165,13 +184,8 @@ struct JsonAnnotation {
                     );
                 };
 
-                let ignored = DisplayCommaSeparatedSet(
-                    outcomes
-                        .ignored_tests
-                        .get(&relative_file)
-                        .unwrap_or(&BTreeSet::new())
-                        .to_owned(),
-                );
+                let ignored =
+                    outcomes.ignored_tests.get(&relative_file).cloned().unwrap_or_default();
                 let executed = DisplayCommaSeparatedSet(
                     outcomes
                         .executed_tests
@@ -185,18 +199,145 @@ struct JsonAnnotation {
                 self.ids.entry(annotation.id.clone()).or_default().insert(annotated_file);
             }
         }
-        if !unknown_tests.is_empty() {
-            eprintln!(
-                "The following tests have no entries in test_outcomes, \
-                likely due to test_outcomes being stale:"
-            );
-            for test in unknown_tests {
-                eprintln!("  - {test}");
+        print_unknown_tests(unknown_tests);
+
+        Ok(())
+    }
+
+    /// Loads the annotations of the tests executed by libtest in a Cargo package, like unit tests
+    /// and doctests, which are not collected by compiletest.
+    pub(crate) fn load_cargo_package(
+        &mut self,
+        krate: &str,
+        package_dir: &Path,
+        src_base: &Path,
+        test_outcomes: Option<&TestOutcomes>,
+    ) -> Result<(), Error> {
+        if test_outcomes.is_none() {
+            self.considers_ignored_tests = false;
+        }
+
+        let mut unknown_tests = BTreeSet::new();
+        for found in libtest_annotations::collect(package_dir)? {
+            let relative_to = |base: &Path| -> Result<String, Error> {
+                let path = found.file.strip_prefix(base).unwrap_or(&found.file);
+                Ok(path
+                    .to_str()
+                    .ok_or_else(|| anyhow::anyhow!("non-utf8 path: {}", path.display()))?
+                    .to_string())
+            };
+            let relative_file = relative_to(src_base)?;
+
+            let (key, source) = match found.test {
+                LibtestTest::Function { name } => (
+                    test_outcomes::test_function_key(krate, &name),
+                    AnnotationSource::TestFunction { name },
+                ),
+                LibtestTest::Doctest { line } => (
+                    test_outcomes::doctest_key(krate, &relative_to(package_dir)?, line),
+                    AnnotationSource::Doctest { line },
+                ),
+            };
+
+            let targets = if let Some(outcomes) = test_outcomes {
+                // Do not consider annotations from ignored tests.
+                let Some(executed) = outcomes.executed_tests.get(&key) else {
+                    if let Some(targets) = outcomes.ignored_tests.get(&key) {
+                        // The report links to ignored tests, so record the file they are in.
+                        let ignored = self.ignored_tests.entry(relative_file).or_default();
+                        for (target, reasons) in targets {
+                            ignored
+                                .entry(target.clone())
+                                .or_default()
+                                .extend(reasons.iter().cloned());
+                        }
+                    } else {
+                        unknown_tests.insert(key);
+                    }
+                    continue;
+                };
+                Targets {
+                    executed: DisplayCommaSeparatedSet(executed.clone()),
+                    ignored: outcomes.ignored_tests.get(&key).cloned().unwrap_or_default(),
+                }
+            } else {
+                Targets::default()
+            };
+
+            for id in found.annotations {
+                let annotated_file = AnnotatedFile {
+                    test: relative_file.clone().into(),
+                    source: source.clone(),
+                    targets: targets.clone(),
+                };
+                self.ids.entry(id).or_default().insert(annotated_file);
             }
         }
+        print_unknown_tests(unknown_tests);
 
         Ok(())
     }
+
+    /// All the targets tests were executed or ignored on, according to the test outcomes.
+    pub(crate) fn targets(&self) -> BTreeSet<String> {
+        let mut targets = BTreeSet::new();
+        for file in self.ids.values().flatten() {
+            targets.extend(file.targets.executed.0.iter().cloned());
+            targets.extend(file.targets.ignored.keys().cloned());
+        }
+        for ignored in self.ignored_tests.values() {
+            targets.extend(ignored.keys().cloned());
+        }
+        targets
+    }
+
+    /// Restricts the annotations to the tests executed on the given target, as coverage is
+    /// qualified separately for each target.
+    pub(crate) fn for_target(&self, target: &str) -> Annotations {
+        let mut ids = BTreeMap::new();
+        for (id, files) in &self.ids {
+            let files = files
+                .iter()
+                .filter(|file| file.targets.executed.0.contains(target))
+                .map(|file| AnnotatedFile {
+                    targets: Targets {
+                        executed: DisplayCommaSeparatedSet(BTreeSet::from([target.to_string()])),
+                        ignored: ignored_on(&file.targets.ignored, target),
+                    },
+                    ..file.clone()
+                })
+                .collect::<BTreeSet<_>>();
+            if !files.is_empty() {
+                ids.insert(id.clone(), files);
+            }
+        }
+
+        let ignored_tests = self
+            .ignored_tests
+            .iter()
+            .map(|(test, targets)| (test.clone(), ignored_on(targets, target)))
+            .filter(|(_, targets)| !targets.is_empty())
+            .collect();
+
+        Annotations { ids, ignored_tests, considers_ignored_tests: self.considers_ignored_tests }
+    }
+}
+
+/// Restricts the targets a test was ignored on to the given one.
+fn ignored_on(targets: &IgnoredOn, target: &str) -> IgnoredOn {
+    targets.get_key_value(target).map(|(t, r)| (t.clone(), r.clone())).into_iter().collect()
+}
+
+fn print_unknown_tests(unknown_tests: BTreeSet<String>) {
+    if !unknown_tests.is_empty() {
+        eprintln!(
+            "The following tests have no entries in test_outcomes, \
+            likely due to test_outcomes being stale:"
+        );
+        for test in unknown_tests {
+            eprintln!("  - {test}");
+        }
+    }
 }
 
 #[cfg(test)]
@@ -216,8 +357,8 @@ fn test_load_file() -> Result<(), Error> {
                 ("example/foobar.rs".into(), BTreeSet::default()),
             ]),
             ignored_tests: BTreeMap::from([
-                ("example/ignored.rs".into(), BTreeSet::default()),
-                ("example/ignored-without-annotations.rs".into(), BTreeSet::default()),
+                ("example/ignored.rs".into(), BTreeMap::default()),
+                ("example/ignored-without-annotations.rs".into(), BTreeMap::default()),
             ]),
         });
 
@@ -244,7 +385,7 @@ fn test_load_file() -> Result<(), Error> {
             ]),
             annotations.ids,
         );
-        let expected = BTreeMap::from([("example/ignored.rs".into(), BTreeSet::default())]);
+        let expected = BTreeMap::from([("example/ignored.rs".into(), BTreeMap::default())]);
         assert_eq!(expected, annotations.ignored_tests);
 
         Ok(())
@@ -269,8 +410,8 @@ fn test_load_directory() -> Result<(), Error> {
                 ("example/foobar.rs".into(), BTreeSet::default()),
             ]),
             ignored_tests: BTreeMap::from([
-                ("example/ignored.rs".into(), BTreeSet::default()),
-                ("example/ignored-without-annotations.rs".into(), BTreeSet::default()),
+                ("example/ignored.rs".into(), BTreeMap::default()),
+                ("example/ignored-without-annotations.rs".into(), BTreeMap::default()),
             ]),
         });
 
@@ -303,12 +444,128 @@ fn test_load_directory() -> Result<(), Error> {
             ]),
             annotations.ids,
         );
-        let expected = BTreeMap::from([("example/ignored.rs".into(), BTreeSet::default())]);
+        let expected = BTreeMap::from([("example/ignored.rs".into(), BTreeMap::default())]);
         assert_eq!(expected, annotations.ignored_tests,);
 
         Ok(())
     }
 
+    #[test]
+    fn test_load_file_ignored_on_some_targets() -> Result<(), Error> {
+        let file = NamedTempFile::new()?;
+        std::fs::write(file.path(), annotations_file_2()?)?;
+
+        let x86 = "x86_64-unknown-linux-gnu";
+        let arm = "aarch64-unknown-none";
+        let ignored_on = BTreeMap::from([(arm.into(), BTreeSet::from(["no MMU".into()]))]);
+        let outcomes = TestOutcomes {
+            executed_tests: BTreeMap::from([(
+                "example/baz.rs".into(),
+                BTreeSet::from([x86.into()]),
+            )]),
+            ignored_tests: BTreeMap::from([("example/baz.rs".into(), ignored_on.clone())]),
+        };
+
+        let mut annotations = Annotations::new();
+        annotations.load_file(file.path(), Path::new("/base"), Some(&outcomes))?;
+
+        let baz = AnnotatedFile {
+            targets: Targets {
+                executed: DisplayCommaSeparatedSet(BTreeSet::from([x86.into()])),
+                ignored: ignored_on,
+            },
+            ..test_itself("example/baz.rs")
+        };
+        assert_eq!(
+            BTreeMap::from([
+                ("bar".into(), BTreeSet::from([baz.clone()])),
+                ("baz".into(), BTreeSet::from([baz])),
+            ]),
+            annotations.ids
+        );
+        // The reasons are attached to the file, as it's not ignored on every target.
+        assert_eq!(BTreeMap::new(), annotations.ignored_tests);
+        assert_eq!(BTreeSet::from([arm.to_string(), x86.to_string()]), annotations.targets());
+        assert_eq!(BTreeMap::new(), annotations.for_target(arm).ids);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_target() {
+        let x86 = "x86_64-unknown-linux-gnu";
+        let arm = "aarch64-unknown-none";
+        let reason = |reason: &str| BTreeSet::from([reason.to_string()]);
+        let on = |test: &str, executed: &[&str], ignored: &[&str]| AnnotatedFile {
+            targets: Targets {
+                executed: DisplayCommaSeparatedSet(
+                    executed.iter().map(|t| t.to_string()).collect(),
+                ),
+                ignored: ignored.iter().map(|t| (t.to_string(), reason("no MMU"))).collect(),
+            },
+            ..test_itself(test)
+        };
+
+        let mut annotations = Annotations::new();
+        annotations.ids.insert(
+            "both".into(),
+            BTreeSet::from([
+                on("example/both.rs", &[x86, arm], &[]),
+                on("example/x86.rs", &[x86], &[arm]),
+            ]),
+        );
+        annotations
+            .ids
+            .insert("x86".into(), BTreeSet::from([on("example/x86.rs", &[x86], &[arm])]));
+        annotations
+            .ignored_tests
+            .insert("example/ignored.rs".into(), BTreeMap::from([(arm.into(), reason("no MMU"))]));
+        annotations.ignored_tests.insert(
+            "example/ignored-everywhere.rs".into(),
+            BTreeMap::from([(arm.into(), reason("no MMU")), (x86.into(), reason("flaky"))]),
+        );
+
+        assert_eq!(BTreeSet::from([arm.to_string(), x86.to_string()]), annotations.targets());
+
+        let arm_only = annotations.for_target(arm);
+        assert_eq!(
+            BTreeMap::from([("both".into(), BTreeSet::from([on("example/both.rs", &[arm], &[])]))]),
+            arm_only.ids
+        );
+        assert_eq!(
+            BTreeMap::from([
+                ("example/ignored.rs".into(), BTreeMap::from([(arm.into(), reason("no MMU"))])),
+                (
+                    "example/ignored-everywhere.rs".into(),
+                    BTreeMap::from([(arm.into(), reason("no MMU"))])
+                ),
+            ]),
+            arm_only.ignored_tests
+        );
+
+        let x86_only = annotations.for_target(x86);
+        assert_eq!(
+            BTreeMap::from([
+                (
+                    "both".into(),
+                    BTreeSet::from([
+                        on("example/both.rs", &[x86], &[]),
+                        on("example/x86.rs", &[x86], &[])
+                    ])
+                ),
+                ("x86".into(), BTreeSet::from([on("example/x86.rs", &[x86], &[])])),
+            ]),
+            x86_only.ids
+        );
+        assert_eq!(
+            BTreeMap::from([(
+                "example/ignored-everywhere.rs".into(),
+                BTreeMap::from([(x86.into(), reason("flaky"))])
+            )]),
+            x86_only.ignored_tests
+        );
+    }
+
     fn test_itself(path: impl AsRef<Path>) -> AnnotatedFile {
         AnnotatedFile {
             test: path.as_ref().into(),
@@ -0,0 +1,358 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Comparison between the traceability matrices of two releases, showing which elements gained or
+//! lost test coverage, and which elements were added to or removed from the documentation.
+
+use crate::matrix::{Element, ElementKind, LinkStatus, MatrixAnalysis, TraceabilityMatrix};
+use anyhow::Error;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct MatrixDiff {
+    pub(crate) kinds: Vec<KindDiff>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct KindDiff {
+    pub(crate) kind: &'static ElementKind,
+    pub(crate) changed: Vec<StatusChange>,
+    pub(crate) added: Vec<ElementStatus>,
+    pub(crate) removed: Vec<ElementStatus>,
+}
+
+impl KindDiff {
+    pub(crate) fn gained_coverage(&self) -> usize {
+        self.changed.iter().filter(|c| c.gained_coverage()).count()
+    }
+
+    pub(crate) fn lost_coverage(&self) -> usize {
+        self.changed.iter().filter(|c| !c.gained_coverage()).count()
+    }
+}
+
+/// Element present in both releases, whose link status changed between them.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct StatusChange {
+    pub(crate) element: Element,
+    pub(crate) before: LinkStatus,
+    pub(crate) after: LinkStatus,
+}
+
+impl StatusChange {
+    pub(crate) fn gained_coverage(&self) -> bool {
+        self.after > self.before
+    }
+}
+
+/// Element present in only one of the releases, with its link status in that release.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct ElementStatus {
+    pub(crate) element: Element,
+    pub(crate) status: LinkStatus,
+}
+
+pub(crate) fn compare(before: &TraceabilityMatrix, after: &TraceabilityMatrix) -> MatrixDiff {
+    let kinds = before
+        .analyses_by_kind()
+        .zip(after.analyses_by_kind())
+        .map(|(before, after)| {
+            assert_eq!(before.kind, after.kind);
+            compare_analysis(before, after)
+        })
+        .collect();
+    MatrixDiff { kinds }
+}
+
+fn compare_analysis(before: &MatrixAnalysis, after: &MatrixAnalysis) -> KindDiff {
+    let before = statuses(before);
+    let after_statuses = statuses(after);
+
+    let mut diff =
+        KindDiff { kind: after.kind, changed: Vec::new(), added: Vec::new(), removed: Vec::new() };
+    for (id, (element, status)) in &after_statuses {
+        match before.get(id) {
+            Some((_, before_status)) if before_status == status => {}
+            Some((_, before_status)) => diff.changed.push(StatusChange {
+                element: (*element).clone(),
+                before: *before_status,
+                after: *status,
+            }),
+            None => diff.added.push(ElementStatus { element: (*element).clone(), status: *status }),
+        }
+    }
+    for (id, (element, status)) in &before {
+        if !after_statuses.contains_key(id) {
+            diff.removed.push(ElementStatus { element: (*element).clone(), status: *status });
+        }
+    }
+
+    diff.changed.sort_by(|a, b| a.element.cmp(&b.element));
+    diff.added.sort_by(|a, b| a.element.cmp(&b.element));
+    diff.removed.sort_by(|a, b| a.element.cmp(&b.element));
+    diff
+}
+
+fn statuses(analysis: &MatrixAnalysis) -> BTreeMap<&str, (&Element, LinkStatus)> {
+    analysis
+        .elements()
+        .map(|(element, status, _)| (element.id.as_str(), (element, status)))
+        .collect()
+}
+
+pub(crate) fn to_json(diff: &MatrixDiff) -> Result<String, Error> {
+    #[derive(Serialize)]
+    struct JsonDiff<'a> {
+        kinds: Vec<JsonKindDiff<'a>>,
+    }
+
+    #[derive(Serialize)]
+    struct JsonKindDiff<'a> {
+        kind: &'static str,
+        changed: Vec<JsonStatusChange<'a>>,
+        added: Vec<JsonElementStatus<'a>>,
+        removed: Vec<JsonElementStatus<'a>>,
+    }
+
+    #[derive(Serialize)]
+    struct JsonStatusChange<'a> {
+        #[serde(flatten)]
+        element: JsonElement<'a>,
+        before: LinkStatus,
+        after: LinkStatus,
+    }
+
+    #[derive(Serialize)]
+    struct JsonElementStatus<'a> {
+        #[serde(flatten)]
+        element: JsonElement<'a>,
+        status: LinkStatus,
+    }
+
+    #[derive(Serialize)]
+    struct JsonElement<'a> {
+        id: &'a str,
+        name: String,
+        documentation: &'a str,
+        page: &'a str,
+        link: &'a str,
+    }
+
+    impl<'a> From<&'a Element> for JsonElement<'a> {
+        fn from(element: &'a Element) -> Self {
+            JsonElement {
+                id: &element.id,
+                name: element.name(),
+                documentation: &element.page.documentation,
+                page: &element.page.name,
+                link: &element.link,
+            }
+        }
+    }
+
+    fn to_element_status(e: &ElementStatus) -> JsonElementStatus<'_> {
+        JsonElementStatus { element: (&e.element).into(), status: e.status }
+    }
+
+    let json = JsonDiff {
+        kinds: diff
+            .kinds
+            .iter()
+            .map(|kind| JsonKindDiff {
+                kind: kind.kind.singular,
+                changed: kind
+                    .changed
+                    .iter()
+                    .map(|c| JsonStatusChange {
+                        element: (&c.element).into(),
+                        before: c.before,
+                        after: c.after,
+                    })
+                    .collect(),
+                added: kind.added.iter().map(to_element_status).collect(),
+                removed: kind.removed.iter().map(to_element_status).collect(),
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_string_pretty(&json)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::{
+        Link, LinkTest, Page, ELEMENT_KIND_CLI_OPTION, ELEMENT_KIND_PARAGRAPH, ELEMENT_KIND_SECTION,
+    };
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_compare() {
+        let before = matrix(
+            &[("fls_gained", LinkStatus::Unlinked), ("fls_partial", LinkStatus::Unlinked)],
+            &[
+                ("fls_lost", LinkStatus::Linked),
+                ("fls_same", LinkStatus::Linked),
+                ("fls_removed", LinkStatus::PartiallyLinked),
+            ],
+        );
+        let after = matrix(
+            &[("fls_gained", LinkStatus::Linked), ("fls_partial", LinkStatus::PartiallyLinked)],
+            &[
+                ("fls_lost", LinkStatus::Unlinked),
+                ("fls_same", LinkStatus::Linked),
+                ("fls_added", LinkStatus::Unlinked),
+            ],
+        );
+
+        let diff = compare(&before, &after);
+        assert_eq!(
+            MatrixDiff {
+                kinds: vec![
+                    KindDiff {
+                        kind: &ELEMENT_KIND_SECTION,
+                        changed: vec![
+                            StatusChange {
+                                element: element(&ELEMENT_KIND_SECTION, "fls_gained"),
+                                before: LinkStatus::Unlinked,
+                                after: LinkStatus::Linked,
+                            },
+                            StatusChange {
+                                element: element(&ELEMENT_KIND_SECTION, "fls_partial"),
+                                before: LinkStatus::Unlinked,
+                                after: LinkStatus::PartiallyLinked,
+                            },
+                        ],
+                        added: vec![],
+                        removed: vec![],
+                    },
+                    KindDiff {
+                        kind: &ELEMENT_KIND_PARAGRAPH,
+                        changed: vec![StatusChange {
+                            element: element(&ELEMENT_KIND_PARAGRAPH, "fls_lost"),
+                            before: LinkStatus::Linked,
+                            after: LinkStatus::Unlinked,
+                        }],
+                        added: vec![ElementStatus {
+                            element: element(&ELEMENT_KIND_PARAGRAPH, "fls_added"),
+                            status: LinkStatus::Unlinked,
+                        }],
+                        removed: vec![ElementStatus {
+                            element: element(&ELEMENT_KIND_PARAGRAPH, "fls_removed"),
+                            status: LinkStatus::PartiallyLinked,
+                        }],
+                    },
+                    KindDiff {
+                        kind: &ELEMENT_KIND_CLI_OPTION,
+                        changed: vec![],
+                        added: vec![],
+                        removed: vec![],
+                    },
+                ],
+            },
+            diff
+        );
+        assert_eq!(2, diff.kinds[0].gained_coverage());
+        assert_eq!(0, diff.kinds[0].lost_coverage());
+        assert_eq!(1, diff.kinds[1].lost_coverage());
+    }
+
+    #[test]
+    fn test_to_json() -> Result<(), Error> {
+        let before = matrix(&[], &[("fls_lost", LinkStatus::Linked)]);
+        let after =
+            matrix(&[("fls_added", LinkStatus::Linked)], &[("fls_lost", LinkStatus::Unlinked)]);
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(&compare(&before, &after))?)?;
+        assert_eq!(
+            serde_json::json!({
+                "kinds": [
+                    {
+                        "kind": "section",
+                        "changed": [],
+                        "added": [
+                            {
+                                "id": "fls_added",
+                                "name": "fls_added",
+                                "documentation": "FLS",
+                                "page": "Example",
+                                "link": "example.html#fls_added",
+                                "status": "linked",
+                            },
+                        ],
+                        "removed": [],
+                    },
+                    {
+                        "kind": "paragraph",
+                        "changed": [
+                            {
+                                "id": "fls_lost",
+                                "name": "fls_lost",
+                                "documentation": "FLS",
+                                "page": "Example",
+                                "link": "example.html#fls_lost",
+                                "before": "linked",
+                                "after": "unlinked",
+                            },
+                        ],
+                        "added": [],
+                        "removed": [],
+                    },
+                    {
+                        "kind": "command line option",
+                        "changed": [],
+                        "added": [],
+                        "removed": [],
+                    },
+                ],
+            }),
+            json
+        );
+
+        Ok(())
+    }
+
+    fn matrix(
+        sections: &[(&str, LinkStatus)],
+        paragraphs: &[(&str, LinkStatus)],
+    ) -> TraceabilityMatrix {
+        let analysis = |kind: &'static ElementKind, elements: &[(&str, LinkStatus)]| {
+            let mut analysis = MatrixAnalysis::new(kind);
+            for (id, status) in elements {
+                let element = element(kind, id);
+                let link = || Link {
+                    element: element.clone(),
+                    tests: vec![LinkTest::Informational],
+                    untested_targets: BTreeSet::new(),
+                };
+                match status {
+                    LinkStatus::Unlinked => analysis.unlinked.insert(element.clone()),
+                    LinkStatus::PartiallyLinked => analysis.partially_linked.insert(link()),
+                    LinkStatus::Linked => analysis.linked.insert(link()),
+                };
+            }
+            analysis
+        };
+        TraceabilityMatrix {
+            sections: analysis(&ELEMENT_KIND_SECTION, sections),
+            paragraphs: analysis(&ELEMENT_KIND_PARAGRAPH, paragraphs),
+            cli_options: analysis(&ELEMENT_KIND_CLI_OPTION, &[]),
+            unknown_annotations: Vec::new(),
+        }
+    }
+
+    fn element(kind: &'static ElementKind, id: &str) -> Element {
+        Element {
+            kind,
+            number: None,
+            page: Page {
+                documentation: "FLS".into(),
+                name: "Example".into(),
+                link: "example.html".into(),
+            },
+            title: Some(id.into()),
+            id: id.into(),
+            link: format!("example.html#{id}"),
+        }
+    }
+}
@@ -1,14 +1,25 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
-use anyhow::Error;
+use crate::matrix::{ElementKind, ELEMENT_KINDS};
+use anyhow::{Context, Error};
 use serde::Deserialize;
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 pub(crate) struct Documentation {
     pub(crate) name: String,
     pub(crate) url: String,
     pub(crate) ids: TraceabilityIds,
+    pub(crate) kinds: Vec<&'static ElementKind>,
+    /// IDs files of previous versions of the documentation, along with their path.
+    pub(crate) history: Vec<(PathBuf, TraceabilityIds)>,
+}
+
+impl Documentation {
+    pub(crate) fn contributes(&self, kind: &'static ElementKind) -> bool {
+        self.kinds.contains(&kind)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -52,10 +63,198 @@ pub(crate) struct CliOption {
     pub(crate) link: String,
 }
 
-pub(crate) fn load(name: &str, path: &Path, url: &str) -> Result<Documentation, Error> {
-    Ok(Documentation {
-        name: name.into(),
-        url: url.into(),
-        ids: serde_json::from_slice(&std::fs::read(path)?)?,
-    })
+/// Loads the documentations listed in a configuration file like this:
+///
+/// ```toml
+/// [[documentation]]
+/// name = "FLS"
+/// # Relative paths are resolved from the directory containing this file.
+/// ids = "specification/paragraph-ids.json"
+/// url = "../specification"
+/// # Kinds of elements to include in the matrix. Optional, defaults to all of them.
+/// kinds = ["section", "paragraph"]
+/// # IDs files of previous versions of the documentation, used to provide suggestions when tests
+/// # are annotated with IDs that don't exist anymore. Optional.
+/// history = ["history/paragraph-ids-1.json"]
+/// ```
+///
+/// Tests can annotate elements either with their ID, or with the ID prefixed by the name of the
+/// documentation (like `FLS:fls_xxxxxxxxxxxx`).
+pub(crate) fn load_config(path: &Path) -> Result<Vec<Documentation>, Error> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Config {
+        #[serde(rename = "documentation")]
+        documentations: Vec<ConfigDocumentation>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct ConfigDocumentation {
+        name: String,
+        ids: PathBuf,
+        url: String,
+        kinds: Option<Vec<String>>,
+        #[serde(default)]
+        history: Vec<PathBuf>,
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read documentations from {}", path.display()))?;
+    let config: Config = toml::from_str(&contents)
+        .with_context(|| format!("failed to parse documentations from {}", path.display()))?;
+
+    let mut names = HashSet::new();
+    let mut documentations = Vec::new();
+    for documentation in config.documentations {
+        let name = documentation.name;
+        if name.is_empty() || name.contains(':') {
+            anyhow::bail!("invalid documentation name: {name:?}");
+        }
+        if !names.insert(name.clone()) {
+            anyhow::bail!("documentation {name} is configured multiple times");
+        }
+
+        let kinds = match documentation.kinds {
+            Some(kinds) => kinds
+                .iter()
+                .map(|kind| {
+                    ELEMENT_KINDS.iter().copied().find(|k| k.singular == kind).ok_or_else(|| {
+                        anyhow::anyhow!("unknown element kind in documentation {name}: {kind}")
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            None => ELEMENT_KINDS.to_vec(),
+        };
+
+        let load_ids = |ids_path: &Path| -> Result<TraceabilityIds, Error> {
+            let ids_path = match path.parent() {
+                Some(parent) => parent.join(ids_path),
+                None => ids_path.into(),
+            };
+            serde_json::from_slice(&std::fs::read(&ids_path)?)
+                .with_context(|| format!("failed to read ids from {}", ids_path.display()))
+        };
+        let ids = load_ids(&documentation.ids)?;
+        let history = documentation
+            .history
+            .into_iter()
+            .map(|path| Ok((path.clone(), load_ids(&path)?)))
+            .collect::<Result<_, Error>>()?;
+
+        documentations.push(Documentation { name, url: documentation.url, ids, kinds, history });
+    }
+    Ok(documentations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::{ELEMENT_KIND_CLI_OPTION, ELEMENT_KIND_PARAGRAPH};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_config() -> Result<(), Error> {
+        let dir = TempDir::new()?;
+        std::fs::create_dir(dir.path().join("sm"))?;
+        std::fs::write(dir.path().join("fls.json"), r#"{"documents": []}"#)?;
+        std::fs::write(dir.path().join("sm/ids.json"), r#"{"documents": []}"#)?;
+
+        let config = dir.path().join("documentations.toml");
+        std::fs::write(
+            &config,
+            r#"
+                [[documentation]]
+                name = "FLS"
+                ids = "fls.json"
+                url = "../specification"
+
+                [[documentation]]
+                name = "SM"
+                ids = "sm/ids.json"
+                url = "../safety-manual"
+                kinds = ["paragraph", "command line option"]
+                history = ["fls.json"]
+            "#,
+        )?;
+
+        let documentations = load_config(&config)?;
+        assert_eq!(2, documentations.len());
+        assert_eq!("FLS", documentations[0].name);
+        assert_eq!("../specification", documentations[0].url);
+        assert_eq!(ELEMENT_KINDS, documentations[0].kinds.as_slice());
+        assert_eq!("SM", documentations[1].name);
+        assert_eq!(
+            vec![&ELEMENT_KIND_PARAGRAPH, &ELEMENT_KIND_CLI_OPTION],
+            documentations[1].kinds
+        );
+        assert!(documentations[0].history.is_empty());
+        assert_eq!(
+            vec![PathBuf::from("fls.json")],
+            documentations[1].history.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_generated_by_bootstrap() -> Result<(), Error> {
+        // Bootstrap writes the configuration in the build directory, with absolute paths for the
+        // IDs files and for the history checked into the repository.
+        let src = TempDir::new()?;
+        let build = TempDir::new()?;
+        let ids = build.path().join("paragraph-ids.json");
+        let history = src.path().join("history").join("fls-1.0.json");
+        std::fs::create_dir(history.parent().unwrap())?;
+        std::fs::write(&ids, r#"{"documents": []}"#)?;
+        std::fs::write(
+            &history,
+            r#"{"documents": [{"title": "Old", "link": "old.html", "informational": false}]}"#,
+        )?;
+
+        let config = build.path().join("traceability-documentations.toml");
+        std::fs::write(
+            &config,
+            format!(
+                "[[documentation]]\nname = \"FLS\"\nids = {}\nurl = \"../specification\"\n\
+                 history = [{}]\n",
+                toml::Value::String(ids.display().to_string()),
+                toml::Value::String(history.display().to_string()),
+            ),
+        )?;
+
+        let documentations = load_config(&config)?;
+        assert_eq!(1, documentations[0].history.len());
+        let (path, ids) = &documentations[0].history[0];
+        assert_eq!(&history, path);
+        assert_eq!("Old", ids.documents[0].title);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_invalid_config() -> Result<(), Error> {
+        let dir = TempDir::new()?;
+        std::fs::write(dir.path().join("ids.json"), r#"{"documents": []}"#)?;
+        let config = dir.path().join("documentations.toml");
+
+        let assert_error = |contents: &str, expected: &str| -> Result<(), Error> {
+            std::fs::write(&config, contents)?;
+            let err = load_config(&config).err().expect("the config should be invalid");
+            assert_eq!(expected, err.to_string());
+            Ok(())
+        };
+        let entry = |name: &str, extra: &str| {
+            format!("[[documentation]]\nname = {name:?}\nids = \"ids.json\"\nurl = \"\"\n{extra}")
+        };
+
+        assert_error(
+            &format!("{}{}", entry("FLS", ""), entry("FLS", "")),
+            "documentation FLS is configured multiple times",
+        )?;
+        assert_error(&entry("A:B", ""), "invalid documentation name: \"A:B\"")?;
+        assert_error(
+            &entry("FLS", "kinds = [\"chapter\"]"),
+            "unknown element kind in documentation FLS: chapter",
+        )?;
+        Ok(())
+    }
 }
@@ -0,0 +1,486 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Machine-readable exports of the traceability matrix, meant to be imported by other tools.
+
+use crate::annotations::{AnnotatedFile, AnnotationSource, Annotations};
+use crate::matrix::{Element, Link, LinkStatus, LinkTest, TraceabilityMatrix};
+use crate::test_outcomes::IgnoredOn;
+use anyhow::Error;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+#[derive(Serialize)]
+struct JsonMatrix<'a> {
+    considers_ignored_tests: bool,
+    elements: Vec<JsonElement<'a>>,
+    unknown_annotations: Vec<JsonUnknownAnnotation<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonElement<'a> {
+    kind: &'static str,
+    id: &'a str,
+    number: Option<String>,
+    title: Option<&'a str>,
+    link: &'a str,
+    documentation: &'a str,
+    page: &'a str,
+    page_link: &'a str,
+    status: LinkStatus,
+    untested_targets: Option<&'a BTreeSet<String>>,
+    tests: Vec<JsonLinkTest<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JsonLinkTest<'a> {
+    File(JsonFile<'a>),
+    NoParagraphsInSection,
+    Informational,
+    InheritFromSection { section_id: &'a str, section_number: String },
+}
+
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    test: &'a str,
+    source: &'a AnnotationSource,
+    executed_targets: &'a BTreeSet<String>,
+    /// Targets the test was ignored on, with the reasons why.
+    ignored_targets: &'a IgnoredOn,
+}
+
+#[derive(Serialize)]
+struct JsonUnknownAnnotation<'a> {
+    annotation: &'a str,
+    #[serde(flatten)]
+    file: JsonFile<'a>,
+}
+
+pub(crate) fn to_json(
+    annotations: &Annotations,
+    matrix: &TraceabilityMatrix,
+) -> Result<String, Error> {
+    let mut elements = Vec::new();
+    for (element, status, link) in sorted_elements(matrix) {
+        elements.push(JsonElement {
+            kind: element.kind.singular,
+            id: &element.id,
+            number: element.number.as_ref().map(|n| n.to_string()),
+            title: element.title.as_deref(),
+            link: &element.link,
+            documentation: &element.page.documentation,
+            page: &element.page.name,
+            page_link: &element.page.link,
+            status,
+            untested_targets: link.map(|l| &l.untested_targets),
+            tests: link
+                .iter()
+                .flat_map(|l| l.tests.iter())
+                .map(|test| {
+                    Ok(match test {
+                        LinkTest::File(file) => JsonLinkTest::File(json_file(file)?),
+                        LinkTest::NoParagraphsInSection => JsonLinkTest::NoParagraphsInSection,
+                        LinkTest::Informational => JsonLinkTest::Informational,
+                        LinkTest::InheritFromSection { section_id, section_number } => {
+                            JsonLinkTest::InheritFromSection {
+                                section_id,
+                                section_number: section_number.to_string(),
+                            }
+                        }
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+        });
+    }
+
+    let unknown_annotations = matrix
+        .unknown_annotations
+        .iter()
+        .map(|unknown| {
+            Ok(JsonUnknownAnnotation {
+                annotation: &unknown.annotation,
+                file: json_file(&unknown.file)?,
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+
+    Ok(serde_json::to_string_pretty(&JsonMatrix {
+        considers_ignored_tests: annotations.considers_ignored_tests,
+        elements,
+        unknown_annotations,
+    })?)
+}
+
+fn json_file(file: &AnnotatedFile) -> Result<JsonFile<'_>, Error> {
+    Ok(JsonFile {
+        test: path_str(file)?,
+        source: &file.source,
+        executed_targets: &file.targets.executed.0,
+        ignored_targets: &file.targets.ignored,
+    })
+}
+
+const CSV_HEADER: &[&str] = &[
+    "kind",
+    "id",
+    "number",
+    "title",
+    "link",
+    "documentation",
+    "page",
+    "status",
+    "untested_targets",
+    "test_kind",
+    "test",
+    "annotation_source",
+    "annotation_detail",
+    "executed_targets",
+    "ignored_targets",
+    "ignore_reasons",
+    "inherited_from_section",
+];
+
+/// Renders the matrix as CSV, with one row for each test linked to an element (or a single row
+/// for elements without tests). Lists of targets are separated by spaces, while the reasons tests
+/// were ignored are listed one target per line.
+pub(crate) fn to_csv(matrix: &TraceabilityMatrix) -> Result<String, Error> {
+    let mut csv = String::new();
+    push_csv_row(&mut csv, CSV_HEADER.iter().map(|h| h.to_string()));
+
+    for (element, status, link) in sorted_elements(matrix) {
+        let element_columns = [
+            element.kind.singular.to_string(),
+            element.id.clone(),
+            element.number.as_ref().map(|n| n.to_string()).unwrap_or_default(),
+            element.title.clone().unwrap_or_default(),
+            element.link.clone(),
+            element.page.documentation.clone(),
+            element.page.name.clone(),
+            status.to_string(),
+            link.map(|l| join_targets(&l.untested_targets)).unwrap_or_default(),
+        ];
+
+        let mut rows = Vec::new();
+        for test in link.iter().flat_map(|l| l.tests.iter()) {
+            rows.push(match test {
+                LinkTest::File(file) => {
+                    let (source, detail) = match &file.source {
+                        AnnotationSource::TestItself => ("test itself", String::new()),
+                        AnnotationSource::ParentDirectory { bulk_file } => {
+                            ("parent directory", bulk_file.display().to_string())
+                        }
+                        AnnotationSource::Makefile => ("makefile", String::new()),
+                        AnnotationSource::TestFunction { name } => ("test function", name.clone()),
+                        AnnotationSource::Doctest { line } => ("doctest", line.to_string()),
+                    };
+                    CsvTest {
+                        kind: "file",
+                        test: path_str(file)?.into(),
+                        source,
+                        detail,
+                        executed_targets: join_targets(&file.targets.executed.0),
+                        ignored_targets: join_targets(file.targets.ignored.keys()),
+                        ignore_reasons: ignore_reasons(&file.targets.ignored),
+                        ..CsvTest::default()
+                    }
+                }
+                LinkTest::NoParagraphsInSection => {
+                    CsvTest { kind: "no paragraphs in section", ..CsvTest::default() }
+                }
+                LinkTest::Informational => CsvTest { kind: "informational", ..CsvTest::default() },
+                LinkTest::InheritFromSection { section_id, .. } => CsvTest {
+                    kind: "inherit from section",
+                    inherited_from_section: section_id.clone(),
+                    ..CsvTest::default()
+                },
+            });
+        }
+        // Elements without tests still get a row.
+        if rows.is_empty() {
+            rows.push(CsvTest::default());
+        }
+
+        for row in rows {
+            let test_columns = [
+                row.kind.into(),
+                row.test,
+                row.source.into(),
+                row.detail,
+                row.executed_targets,
+                row.ignored_targets,
+                row.ignore_reasons,
+                row.inherited_from_section,
+            ];
+            push_csv_row(&mut csv, element_columns.iter().cloned().chain(test_columns));
+        }
+    }
+
+    Ok(csv)
+}
+
+#[derive(Default)]
+struct CsvTest {
+    kind: &'static str,
+    test: String,
+    source: &'static str,
+    detail: String,
+    executed_targets: String,
+    ignored_targets: String,
+    ignore_reasons: String,
+    inherited_from_section: String,
+}
+
+fn push_csv_row(csv: &mut String, columns: impl Iterator<Item = String>) {
+    let columns = columns
+        .map(|column| {
+            if column.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", column.replace('"', "\"\""))
+            } else {
+                column
+            }
+        })
+        .collect::<Vec<_>>();
+    csv.push_str(&columns.join(","));
+    csv.push_str("\r\n");
+}
+
+fn join_targets<'a>(targets: impl IntoIterator<Item = &'a String>) -> String {
+    targets.into_iter().map(|t| t.as_str()).collect::<Vec<_>>().join(" ")
+}
+
+fn ignore_reasons(ignored: &IgnoredOn) -> String {
+    ignored
+        .iter()
+        .map(|(target, reasons)| match reasons.is_empty() {
+            true => target.clone(),
+            false => {
+                let reasons = reasons.iter().map(|r| r.as_str()).collect::<Vec<_>>().join("; ");
+                format!("{target}: {reasons}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn path_str(file: &AnnotatedFile) -> Result<&str, Error> {
+    file.test.to_str().ok_or_else(|| anyhow::anyhow!("non-utf8 path: {}", file.test.display()))
+}
+
+/// All the elements of the matrix, grouped by kind and sorted like in the report.
+fn sorted_elements(
+    matrix: &TraceabilityMatrix,
+) -> impl Iterator<Item = (&Element, LinkStatus, Option<&Link>)> {
+    matrix.analyses_by_kind().flat_map(|analysis| {
+        let mut elements = analysis.elements().collect::<Vec<_>>();
+        elements.sort_by(|a, b| a.0.cmp(b.0));
+        elements
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::{DisplayCommaSeparatedSet, Targets};
+    use crate::matrix::{
+        ElementKind, MatrixAnalysis, Page, UnknownAnnotation, ELEMENT_KIND_CLI_OPTION,
+        ELEMENT_KIND_PARAGRAPH, ELEMENT_KIND_SECTION,
+    };
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_to_json() -> Result<(), Error> {
+        let json: serde_json::Value = serde_json::from_str(&to_json(&annotations(), &matrix())?)?;
+        assert_eq!(
+            json!({
+                "considers_ignored_tests": true,
+                "elements": [
+                    {
+                        "kind": "section",
+                        "id": "fls_section",
+                        "number": "1",
+                        "title": "Section",
+                        "link": "fls/example.html#fls_section",
+                        "documentation": "FLS",
+                        "page": "Example",
+                        "page_link": "fls/example.html",
+                        "status": "partially_linked",
+                        "untested_targets": ["x86_64-unknown-none"],
+                        "tests": [
+                            {
+                                "kind": "file",
+                                "test": "tests/ui/foo.rs",
+                                "source": {"kind": "test_itself"},
+                                "executed_targets": ["x86_64-unknown-linux-gnu"],
+                                "ignored_targets": {"x86_64-unknown-none": ["no MMU"]},
+                            },
+                            {
+                                "kind": "file",
+                                "test": "library/core/src/num/mod.rs",
+                                "source": {"kind": "doctest", "line": 42},
+                                "executed_targets": [],
+                                "ignored_targets": {},
+                            },
+                        ],
+                    },
+                    {
+                        "kind": "paragraph",
+                        "id": "fls_inherited",
+                        "number": "1:1",
+                        "title": null,
+                        "link": "fls/example.html#fls_inherited",
+                        "documentation": "FLS",
+                        "page": "Example",
+                        "page_link": "fls/example.html",
+                        "status": "linked",
+                        "untested_targets": [],
+                        "tests": [
+                            {
+                                "kind": "inherit_from_section",
+                                "section_id": "fls_section",
+                                "section_number": "1",
+                            },
+                        ],
+                    },
+                    {
+                        "kind": "paragraph",
+                        "id": "fls_unlinked",
+                        "number": "1:2",
+                        "title": null,
+                        "link": "fls/example.html#fls_unlinked",
+                        "documentation": "FLS",
+                        "page": "Example",
+                        "page_link": "fls/example.html",
+                        "status": "unlinked",
+                        "untested_targets": null,
+                        "tests": [],
+                    },
+                ],
+                "unknown_annotations": [
+                    {
+                        "annotation": "fls_unknown",
+                        "test": "tests/ui/bar.rs",
+                        "source": {
+                            "kind": "parent_directory",
+                            "bulk_file": "tests/ui/ferrocene-annotations",
+                        },
+                        "executed_targets": [],
+                        "ignored_targets": {},
+                    },
+                ],
+            }),
+            json
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_csv() -> Result<(), Error> {
+        let expected = [
+            "kind,id,number,title,link,documentation,page,status,untested_targets,test_kind,test,\
+             annotation_source,annotation_detail,executed_targets,ignored_targets,ignore_reasons,\
+             inherited_from_section",
+            "section,fls_section,1,Section,fls/example.html#fls_section,FLS,Example,\
+             partially linked,x86_64-unknown-none,file,tests/ui/foo.rs,test itself,,\
+             x86_64-unknown-linux-gnu,x86_64-unknown-none,x86_64-unknown-none: no MMU,",
+            "section,fls_section,1,Section,fls/example.html#fls_section,FLS,Example,\
+             partially linked,x86_64-unknown-none,file,library/core/src/num/mod.rs,doctest,42,,,,",
+            "paragraph,fls_inherited,1:1,,fls/example.html#fls_inherited,FLS,Example,linked,,\
+             inherit from section,,,,,,,fls_section",
+            "paragraph,fls_unlinked,1:2,,fls/example.html#fls_unlinked,FLS,Example,unlinked,\
+             ,,,,,,,,",
+            "",
+        ];
+        assert_eq!(expected.join("\r\n"), to_csv(&matrix())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_escaping() {
+        let mut csv = String::new();
+        push_csv_row(&mut csv, ["a".into(), "b,c".into(), "say \"hi\"".into()].into_iter());
+        assert_eq!("a,\"b,c\",\"say \"\"hi\"\"\"\r\n", csv);
+    }
+
+    fn annotations() -> Annotations {
+        Annotations {
+            ids: BTreeMap::new(),
+            ignored_tests: BTreeMap::new(),
+            considers_ignored_tests: true,
+        }
+    }
+
+    fn matrix() -> TraceabilityMatrix {
+        let mut sections = MatrixAnalysis::new(&ELEMENT_KIND_SECTION);
+        sections.partially_linked.insert(Link {
+            element: element(&ELEMENT_KIND_SECTION, "fls_section", "1", Some("Section")),
+            tests: vec![
+                LinkTest::File(AnnotatedFile {
+                    test: "tests/ui/foo.rs".into(),
+                    source: AnnotationSource::TestItself,
+                    targets: Targets {
+                        executed: targets(&["x86_64-unknown-linux-gnu"]),
+                        ignored: BTreeMap::from([(
+                            "x86_64-unknown-none".into(),
+                            BTreeSet::from(["no MMU".into()]),
+                        )]),
+                    },
+                }),
+                LinkTest::File(AnnotatedFile {
+                    test: "library/core/src/num/mod.rs".into(),
+                    source: AnnotationSource::Doctest { line: 42 },
+                    targets: Targets::default(),
+                }),
+            ],
+            untested_targets: BTreeSet::from(["x86_64-unknown-none".into()]),
+        });
+
+        let mut paragraphs = MatrixAnalysis::new(&ELEMENT_KIND_PARAGRAPH);
+        paragraphs.linked.insert(Link {
+            element: element(&ELEMENT_KIND_PARAGRAPH, "fls_inherited", "1:1", None),
+            tests: vec![LinkTest::InheritFromSection {
+                section_id: "fls_section".into(),
+                section_number: "1".into(),
+            }],
+            untested_targets: BTreeSet::new(),
+        });
+        paragraphs.unlinked.insert(element(&ELEMENT_KIND_PARAGRAPH, "fls_unlinked", "1:2", None));
+
+        TraceabilityMatrix {
+            sections,
+            paragraphs,
+            cli_options: MatrixAnalysis::new(&ELEMENT_KIND_CLI_OPTION),
+            unknown_annotations: vec![UnknownAnnotation {
+                annotation: "fls_unknown".into(),
+                file: AnnotatedFile {
+                    test: "tests/ui/bar.rs".into(),
+                    source: AnnotationSource::ParentDirectory {
+                        bulk_file: "tests/ui/ferrocene-annotations".into(),
+                    },
+                    targets: Targets::default(),
+                },
+            }],
+        }
+    }
+
+    fn element(kind: &'static ElementKind, id: &str, number: &str, title: Option<&str>) -> Element {
+        Element {
+            kind,
+            number: Some(number.into()),
+            page: Page {
+                documentation: "FLS".into(),
+                name: "Example".into(),
+                link: "fls/example.html".into(),
+            },
+            title: title.map(|t| t.into()),
+            id: id.into(),
+            link: format!("fls/example.html#{id}"),
+        }
+    }
+
+    fn targets(targets: &[&str]) -> DisplayCommaSeparatedSet {
+        DisplayCommaSeparatedSet(targets.iter().map(|t| t.to_string()).collect())
+    }
+}
@@ -0,0 +1,339 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Coverage gates, failing the run when the coverage of the traceability matrix regresses.
+//!
+//! Gates are configured in a TOML file like this:
+//!
+//! ```toml
+//! # Path of the baseline file, relative to this file. Optional.
+//! baseline = "traceability-matrix-baseline.json"
+//!
+//! # Minimum percentage of paragraphs linked to a test.
+//! [[gate]]
+//! kind = "paragraph"
+//! min-linked = 80.0
+//!
+//! # Gates can also be restricted to a documentation, or to a page of it.
+//! [[gate]]
+//! kind = "section"
+//! documentation = "FLS"
+//! page = "Expressions"
+//! min-linked = 95.0
+//! ```
+//!
+//! The baseline file contains the IDs of all the elements linked to a test, and is regenerated
+//! when the `TRACEABILITY_MATRIX_BLESS` environment variable is set. Elements linked in the
+//! baseline but not linked anymore are reported as regressions.
+
+use crate::matrix::{Element, LinkStatus, MatrixAnalysis, TraceabilityMatrix};
+use anyhow::{Context, Error};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Gates {
+    baseline: Option<PathBuf>,
+    #[serde(default, rename = "gate")]
+    gates: Vec<Gate>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Gate {
+    kind: String,
+    documentation: Option<String>,
+    page: Option<String>,
+    min_linked: f32,
+}
+
+impl Gate {
+    fn applies_to(&self, element: &Element) -> bool {
+        self.documentation.as_ref().map(|d| *d == element.page.documentation).unwrap_or(true)
+            && self.page.as_ref().map(|p| *p == element.page.name).unwrap_or(true)
+    }
+}
+
+impl std::fmt::Display for Gate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}s", self.kind)?;
+        match (&self.documentation, &self.page) {
+            (Some(documentation), Some(page)) => write!(f, " in {documentation}: {page}"),
+            (Some(documentation), None) => write!(f, " in {documentation}"),
+            (None, Some(page)) => write!(f, " in page {page}"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Linked elements of a previous run, with the key being the kind of the elements.
+type Baseline = BTreeMap<String, BTreeSet<String>>;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum GateFailure {
+    BelowThreshold { gate: String, linked: usize, total: usize, min_linked: f32 },
+    NotLinkedAnymore { kind: &'static str, id: String, name: String, status: LinkStatus },
+}
+
+impl std::fmt::Display for GateFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GateFailure::BelowThreshold { gate, linked, total, min_linked } => write!(
+                f,
+                "{gate}: {linked} out of {total} linked ({:.2}%), \
+                 below the minimum of {min_linked:.2}%",
+                percentage(*linked, *total),
+            ),
+            GateFailure::NotLinkedAnymore { kind, id, name, status } => {
+                write!(f, "{kind} {name} ({id}) was linked in the baseline, but is now {status}")
+            }
+        }
+    }
+}
+
+pub(crate) fn load(path: &Path) -> Result<Gates, Error> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read gates from {}", path.display()))?;
+    let mut gates: Gates = toml::from_str(&contents)
+        .with_context(|| format!("failed to parse gates from {}", path.display()))?;
+    if let (Some(baseline), Some(parent)) = (&gates.baseline, path.parent()) {
+        gates.baseline = Some(parent.join(baseline));
+    }
+    Ok(gates)
+}
+
+pub(crate) fn check(gates: &Gates, matrix: &TraceabilityMatrix) -> Result<Vec<GateFailure>, Error> {
+    let mut failures = Vec::new();
+
+    for gate in &gates.gates {
+        let analysis = find_analysis(matrix, &gate.kind)?;
+        let (mut linked, mut total) = (0, 0);
+        for (element, status, _) in analysis.elements() {
+            if gate.applies_to(element) {
+                total += 1;
+                if status == LinkStatus::Linked {
+                    linked += 1;
+                }
+            }
+        }
+        if total == 0 {
+            anyhow::bail!("the gate for {gate} doesn't match any element");
+        }
+        if percentage(linked, total) < gate.min_linked {
+            failures.push(GateFailure::BelowThreshold {
+                gate: gate.to_string(),
+                linked,
+                total,
+                min_linked: gate.min_linked,
+            });
+        }
+    }
+
+    if let Some(path) = &gates.baseline {
+        let baseline: Baseline = serde_json::from_slice(&std::fs::read(path)?)
+            .with_context(|| format!("failed to read the baseline from {}", path.display()))?;
+        for (kind, ids) in &baseline {
+            let analysis = find_analysis(matrix, kind)?;
+            for (element, status, _) in analysis.elements() {
+                if status != LinkStatus::Linked && ids.contains(&element.id) {
+                    failures.push(GateFailure::NotLinkedAnymore {
+                        kind: element.kind.singular,
+                        id: element.id.clone(),
+                        name: element.name(),
+                        status,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Regenerates the baseline file with the elements linked in the current matrix.
+pub(crate) fn bless(gates: &Gates, matrix: &TraceabilityMatrix) -> Result<(), Error> {
+    let Some(path) = &gates.baseline else {
+        anyhow::bail!("no baseline file is configured in the gates");
+    };
+
+    let mut baseline = Baseline::new();
+    for analysis in matrix.analyses_by_kind() {
+        baseline.insert(
+            analysis.kind.singular.into(),
+            analysis.linked.iter().map(|link| link.id.clone()).collect(),
+        );
+    }
+
+    let mut contents = serde_json::to_string_pretty(&baseline)?;
+    contents.push('\n');
+    std::fs::write(path, contents)?;
+    eprintln!("Updated the coverage baseline at {}", path.display());
+    Ok(())
+}
+
+fn find_analysis<'a>(
+    matrix: &'a TraceabilityMatrix,
+    kind: &str,
+) -> Result<&'a MatrixAnalysis, Error> {
+    matrix
+        .analyses_by_kind()
+        .find(|analysis| analysis.kind.singular == kind)
+        .ok_or_else(|| anyhow::anyhow!("unknown element kind in the gates: {kind}"))
+}
+
+fn percentage(linked: usize, total: usize) -> f32 {
+    linked as f32 * 100.0 / total as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::{
+        Link, Page, ELEMENT_KIND_CLI_OPTION, ELEMENT_KIND_PARAGRAPH, ELEMENT_KIND_SECTION,
+    };
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_thresholds() -> Result<(), Error> {
+        let gates = parse(
+            r#"
+                [[gate]]
+                kind = "paragraph"
+                min-linked = 50.0
+
+                [[gate]]
+                kind = "paragraph"
+                documentation = "FLS"
+                page = "Expressions"
+                min-linked = 100.0
+
+                [[gate]]
+                kind = "section"
+                documentation = "UM"
+                min-linked = 100.0
+            "#,
+        )?;
+
+        assert_eq!(
+            vec![GateFailure::BelowThreshold {
+                gate: "paragraphs in FLS: Expressions".into(),
+                linked: 1,
+                total: 2,
+                min_linked: 100.0,
+            }],
+            check(&gates, &matrix())?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_gates() -> Result<(), Error> {
+        let gates = parse("[[gate]]\nkind = \"chapter\"\nmin-linked = 1.0\n")?;
+        let err = check(&gates, &matrix()).unwrap_err();
+        assert_eq!("unknown element kind in the gates: chapter", err.to_string());
+
+        let gates = parse("[[gate]]\nkind = \"section\"\npage = \"Typo\"\nmin-linked = 1.0\n")?;
+        let err = check(&gates, &matrix()).unwrap_err();
+        assert_eq!("the gate for sections in page Typo doesn't match any element", err.to_string());
+
+        assert!(parse("[[gate]]\nkind = \"section\"\nmin-linked = 1.0\ntypo = 1\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_baseline() -> Result<(), Error> {
+        let dir = TempDir::new()?;
+        let gates_path = dir.path().join("gates.toml");
+        std::fs::write(&gates_path, "baseline = \"baseline.json\"\n")?;
+        let gates = load(&gates_path)?;
+
+        // Blessing records the currently linked elements, which are not regressions.
+        bless(&gates, &matrix())?;
+        let baseline: Baseline =
+            serde_json::from_slice(&std::fs::read(dir.path().join("baseline.json"))?)?;
+        assert_eq!(
+            Baseline::from([
+                ("command line option".into(), BTreeSet::new()),
+                ("paragraph".into(), BTreeSet::from(["fls_linked".into(), "um_linked".into()])),
+                ("section".into(), BTreeSet::from(["um_section".into()])),
+            ]),
+            baseline
+        );
+        assert_eq!(Vec::<GateFailure>::new(), check(&gates, &matrix())?);
+
+        // An element linked in the baseline but not anymore is a regression.
+        std::fs::write(
+            dir.path().join("baseline.json"),
+            r#"{"paragraph": ["fls_linked", "fls_unlinked", "fls_removed"]}"#,
+        )?;
+        assert_eq!(
+            vec![GateFailure::NotLinkedAnymore {
+                kind: "paragraph",
+                id: "fls_unlinked".into(),
+                name: "fls_unlinked".into(),
+                status: LinkStatus::Unlinked,
+            }],
+            check(&gates, &matrix())?
+        );
+        Ok(())
+    }
+
+    fn parse(contents: &str) -> Result<Gates, Error> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    fn matrix() -> TraceabilityMatrix {
+        let link = |element: Element| Link {
+            element,
+            tests: Vec::new(),
+            untested_targets: BTreeSet::new(),
+        };
+
+        let mut sections = MatrixAnalysis::new(&ELEMENT_KIND_SECTION);
+        sections.linked.insert(link(element(&ELEMENT_KIND_SECTION, "UM", "Usage", "um_section")));
+
+        let mut paragraphs = MatrixAnalysis::new(&ELEMENT_KIND_PARAGRAPH);
+        paragraphs.linked.insert(link(element(
+            &ELEMENT_KIND_PARAGRAPH,
+            "FLS",
+            "Expressions",
+            "fls_linked",
+        )));
+        paragraphs.unlinked.insert(element(
+            &ELEMENT_KIND_PARAGRAPH,
+            "FLS",
+            "Expressions",
+            "fls_unlinked",
+        ));
+        paragraphs.linked.insert(link(element(
+            &ELEMENT_KIND_PARAGRAPH,
+            "UM",
+            "Usage",
+            "um_linked",
+        )));
+
+        TraceabilityMatrix {
+            sections,
+            paragraphs,
+            cli_options: MatrixAnalysis::new(&ELEMENT_KIND_CLI_OPTION),
+            unknown_annotations: Vec::new(),
+        }
+    }
+
+    fn element(
+        kind: &'static crate::matrix::ElementKind,
+        documentation: &str,
+        page: &str,
+        id: &str,
+    ) -> Element {
+        Element {
+            kind,
+            number: None,
+            page: Page { documentation: documentation.into(), name: page.into(), link: "".into() },
+            title: Some(id.into()),
+            id: id.into(),
+            link: "".into(),
+        }
+    }
+}
@@ -0,0 +1,653 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Collection of the annotations of the tests executed by libtest in Cargo packages (like the
+//! tests in `library/core/tests`), which are not discovered by compiletest.
+//!
+//! Test functions are annotated with comments placed above them, alongside their attributes:
+//!
+//! ```text
+//! // ferrocene-annotations: fls_xxxxxxxxxxxx
+//! #[test]
+//! fn test_something() {}
+//! ```
+//!
+//! Doctests are annotated with hidden comments inside of the code block:
+//!
+//! ```text
+//! /// ```
+//! /// # // ferrocene-annotations: fls_xxxxxxxxxxxx
+//! /// assert_eq!(1 + 1, 2);
+//! /// ```
+//! ```
+
+use anyhow::Error;
+use std::path::{Path, PathBuf};
+
+const PREFIX: &str = "// ferrocene-annotations: ";
+const TYPO_PREFIX: &str = "// ferrocene-annotation: ";
+
+/// Source directories of a package, along with the name of their crate root.
+const ROOTS: &[(&str, &str)] = &[("src", "lib.rs"), ("tests", "lib.rs")];
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct LibtestFile {
+    pub(crate) file: PathBuf,
+    pub(crate) test: LibtestTest,
+    pub(crate) annotations: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum LibtestTest {
+    /// A `#[test]` function, with its full path inside of the crate (like `num::test_add`).
+    Function { name: String },
+    /// A doctest, with the line its code block starts at.
+    Doctest { line: usize },
+}
+
+/// Collects the annotated tests of the package in the given directory.
+pub(crate) fn collect(package_dir: &Path) -> Result<Vec<LibtestFile>, Error> {
+    let mut found = Vec::new();
+    for (dir, crate_root) in ROOTS {
+        let root = package_dir.join(dir);
+        if !root.is_dir() {
+            continue;
+        }
+        let mut files = Vec::new();
+        find_rust_files(&root, &mut files)?;
+        files.sort();
+
+        for file in files {
+            let module = module_path(&root, crate_root, &file);
+            let contents = std::fs::read_to_string(&file)?;
+            collect_file(&file, &module, &contents, &mut found)?;
+        }
+    }
+    Ok(found)
+}
+
+fn find_rust_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_rust_files(&path, files)?;
+        } else if path.extension().and_then(|e| e.to_str()) == Some("rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Path of the module defined by a file, following the default module layout.
+fn module_path(root: &Path, crate_root: &str, file: &Path) -> Vec<String> {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+    if relative == Path::new(crate_root) {
+        return Vec::new();
+    }
+    let mut module = relative
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    if module.last().map(|m| m.as_str()) == Some("mod") {
+        module.pop();
+    }
+    module
+}
+
+fn collect_file(
+    file: &Path,
+    module: &[String],
+    contents: &str,
+    found: &mut Vec<LibtestFile>,
+) -> Result<(), Error> {
+    let error =
+        |line: usize, message: &str| anyhow::anyhow!("{}:{line}: {message}", file.display());
+
+    // Annotations and attributes of the next item.
+    let mut pending = Vec::new();
+    let mut pending_is_test = false;
+    // Inline modules, along with the brace depth they were declared at.
+    let mut inline_modules: Vec<(String, usize)> = Vec::new();
+    let mut depth = 0;
+    // Line the current doctest starts at, along with its annotations.
+    let mut doctest: Option<(usize, Vec<String>)> = None;
+
+    // Brackets still open in an attribute spanning multiple lines.
+    let mut attribute_depth = 0;
+
+    for (idx, line) in strip_literals(contents).iter().enumerate() {
+        let line_number = idx + 1;
+        let trimmed = line.trim();
+
+        if let Some(doc) = trimmed.strip_prefix("///").or_else(|| trimmed.strip_prefix("//!")) {
+            let doc = doc.trim();
+            if doc.starts_with("```") {
+                match doctest.take() {
+                    Some((line, annotations)) => {
+                        if !annotations.is_empty() {
+                            found.push(LibtestFile {
+                                file: file.into(),
+                                test: LibtestTest::Doctest { line },
+                                annotations,
+                            });
+                        }
+                    }
+                    None => doctest = Some((line_number, Vec::new())),
+                }
+            } else if let Some((_, annotations)) = &mut doctest {
+                let hidden = doc.strip_prefix('#').map(|d| d.trim_start()).unwrap_or(doc);
+                if let Some(id) = hidden.strip_prefix(PREFIX) {
+                    annotations.push(id.trim().to_string());
+                } else if hidden.starts_with(TYPO_PREFIX) {
+                    return Err(error(line_number, TYPO_MESSAGE));
+                }
+            }
+            continue;
+        }
+
+        if let Some(id) = trimmed.strip_prefix(PREFIX) {
+            pending.push(id.trim().to_string());
+            continue;
+        } else if trimmed.starts_with(TYPO_PREFIX) {
+            return Err(error(line_number, TYPO_MESSAGE));
+        } else if trimmed.is_empty() || trimmed.starts_with("//") {
+            continue;
+        }
+
+        // Attributes can be followed by the item on the same line, like `#[test] fn foo() {}`.
+        let code = strip_attributes(trimmed, &mut attribute_depth, &mut pending_is_test);
+        if code.is_empty() {
+            // Only attributes on this line.
+        } else if let Some(name) = item_name(code, "fn") {
+            if pending_is_test {
+                let path = module
+                    .iter()
+                    .map(|m| m.as_str())
+                    .chain(inline_modules.iter().map(|(m, _)| m.as_str()))
+                    .chain(std::iter::once(name))
+                    .collect::<Vec<_>>();
+                if !pending.is_empty() {
+                    found.push(LibtestFile {
+                        file: file.into(),
+                        test: LibtestTest::Function { name: path.join("::") },
+                        annotations: std::mem::take(&mut pending),
+                    });
+                }
+            }
+            if !pending.is_empty() {
+                return Err(error(line_number, PLACEMENT_MESSAGE));
+            }
+            pending_is_test = false;
+        } else {
+            if !pending.is_empty() {
+                return Err(error(line_number, PLACEMENT_MESSAGE));
+            }
+            pending_is_test = false;
+            if let Some(name) = item_name(code, "mod") {
+                if code.ends_with('{') || code.contains("{}") {
+                    inline_modules.push((name.into(), depth));
+                }
+            }
+        }
+
+        let (opened, closed) = count_braces(trimmed);
+        depth = (depth + opened).saturating_sub(closed);
+        while inline_modules.last().map(|(_, d)| *d >= depth).unwrap_or(false) {
+            inline_modules.pop();
+        }
+    }
+
+    if !pending.is_empty() {
+        return Err(error(contents.lines().count(), PLACEMENT_MESSAGE));
+    }
+    Ok(())
+}
+
+const TYPO_MESSAGE: &str =
+    "attribute is called 'ferrocene-annotations', not 'ferrocene-annotation'";
+const PLACEMENT_MESSAGE: &str = "ferrocene-annotations must be placed above a #[test] function";
+
+/// Removes the attributes at the start of the line, returning the rest of it. `depth` tracks the
+/// brackets of an attribute continuing on the following lines, and `is_test` is set when one of
+/// the attributes is `#[test]`.
+fn strip_attributes<'a>(mut line: &'a str, depth: &mut usize, is_test: &mut bool) -> &'a str {
+    'attributes: loop {
+        if *depth == 0 {
+            line = line.trim_start();
+            if !line.starts_with("#[") {
+                return line;
+            }
+            if line.starts_with("#[test]") {
+                *is_test = true;
+            }
+            line = &line[1..];
+        }
+        for (idx, c) in line.char_indices() {
+            match c {
+                '[' => *depth += 1,
+                ']' => {
+                    *depth = depth.saturating_sub(1);
+                    if *depth == 0 {
+                        line = &line[idx + 1..];
+                        continue 'attributes;
+                    }
+                }
+                _ => {}
+            }
+        }
+        return "";
+    }
+}
+
+/// Returns the lines of the file with the string and character literals and the block comments
+/// replaced by spaces, so that their contents (which can span multiple lines) are not mistaken for
+/// code. Line comments are left untouched, as they contain the annotations.
+fn strip_literals(contents: &str) -> Vec<String> {
+    enum State {
+        Code,
+        String,
+        RawString { hashes: usize },
+        BlockComment { depth: usize },
+    }
+    let is_ident = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric() || *c == '_');
+
+    let mut state = State::Code;
+    let mut lines = Vec::new();
+    for line in contents.lines() {
+        let chars = line.chars().collect::<Vec<_>>();
+        let mut out = chars.clone();
+        let blank = |out: &mut Vec<char>, range: std::ops::Range<usize>| {
+            out[range].iter_mut().for_each(|c| *c = ' ');
+        };
+
+        let mut i = 0;
+        while i < chars.len() {
+            let next = chars.get(i + 1).copied();
+            match state {
+                State::Code => match chars[i] {
+                    '/' if next == Some('/') => break,
+                    '/' if next == Some('*') => {
+                        blank(&mut out, i..i + 2);
+                        state = State::BlockComment { depth: 1 };
+                        i += 1;
+                    }
+                    '"' => {
+                        blank(&mut out, i..i + 1);
+                        state = State::String;
+                    }
+                    'r' if !is_ident(i.checked_sub(1).and_then(|p| chars.get(p)))
+                        || (i >= 1
+                            && chars[i - 1] == 'b'
+                            && !is_ident(i.checked_sub(2).and_then(|p| chars.get(p)))) =>
+                    {
+                        let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+                        if chars.get(i + 1 + hashes) == Some(&'"') {
+                            let start = if is_ident(i.checked_sub(1).and_then(|p| chars.get(p))) {
+                                i - 1
+                            } else {
+                                i
+                            };
+                            blank(&mut out, start..i + hashes + 2);
+                            state = State::RawString { hashes };
+                            i += hashes + 1;
+                        }
+                    }
+                    '\'' => {
+                        // Blank character literals like '{', while leaving lifetimes alone.
+                        let len = match next {
+                            Some('\\') => {
+                                let rest = chars.get(i + 3..).unwrap_or_default();
+                                rest.iter().position(|c| *c == '\'').map(|p| p + 4)
+                            }
+                            Some(_) if chars.get(i + 2) == Some(&'\'') => Some(3),
+                            _ => None,
+                        };
+                        if let Some(len) = len {
+                            blank(&mut out, i..i + len);
+                            i += len - 1;
+                        }
+                    }
+                    _ => {}
+                },
+                State::String => {
+                    match chars[i] {
+                        '\\' if next.is_some() => {
+                            blank(&mut out, i..i + 1);
+                            i += 1;
+                        }
+                        '"' => state = State::Code,
+                        _ => {}
+                    }
+                    blank(&mut out, i..i + 1);
+                }
+                State::RawString { hashes } => {
+                    let closes = chars[i] == '"'
+                        && chars.len() > i + hashes
+                        && chars[i + 1..=i + hashes].iter().all(|c| *c == '#');
+                    if closes {
+                        blank(&mut out, i..i + hashes + 1);
+                        state = State::Code;
+                        i += hashes;
+                    } else {
+                        blank(&mut out, i..i + 1);
+                    }
+                }
+                State::BlockComment { depth } => {
+                    let pair = (chars[i], next);
+                    blank(&mut out, i..i + 1);
+                    if pair == ('/', Some('*')) {
+                        blank(&mut out, i..i + 2);
+                        state = State::BlockComment { depth: depth + 1 };
+                        i += 1;
+                    } else if pair == ('*', Some('/')) {
+                        blank(&mut out, i..i + 2);
+                        state = match depth {
+                            1 => State::Code,
+                            _ => State::BlockComment { depth: depth - 1 },
+                        };
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+        }
+        lines.push(out.into_iter().collect());
+    }
+    lines
+}
+
+/// Returns the name of the item declared by the line, if it declares an item of the given kind.
+fn item_name<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let mut words = line.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        if word == keyword {
+            let name = words.next()?;
+            let end = name.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(name.len());
+            return if end == 0 { None } else { Some(&name[..end]) };
+        }
+        let is_qualifier = word.starts_with("pub")
+            || matches!(word, "const" | "async" | "unsafe" | "extern" | "default")
+            || (word.starts_with('"') && word.ends_with('"'));
+        if !is_qualifier {
+            return None;
+        }
+    }
+    None
+}
+
+/// Counts the braces opened and closed in a line, ignoring the ones in strings, characters and
+/// comments.
+fn count_braces(line: &str) -> (usize, usize) {
+    let (mut opened, mut closed) = (0, 0);
+    let mut chars = line.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '/' if chars.peek() == Some(&'/') => break,
+            '\'' => {
+                // Skip character literals like '{', while leaving lifetimes alone.
+                let mut lookahead = chars.clone();
+                let literal = match lookahead.next() {
+                    Some('\\') => lookahead.nth(1) == Some('\''),
+                    Some(_) => lookahead.next() == Some('\''),
+                    None => false,
+                };
+                if literal {
+                    chars = lookahead;
+                }
+            }
+            '{' => opened += 1,
+            '}' => closed += 1,
+            _ => {}
+        }
+    }
+    (opened, closed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_collect() -> Result<(), Error> {
+        let dir = TempDir::new()?;
+        std::fs::create_dir_all(dir.path().join("src"))?;
+        std::fs::create_dir_all(dir.path().join("tests/num"))?;
+        std::fs::write(
+            dir.path().join("src/lib.rs"),
+            concat!(
+                "/// Adds two numbers.\n",
+                "///\n",
+                "/// ```\n",
+                "/// # // ferrocene-annotations: fls_doctest\n",
+                "/// assert_eq!(2, add(1, 1));\n",
+                "/// ```\n",
+                "pub fn add(a: u8, b: u8) -> u8 { a + b }\n",
+                "\n",
+                "/// ```\n",
+                "/// assert_eq!(0, sub(1, 1));\n",
+                "/// ```\n",
+                "pub fn sub(a: u8, b: u8) -> u8 { a - b }\n",
+                "\n",
+                "#[cfg(test)]\n",
+                "mod tests {\n",
+                "    // ferrocene-annotations: fls_unit\n",
+                "    #[test]\n",
+                "    fn test_add() {\n",
+                "        assert_eq!('{', format!(\"{{\").chars().next().unwrap());\n",
+                "    }\n",
+                "}\n",
+                "\n",
+                "// ferrocene-annotations: fls_after_module\n",
+                "#[test]\n",
+                "fn test_sub() {}\n",
+            ),
+        )?;
+        std::fs::write(dir.path().join("tests/lib.rs"), "mod num;\n")?;
+        std::fs::write(
+            dir.path().join("tests/num/mod.rs"),
+            concat!(
+                "mod ops;\n",
+                "\n",
+                "// ferrocene-annotations: fls_first\n",
+                "// ferrocene-annotations: fls_second\n",
+                "#[test]\n",
+                "#[cfg_attr(miri, ignore)]\n",
+                "fn test_num() {}\n",
+                "\n",
+                "#[test]\n",
+                "fn test_not_annotated() {}\n",
+            ),
+        )?;
+        std::fs::write(
+            dir.path().join("tests/num/ops.rs"),
+            concat!(
+                "// ferrocene-annotations: fls_ops\n",
+                "#[test]\n",
+                "pub(crate) fn test_ops<'a>() {}\n",
+            ),
+        )?;
+
+        assert_eq!(
+            vec![
+                LibtestFile {
+                    file: dir.path().join("src/lib.rs"),
+                    test: LibtestTest::Doctest { line: 3 },
+                    annotations: vec!["fls_doctest".into()],
+                },
+                LibtestFile {
+                    file: dir.path().join("src/lib.rs"),
+                    test: LibtestTest::Function { name: "tests::test_add".into() },
+                    annotations: vec!["fls_unit".into()],
+                },
+                LibtestFile {
+                    file: dir.path().join("src/lib.rs"),
+                    test: LibtestTest::Function { name: "test_sub".into() },
+                    annotations: vec!["fls_after_module".into()],
+                },
+                LibtestFile {
+                    file: dir.path().join("tests/num/mod.rs"),
+                    test: LibtestTest::Function { name: "num::test_num".into() },
+                    annotations: vec!["fls_first".into(), "fls_second".into()],
+                },
+                LibtestFile {
+                    file: dir.path().join("tests/num/ops.rs"),
+                    test: LibtestTest::Function { name: "num::ops::test_ops".into() },
+                    annotations: vec!["fls_ops".into()],
+                },
+            ],
+            collect(dir.path())?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_attribute_on_the_same_line() -> Result<(), Error> {
+        let contents = concat!(
+            "// ferrocene-annotations: fls_one_line\n",
+            "#[test] fn test_one_line() {}\n",
+            "\n",
+            "// ferrocene-annotations: fls_attributes\n",
+            "#[test] #[cfg_attr(\n",
+            "    miri,\n",
+            "    ignore = \"slow]\",\n",
+            ")] fn test_attributes() {}\n",
+            "\n",
+            "#[test] fn test_not_annotated() {}\n",
+        );
+        let mut found = Vec::new();
+        collect_file(Path::new("lib.rs"), &[], contents, &mut found)?;
+        assert_eq!(
+            vec![
+                LibtestFile {
+                    file: "lib.rs".into(),
+                    test: LibtestTest::Function { name: "test_one_line".into() },
+                    annotations: vec!["fls_one_line".into()],
+                },
+                LibtestFile {
+                    file: "lib.rs".into(),
+                    test: LibtestTest::Function { name: "test_attributes".into() },
+                    annotations: vec!["fls_attributes".into()],
+                },
+            ],
+            found
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_multiline_literals() -> Result<(), Error> {
+        let contents = concat!(
+            "mod outer {\n",
+            "    const TEMPLATE: &str = \"{\n",
+            "        fn not_a_test() {\n",
+            "    \";\n",
+            "    const RAW: &str = r#\"\n",
+            "        }} \"quoted\" {\n",
+            "    \"#;\n",
+            "    /* a comment {\n",
+            "       /* nested */ { */\n",
+            "    mod inner {\n",
+            "        // ferrocene-annotations: fls_inner\n",
+            "        #[test]\n",
+            "        fn test_inner() {}\n",
+            "    }\n",
+            "\n",
+            "    // ferrocene-annotations: fls_outer\n",
+            "    #[test]\n",
+            "    fn test_outer() {}\n",
+            "}\n",
+        );
+        let mut found = Vec::new();
+        collect_file(Path::new("lib.rs"), &["num".into()], contents, &mut found)?;
+        assert_eq!(
+            vec![
+                LibtestFile {
+                    file: "lib.rs".into(),
+                    test: LibtestTest::Function { name: "num::outer::inner::test_inner".into() },
+                    annotations: vec!["fls_inner".into()],
+                },
+                LibtestFile {
+                    file: "lib.rs".into(),
+                    test: LibtestTest::Function { name: "num::outer::test_outer".into() },
+                    annotations: vec!["fls_outer".into()],
+                },
+            ],
+            found
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_literals() {
+        assert_eq!(
+            vec![
+                "let s =       ; // \"{\"",
+                "let c =    ;     ; let r =        ; f::<'a>();",
+                "let multi =  ",
+                "                    ",
+                "      ;",
+            ],
+            strip_literals(concat!(
+                "let s = \"{\\\"}\"; // \"{\"\n",
+                "let c = '{'; '\\''; let r = br#\"}\"#; f::<'a>();\n",
+                "let multi = \"\n",
+                "  { /* not a comment\n",
+                "  */ \";\n",
+            ))
+        );
+    }
+
+    #[test]
+    fn test_collect_misplaced_annotation() {
+        let assert_error = |contents: &str, expected: &str| {
+            let mut found = Vec::new();
+            let err = collect_file(Path::new("lib.rs"), &[], contents, &mut found).unwrap_err();
+            assert_eq!(expected, err.to_string());
+        };
+
+        assert_error(
+            "// ferrocene-annotations: fls_foo\nfn not_a_test() {}\n",
+            "lib.rs:2: ferrocene-annotations must be placed above a #[test] function",
+        );
+        assert_error(
+            "// ferrocene-annotations: fls_foo\nstruct Foo;\n",
+            "lib.rs:2: ferrocene-annotations must be placed above a #[test] function",
+        );
+        assert_error(
+            "#[test]\nfn foo() {}\n// ferrocene-annotations: fls_foo\n",
+            "lib.rs:3: ferrocene-annotations must be placed above a #[test] function",
+        );
+        assert_error(
+            "// ferrocene-annotation: fls_foo\n#[test]\nfn foo() {}\n",
+            "lib.rs:1: attribute is called 'ferrocene-annotations', not 'ferrocene-annotation'",
+        );
+    }
+
+    #[test]
+    fn test_item_name() {
+        assert_eq!(Some("foo"), item_name("fn foo() {", "fn"));
+        assert_eq!(Some("foo"), item_name("pub(crate) const unsafe fn foo<T>() {", "fn"));
+        assert_eq!(Some("foo"), item_name("extern \"C\" fn foo() {", "fn"));
+        assert_eq!(Some("tests"), item_name("mod tests {", "mod"));
+        assert_eq!(None, item_name("let fn_ptr = foo;", "fn"));
+        assert_eq!(None, item_name("mod tests;", "fn"));
+    }
+
+    #[test]
+    fn test_count_braces() {
+        assert_eq!((1, 0), count_braces("fn foo() {"));
+        assert_eq!((1, 1), count_braces("fn foo<'a>(x: &'a str) {}"));
+        assert_eq!((0, 0), count_braces("let s = \"{}\\\"{\";"));
+        assert_eq!((0, 0), count_braces("let c = '{'; // }"));
+        assert_eq!((0, 1), count_braces("} // {"));
+    }
+}
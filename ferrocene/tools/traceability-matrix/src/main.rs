@@ -2,57 +2,149 @@
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
 mod annotations;
+mod diff;
 mod documentations;
+mod export;
+mod gates;
+mod libtest_annotations;
 mod matrix;
 mod report;
+mod suggestions;
+mod targets;
 mod test_outcomes;
 mod utils;
 
 use crate::annotations::Annotations;
+use crate::diff::MatrixDiff;
+use crate::documentations::Documentation;
 use crate::matrix::TraceabilityMatrix;
 use crate::report::Urls;
+use crate::suggestions::AnnotationWarnings;
+use crate::targets::TargetSummary;
 use crate::test_outcomes::TestOutcomes;
 use crate::utils::capitalize;
 use anyhow::Error;
 use std::path::PathBuf;
 
 fn main() -> Result<(), Error> {
-    let annotations_path = env_path("ANNOTATIONS");
     let html_out = env_path("HTML_OUT");
-    let src_base = env_path("SRC_BASE");
-    let test_outcomes_dir = maybe_env_path("TEST_OUTCOMES_DIR");
 
-    let urls = Urls { src: env_str("SRC_URL") };
+    let (documentations, annotations) = load_release("")?;
+    let target_summaries = targets::summarize(&documentations, &annotations)?;
+    // Only the tests executed on the selected target are considered, if one is selected.
+    let target = maybe_env_str("TARGET");
+    let annotations = targets::select(annotations, target.as_deref())?;
+    let matrix = matrix::prepare(&documentations, &annotations)?;
 
-    let documentations = [
-        documentations::load("FLS", &env_path("FLS_IDS"), &env_str("FLS_URL"))?,
-        documentations::load("UM", &env_path("UM_IDS"), &env_str("UM_URL"))?,
-    ];
+    // Setting the BASE_* variables (mirroring the ones describing the current release) compares
+    // the matrix with the one of a previous release, rather than generating the full report.
+    if maybe_env_path("BASE_ANNOTATIONS").is_some() {
+        let (base_documentations, base_annotations) = load_release("BASE_")?;
+        let base_annotations = targets::select(base_annotations, target.as_deref())?;
+        let base_matrix = matrix::prepare(&base_documentations, &base_annotations)?;
 
-    let test_outcomes =
-        if let Some(dir) = test_outcomes_dir { Some(TestOutcomes::load(&dir)?) } else { None };
+        let diff = diff::compare(&base_matrix, &matrix);
+        cli_diff_summary(&diff);
 
-    let mut annotations = Annotations::new();
-    annotations.load_directory(&annotations_path, &src_base, test_outcomes.as_ref())?;
+        let report = report::generate_diff(&diff, &env_str("BASE_RELEASE"), &env_str("RELEASE"))?;
+        std::fs::write(&html_out, report.as_bytes())?;
+        std::fs::write(env_path("DIFF_JSON_OUT"), diff::to_json(&diff)?.as_bytes())?;
 
-    let matrix = matrix::prepare(&documentations, &annotations)?;
-    cli_summary(&matrix);
+        eprintln!("Full diff: file://{}", std::fs::canonicalize(&html_out)?.display());
+        eprintln!();
+        return Ok(());
+    }
+
+    let warnings = suggestions::analyze(&documentations, &annotations, &matrix);
+    cli_summary(&matrix, &warnings);
+    cli_targets_summary(&target_summaries);
 
-    let report = report::generate(&annotations, &matrix, urls)?;
+    let urls = Urls { src: env_str("SRC_URL") };
+    let report = report::generate(
+        &annotations,
+        &matrix,
+        &warnings,
+        target.as_deref(),
+        &target_summaries,
+        urls,
+    )?;
     std::fs::write(&html_out, report.as_bytes())?;
 
+    if let Some(json_out) = maybe_env_path("JSON_OUT") {
+        std::fs::write(json_out, export::to_json(&annotations, &matrix)?.as_bytes())?;
+    }
+    if let Some(csv_out) = maybe_env_path("CSV_OUT") {
+        std::fs::write(csv_out, export::to_csv(&matrix)?.as_bytes())?;
+    }
+
     // The file:// link is hopefully clickable in terminals.
     eprintln!("Full report: file://{}", std::fs::canonicalize(&html_out)?.display());
     eprintln!();
 
+    let mut gates_failed = false;
+    if let Some(path) = maybe_env_path("GATES") {
+        let gates = gates::load(&path)?;
+        if maybe_env_str("BLESS").is_some() {
+            gates::bless(&gates, &matrix)?;
+        } else {
+            let failures = gates::check(&gates, &matrix)?;
+            if !failures.is_empty() {
+                eprintln!("Coverage gates failed:");
+                for failure in &failures {
+                    eprintln!("- {failure}");
+                }
+                eprintln!();
+                eprintln!("If the change is expected, rerun with TRACEABILITY_MATRIX_BLESS=1.");
+                eprintln!();
+                gates_failed = true;
+            }
+        }
+    }
+
     if !matrix.unknown_annotations.is_empty() {
         anyhow::bail!("some tests have unknown annotations");
     }
+    if gates_failed {
+        anyhow::bail!("the coverage gates failed");
+    }
 
     Ok(())
 }
 
-fn cli_summary(matrix: &TraceabilityMatrix) {
+/// Loads the documentations and annotations of a release, from the environment variables starting
+/// with the given prefix.
+fn load_release(prefix: &str) -> Result<(Vec<Documentation>, Annotations), Error> {
+    let var = |name: &str| format!("{prefix}{name}");
+
+    let annotations_path = env_path(&var("ANNOTATIONS"));
+    let src_base = env_path(&var("SRC_BASE"));
+    let test_outcomes_dir = maybe_env_path(&var("TEST_OUTCOMES_DIR"));
+    // Comma-separated list of `crate=path/to/package` pairs.
+    let cargo_packages = maybe_env_str(&var("CARGO_PACKAGES"));
+
+    let documentations = documentations::load_config(&env_path(&var("DOCUMENTATIONS")))?;
+
+    let test_outcomes =
+        if let Some(dir) = test_outcomes_dir { Some(TestOutcomes::load(&dir)?) } else { None };
+
+    let mut annotations = Annotations::new();
+    annotations.load_directory(&annotations_path, &src_base, test_outcomes.as_ref())?;
+    for package in cargo_packages.iter().flat_map(|p| p.split(',')).filter(|p| !p.is_empty()) {
+        let Some((krate, path)) = package.split_once('=') else {
+            anyhow::bail!("invalid cargo package {package:?}, expected crate=path");
+        };
+        annotations.load_cargo_package(
+            krate,
+            &PathBuf::from(path),
+            &src_base,
+            test_outcomes.as_ref(),
+        )?;
+    }
+
+    Ok((documentations, annotations))
+}
+
+fn cli_summary(matrix: &TraceabilityMatrix, warnings: &AnnotationWarnings) {
     eprintln!("=====================================");
     eprintln!("==   Traceability matrix summary   ==");
     eprintln!("=====================================");
@@ -74,9 +166,55 @@ fn cli_summary(matrix: &TraceabilityMatrix) {
         eprintln!("Tests with unknown annotations:");
         for test in &matrix.unknown_annotations {
             eprintln!("- {}: {}", test.annotation, test.file);
+            if let Some(help) = warnings.help_for(&test.annotation) {
+                eprintln!("  help: {help}");
+            }
+        }
+        eprintln!();
+    }
+    if !warnings.informational_only.is_empty() {
+        eprintln!("Warning: tests only annotated with informational sections:");
+        for (test, annotations) in &warnings.informational_only {
+            let annotations = annotations.iter().map(|a| a.as_str()).collect::<Vec<_>>();
+            eprintln!("- {test}: {}", annotations.join(", "));
+        }
+        eprintln!();
+    }
+}
+
+fn cli_targets_summary(summaries: &[TargetSummary]) {
+    let Some(first) = summaries.first() else { return };
+    let width = summaries.iter().map(|s| s.target.len()).max().unwrap_or(0);
+    eprintln!("Linked elements per target:");
+    eprint!("  {:width$}", "");
+    for kind in &first.kinds {
+        eprint!("  {:>20}", capitalize(kind.kind.plural));
+    }
+    eprintln!();
+    for summary in summaries {
+        eprint!("  {:width$}", summary.target);
+        for kind in &summary.kinds {
+            let cell = format!("{}/{} ({:.2}%)", kind.linked, kind.total, kind.percentage());
+            eprint!("  {cell:>20}");
         }
         eprintln!();
     }
+    eprintln!();
+}
+
+fn cli_diff_summary(diff: &MatrixDiff) {
+    eprintln!("==========================================");
+    eprintln!("==   Traceability matrix diff summary   ==");
+    eprintln!("==========================================");
+    eprintln!();
+    for kind in &diff.kinds {
+        eprintln!("{}:", capitalize(kind.kind.plural));
+        eprintln!("  gained coverage: {}", kind.gained_coverage());
+        eprintln!("  lost coverage:   {}", kind.lost_coverage());
+        eprintln!("  added:           {}", kind.added.len());
+        eprintln!("  removed:         {}", kind.removed.len());
+        eprintln!();
+    }
 }
 
 fn env_str(var: &str) -> String {
@@ -88,6 +226,11 @@ fn env_str(var: &str) -> String {
     }
 }
 
+fn maybe_env_str(var: &str) -> Option<String> {
+    let var = format!("TRACEABILITY_MATRIX_{var}");
+    std::env::var(var).ok()
+}
+
 fn env_path(var: &str) -> PathBuf {
     let var = format!("TRACEABILITY_MATRIX_{var}");
     if let Some(content) = std::env::var_os(&var) {
@@ -4,6 +4,7 @@
 use crate::annotations::{AnnotatedFile, Annotations};
 use crate::documentations::Documentation;
 use anyhow::Error;
+use serde::Serialize;
 use std::collections::{BTreeSet, HashSet};
 use std::fmt::Debug;
 use std::ops::Deref;
@@ -29,6 +30,9 @@
     include_title_when_copying: false,
 };
 
+pub(crate) const ELEMENT_KINDS: &[&ElementKind] =
+    &[&ELEMENT_KIND_SECTION, &ELEMENT_KIND_PARAGRAPH, &ELEMENT_KIND_CLI_OPTION];
+
 pub(crate) fn prepare(
     documentations: &[Documentation],
     annotations: &Annotations,
@@ -43,6 +47,16 @@ pub(crate) fn prepare(
     let mut seen_ids = HashSet::new();
     for documentation in documentations {
         let to_url = |url: &str| format!("{}/{url}", documentation.url);
+        let mut add =
+            |analysis: &mut MatrixAnalysis, extra_tests: &[LinkTest], element: Element| {
+                // Documentations can choose not to include some kinds of elements in the matrix.
+                if !documentation.contributes(analysis.kind) {
+                    return false;
+                }
+                seen_ids.insert(namespaced_id(&documentation.name, &element.id));
+                seen_ids.insert(element.id.clone());
+                analysis.add(annotations, extra_tests, element)
+            };
 
         for page in &documentation.ids.documents {
             let matrix_page = Page {
@@ -52,8 +66,6 @@ pub(crate) fn prepare(
             };
 
             for section in &page.sections {
-                seen_ids.insert(&section.id);
-
                 let mut extra_section_tests = Vec::new();
                 if section.paragraphs.is_empty() {
                     extra_section_tests.push(LinkTest::NoParagraphsInSection);
@@ -61,8 +73,8 @@ pub(crate) fn prepare(
                 if page.informational || section.informational {
                     extra_section_tests.push(LinkTest::Informational);
                 }
-                let has_annotations = matrix.sections.add(
-                    annotations,
+                let has_annotations = add(
+                    &mut matrix.sections,
                     &extra_section_tests,
                     Element {
                         kind: &ELEMENT_KIND_SECTION,
@@ -83,9 +95,8 @@ pub(crate) fn prepare(
                 }
 
                 for paragraph in &section.paragraphs {
-                    seen_ids.insert(&paragraph.id);
-                    matrix.paragraphs.add(
-                        annotations,
+                    add(
+                        &mut matrix.paragraphs,
                         &extra_paragraph_tests,
                         Element {
                             kind: &ELEMENT_KIND_PARAGRAPH,
@@ -100,9 +111,8 @@ pub(crate) fn prepare(
             }
 
             for option in &page.options {
-                seen_ids.insert(&option.id);
-                matrix.cli_options.add(
-                    annotations,
+                add(
+                    &mut matrix.cli_options,
                     &[],
                     Element {
                         kind: &ELEMENT_KIND_CLI_OPTION,
@@ -134,6 +144,12 @@ pub(crate) fn prepare(
     Ok(matrix)
 }
 
+/// ID of an element prefixed by the name of its documentation, which can be used in annotations
+/// to disambiguate between documentations.
+fn namespaced_id(documentation: &str, id: &str) -> String {
+    format!("{documentation}:{id}")
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) struct TraceabilityMatrix {
     pub(crate) sections: MatrixAnalysis,
@@ -172,6 +188,17 @@ pub(crate) fn new(kind: &'static ElementKind) -> Self {
         }
     }
 
+    /// All the elements of this kind, along with their link status and tests.
+    pub(crate) fn elements(&self) -> impl Iterator<Item = (&Element, LinkStatus, Option<&Link>)> {
+        let unlinked = self.unlinked.iter().map(|e| (e, LinkStatus::Unlinked, None));
+        let partially_linked = self
+            .partially_linked
+            .iter()
+            .map(|l| (&l.element, LinkStatus::PartiallyLinked, Some(l)));
+        let linked = self.linked.iter().map(|l| (&l.element, LinkStatus::Linked, Some(l)));
+        unlinked.chain(partially_linked).chain(linked)
+    }
+
     fn add(
         &mut self,
         annotations: &Annotations,
@@ -179,8 +206,11 @@ fn add(
         element: Element,
     ) -> bool {
         let mut tests = extra_tests.to_vec();
-        if let Some(files) = annotations.ids.get(&element.id) {
-            tests.extend(files.iter().map(|file| LinkTest::File(file.clone())));
+        let namespaced = namespaced_id(&element.page.documentation, &element.id);
+        for id in [&element.id, &namespaced] {
+            if let Some(files) = annotations.ids.get(id) {
+                tests.extend(files.iter().map(|file| LinkTest::File(file.clone())));
+            }
         }
 
         if tests.is_empty() {
@@ -188,10 +218,12 @@ fn add(
             false
         } else {
             tests.sort();
+            // The same test could annotate both the plain and the namespaced ID.
+            tests.dedup();
             let mut untested_targets = BTreeSet::new();
             for link_test in &tests {
                 if let LinkTest::File(annotated) = link_test {
-                    for target in &annotated.targets.ignored.0 {
+                    for target in annotated.targets.ignored.keys() {
                         untested_targets.insert(target.to_owned());
                     }
                 }
@@ -218,6 +250,34 @@ fn add(
     }
 }
 
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum LinkStatus {
+    Unlinked,
+    PartiallyLinked,
+    Linked,
+}
+
+impl LinkStatus {
+    pub(crate) fn color(&self) -> &'static str {
+        match self {
+            LinkStatus::Unlinked => "red",
+            LinkStatus::PartiallyLinked => "orange",
+            LinkStatus::Linked => "green",
+        }
+    }
+}
+
+impl std::fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkStatus::Unlinked => write!(f, "unlinked"),
+            LinkStatus::PartiallyLinked => write!(f, "partially linked"),
+            LinkStatus::Linked => write!(f, "linked"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub(crate) struct Link {
     pub(crate) element: Element,
@@ -363,6 +423,8 @@ fn test_prepare() -> Result<(), Error> {
         let documentations = [Documentation {
             name: "FLS".into(),
             url: "../fls".into(),
+            kinds: ELEMENT_KINDS.to_vec(),
+            history: Vec::new(),
             ids: TraceabilityIds {
                 documents: vec![
                     Document {
@@ -697,6 +759,75 @@ fn test_prepare() -> Result<(), Error> {
         Ok(())
     }
 
+    #[test]
+    fn test_prepare_multiple_documentations() -> Result<(), Error> {
+        let documentation = |name: &str, kinds: &[&'static ElementKind]| Documentation {
+            name: name.into(),
+            url: name.to_lowercase(),
+            kinds: kinds.to_vec(),
+            history: Vec::new(),
+            ids: TraceabilityIds {
+                documents: vec![Document {
+                    title: "Document".into(),
+                    link: "document.html".into(),
+                    informational: false,
+                    sections: vec![Section {
+                        id: "shared_section".into(),
+                        number: "1".into(),
+                        title: "Section".into(),
+                        link: "document.html#section".into(),
+                        informational: false,
+                        paragraphs: vec![Paragraph {
+                            id: format!("{}_paragraph", name.to_lowercase()),
+                            number: "1:1".into(),
+                            link: "document.html#paragraph".into(),
+                        }],
+                    }],
+                    options: vec![],
+                }],
+            },
+        };
+        let documentations = [
+            documentation("FLS", ELEMENT_KINDS),
+            // Sections of the safety manual are not part of the matrix.
+            documentation("SM", &[&ELEMENT_KIND_PARAGRAPH]),
+        ];
+
+        let annotations = Annotations {
+            ids: BTreeMap::from([
+                ("FLS:shared_section".into(), BTreeSet::from([test_itself("/example/fls.rs")])),
+                ("sm_paragraph".into(), BTreeSet::from([test_itself("/example/sm.rs")])),
+                ("SM:sm_paragraph".into(), BTreeSet::from([test_itself("/example/sm.rs")])),
+                ("SM:shared_section".into(), BTreeSet::from([test_itself("/example/bad.rs")])),
+                ("FLS:sm_paragraph".into(), BTreeSet::from([test_itself("/example/bad.rs")])),
+            ]),
+            ignored_tests: BTreeMap::new(),
+            considers_ignored_tests: true,
+        };
+
+        let matrix = prepare(&documentations, &annotations)?;
+
+        let ids = |links: &BTreeSet<Link>| {
+            links.iter().map(|l| (l.page.documentation.clone(), l.id.clone())).collect::<Vec<_>>()
+        };
+        assert_eq!(vec![("FLS".into(), "shared_section".into())], ids(&matrix.sections.linked));
+        assert!(matrix.sections.unlinked.is_empty());
+        assert_eq!(
+            vec![("FLS".into(), "fls_paragraph".into()), ("SM".into(), "sm_paragraph".into())],
+            ids(&matrix.paragraphs.linked)
+        );
+        // Annotating the same element with both the plain and the namespaced ID links it once.
+        let sm_paragraph = matrix.paragraphs.linked.iter().find(|l| l.id == "sm_paragraph");
+        assert_eq!(vec![link_test_itself("/example/sm.rs")], sm_paragraph.unwrap().tests);
+
+        assert_eq!(
+            vec!["FLS:sm_paragraph", "SM:shared_section"],
+            matrix.unknown_annotations.iter().map(|u| u.annotation.as_str()).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_element_numbers_ordering() {
         fn numberize(numbers: &[&str]) -> Vec<ElementNumber> {
@@ -2,18 +2,25 @@
 // SPDX-FileCopyrightText: The Ferrocene Developers
 
 use crate::annotations::{AnnotationSource, Annotations};
+use crate::diff::MatrixDiff;
 use crate::matrix::{ElementKind, LinkTest, Page, TraceabilityMatrix};
+use crate::suggestions::AnnotationWarnings;
+use crate::targets::TargetSummary;
+use crate::test_outcomes::IgnoredOn;
 use anyhow::Error;
 use askama::Template;
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[derive(Template)]
 #[template(path = "report.html")]
 struct Report<'a> {
     considers_ignored_tests: bool,
     matrix: &'a TraceabilityMatrix,
+    warnings: &'a AnnotationWarnings,
+    target: Option<&'a str>,
+    target_summaries: &'a [TargetSummary],
     summary: Vec<SummaryRow<'a>>,
-    ignored_tests: BTreeMap<String, BTreeSet<String>>,
+    ignored_tests: BTreeMap<String, IgnoredOn>,
     urls: Urls,
 }
 
@@ -45,6 +52,14 @@ struct SummaryItem {
     percentage: f32,
 }
 
+#[derive(Template)]
+#[template(path = "diff.html")]
+struct DiffReport<'a> {
+    diff: &'a MatrixDiff,
+    before: &'a str,
+    after: &'a str,
+}
+
 pub(crate) struct Urls {
     pub(crate) src: String,
 }
@@ -52,10 +67,16 @@ pub(crate) struct Urls {
 pub(crate) fn generate(
     annotations: &Annotations,
     matrix: &TraceabilityMatrix,
+    warnings: &AnnotationWarnings,
+    target: Option<&str>,
+    target_summaries: &[TargetSummary],
     urls: Urls,
 ) -> Result<String, Error> {
     Ok(Report {
         matrix,
+        warnings,
+        target,
+        target_summaries,
         summary: build_summary(matrix),
         urls,
         ignored_tests: annotations.ignored_tests.clone(),
@@ -64,6 +85,10 @@ pub(crate) fn generate(
     .render()?)
 }
 
+pub(crate) fn generate_diff(diff: &MatrixDiff, before: &str, after: &str) -> Result<String, Error> {
+    Ok(DiffReport { diff, before, after }.render()?)
+}
+
 fn build_summary(matrix: &TraceabilityMatrix) -> Vec<SummaryRow<'_>> {
     let sample_kinds = matrix
         .analyses_by_kind()
@@ -0,0 +1,465 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Help to fix annotations drifting from the documentations, for example after an update of the
+//! FLS renamed or removed some paragraphs.
+
+use crate::annotations::Annotations;
+use crate::documentations::{Documentation, TraceabilityIds};
+use crate::matrix::{
+    ElementKind, TraceabilityMatrix, ELEMENT_KIND_CLI_OPTION, ELEMENT_KIND_PARAGRAPH,
+    ELEMENT_KIND_SECTION,
+};
+use crate::utils::edit_distance;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+#[derive(Debug, PartialEq, Eq, Default)]
+pub(crate) struct AnnotationWarnings {
+    /// Help for each unknown annotation, when any is available.
+    pub(crate) unknown: BTreeMap<String, UnknownAnnotationHelp>,
+    /// Tests whose annotations only point at informational content, along with the annotations.
+    pub(crate) informational_only: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl AnnotationWarnings {
+    pub(crate) fn help_for(&self, annotation: &str) -> Option<String> {
+        self.unknown.get(annotation).map(|help| help.to_string())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum UnknownAnnotationHelp {
+    /// The ID is present in a previous version of the documentation, and an element with the same
+    /// title is present in the current version with a different ID.
+    Renamed { history: PathBuf, renamed_to: Suggestion },
+    /// The ID is present in a previous version of the documentation, but not anymore.
+    Removed { history: PathBuf, name: String, closest: Option<Suggestion> },
+    /// The ID was never present in the documentation, but is similar to an existing one.
+    Typo { closest: Suggestion },
+}
+
+impl std::fmt::Display for UnknownAnnotationHelp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnknownAnnotationHelp::Renamed { history, renamed_to } => {
+                write!(f, "renamed to {renamed_to} since {}", history.display())
+            }
+            UnknownAnnotationHelp::Removed { history, name, closest } => {
+                write!(f, "{name} was removed since {}", history.display())?;
+                if let Some(closest) = closest {
+                    write!(f, ", the closest element is {closest}")?;
+                }
+                Ok(())
+            }
+            UnknownAnnotationHelp::Typo { closest } => write!(f, "did you mean {closest}?"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Suggestion {
+    pub(crate) id: String,
+    pub(crate) name: String,
+}
+
+impl std::fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.id, self.name)
+    }
+}
+
+pub(crate) fn analyze(
+    documentations: &[Documentation],
+    annotations: &Annotations,
+    matrix: &TraceabilityMatrix,
+) -> AnnotationWarnings {
+    let current = documentations
+        .iter()
+        .map(|documentation| {
+            let mut elements = flatten(&documentation.name, &documentation.ids);
+            elements.retain(|element| documentation.contributes(element.kind));
+            elements
+        })
+        .collect::<Vec<_>>();
+
+    let mut warnings = AnnotationWarnings::default();
+    for unknown in &matrix.unknown_annotations {
+        if warnings.unknown.contains_key(&unknown.annotation) {
+            continue;
+        }
+        if let Some(help) = unknown_annotation_help(documentations, &current, &unknown.annotation) {
+            warnings.unknown.insert(unknown.annotation.clone(), help);
+        }
+    }
+
+    let mut informational = HashMap::new();
+    for (documentation, elements) in documentations.iter().zip(&current) {
+        for element in elements {
+            informational.insert(element.id.to_string(), element.informational);
+            informational
+                .insert(format!("{}:{}", documentation.name, element.id), element.informational);
+        }
+    }
+    let mut tests: BTreeMap<String, (bool, BTreeSet<String>)> = BTreeMap::new();
+    for (annotation, files) in &annotations.ids {
+        let Some(is_informational) = informational.get(annotation) else { continue };
+        for file in files {
+            let (only_informational, ids) =
+                tests.entry(file.to_string()).or_insert((true, BTreeSet::new()));
+            *only_informational &= *is_informational;
+            ids.insert(annotation.clone());
+        }
+    }
+    warnings.informational_only = tests
+        .into_iter()
+        .filter(|(_, (only_informational, _))| *only_informational)
+        .map(|(test, (_, ids))| (test, ids))
+        .collect();
+
+    warnings
+}
+
+fn unknown_annotation_help(
+    documentations: &[Documentation],
+    current: &[Vec<KnownElement<'_>>],
+    annotation: &str,
+) -> Option<UnknownAnnotationHelp> {
+    // Namespaced annotations only refer to the elements of their documentation.
+    let (namespace, id) = match annotation.split_once(':') {
+        Some((namespace, id)) if documentations.iter().any(|d| d.name == namespace) => {
+            (Some(namespace), id)
+        }
+        _ => (None, annotation),
+    };
+    let applicable = || {
+        documentations
+            .iter()
+            .zip(current)
+            .filter(move |(d, _)| namespace.map(|n| n == d.name).unwrap_or(true))
+    };
+
+    for (documentation, current) in applicable() {
+        // History files are listed from the oldest to the most recent.
+        for (path, ids) in documentation.history.iter().rev() {
+            let history = flatten(&documentation.name, ids);
+            let Some(old) = history.iter().find(|element| element.id == id) else { continue };
+
+            let same_kind = || current.iter().filter(|element| element.kind == old.kind);
+            if let Some(new) = same_kind().find(|element| element.descriptor == old.descriptor) {
+                return Some(UnknownAnnotationHelp::Renamed {
+                    history: path.clone(),
+                    renamed_to: new.suggestion(),
+                });
+            }
+            return Some(UnknownAnnotationHelp::Removed {
+                history: path.clone(),
+                name: old.name.clone(),
+                closest: closest(same_kind(), id, Some(&old.title)),
+            });
+        }
+    }
+
+    let candidates = applicable().flat_map(|(_, current)| current.iter());
+    closest(candidates, id, None).map(|closest| UnknownAnnotationHelp::Typo { closest })
+}
+
+/// Finds the element with the ID or title most similar to the ones provided.
+fn closest<'a>(
+    candidates: impl Iterator<Item = &'a KnownElement<'a>>,
+    id: &str,
+    title: Option<&str>,
+) -> Option<Suggestion> {
+    candidates
+        .filter_map(|candidate| {
+            let by_id = similarity(id, candidate.id);
+            let by_title = title.and_then(|title| similarity(title, &candidate.title));
+            let distance = match (by_id, by_title) {
+                (Some(a), Some(b)) => a.min(b),
+                (distance, None) | (None, distance) => distance?,
+            };
+            Some((distance, candidate.id, candidate))
+        })
+        .min_by_key(|(distance, id, _)| (*distance, *id))
+        .map(|(_, _, candidate)| candidate.suggestion())
+}
+
+/// Edit distance between the two strings, if they are similar enough to be worth suggesting.
+fn similarity(a: &str, b: &str) -> Option<usize> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let distance = edit_distance(a, b);
+    if distance * 3 <= a.chars().count().max(b.chars().count()) {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+struct KnownElement<'a> {
+    kind: &'static ElementKind,
+    id: &'a str,
+    /// Human readable name of the element.
+    name: String,
+    /// Identifies the element across versions of the documentation, regardless of its ID.
+    descriptor: String,
+    title: String,
+    informational: bool,
+}
+
+impl KnownElement<'_> {
+    fn suggestion(&self) -> Suggestion {
+        Suggestion { id: self.id.into(), name: self.name.clone() }
+    }
+}
+
+fn flatten<'a>(documentation: &str, ids: &'a TraceabilityIds) -> Vec<KnownElement<'a>> {
+    let mut elements = Vec::new();
+    for page in &ids.documents {
+        for section in &page.sections {
+            let informational = page.informational || section.informational;
+            elements.push(KnownElement {
+                kind: &ELEMENT_KIND_SECTION,
+                id: &section.id,
+                name: format!("{documentation} {} {}", section.number, section.title),
+                descriptor: section.title.clone(),
+                title: section.title.clone(),
+                informational,
+            });
+            for paragraph in &section.paragraphs {
+                // Paragraph numbers are in the "section:paragraph" format, and the section
+                // number can change between versions even if the paragraph didn't.
+                let (_, number) =
+                    paragraph.number.split_once(':').unwrap_or(("", &paragraph.number));
+                elements.push(KnownElement {
+                    kind: &ELEMENT_KIND_PARAGRAPH,
+                    id: &paragraph.id,
+                    name: format!("{documentation} {}", paragraph.number),
+                    descriptor: format!("{}:{number}", section.title),
+                    title: section.title.clone(),
+                    informational,
+                });
+            }
+        }
+        for option in &page.options {
+            let title = format!("{} {}", option.program, option.option);
+            elements.push(KnownElement {
+                kind: &ELEMENT_KIND_CLI_OPTION,
+                id: &option.id,
+                name: format!("{documentation} {title}"),
+                descriptor: title.clone(),
+                title,
+                informational: page.informational,
+            });
+        }
+    }
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::{AnnotatedFile, AnnotationSource};
+    use crate::documentations::{Document, Paragraph, Section};
+    use crate::matrix::ELEMENT_KINDS;
+
+    #[test]
+    fn test_unknown_annotations() {
+        let old = ids(&[
+            (
+                "fls_expressions",
+                "6",
+                "Expressions",
+                false,
+                &[("fls_renamed", "6:1"), ("fls_gone", "6:9")],
+            ),
+            ("fls_removed_section", "7", "Removed section", false, &[]),
+        ]);
+        let new = ids(&[(
+            "fls_expressions",
+            "6.1",
+            "Expressions",
+            false,
+            &[("fls_new_name", "6.1:1"), ("fls_other", "6.1:2")],
+        )]);
+        let documentations = [documentation("FLS", new, vec![("history/1.0.json".into(), old)])];
+
+        let warnings = warnings(
+            &documentations,
+            &[
+                ("fls_renamed", "tests/ui/a.rs"),
+                ("fls_gone", "tests/ui/b.rs"),
+                ("fls_removed_section", "tests/ui/c.rs"),
+                ("fls_expresions", "tests/ui/d.rs"),
+                ("FLS:fls_new_nam", "tests/ui/e.rs"),
+                ("OTHER:fls_new_name", "tests/ui/f.rs"),
+                ("fls_completely_different", "tests/ui/g.rs"),
+            ],
+        );
+
+        assert_eq!(
+            BTreeMap::from([
+                (
+                    "fls_renamed".into(),
+                    UnknownAnnotationHelp::Renamed {
+                        history: "history/1.0.json".into(),
+                        renamed_to: suggestion("fls_new_name", "FLS 6.1:1"),
+                    }
+                ),
+                (
+                    "fls_gone".into(),
+                    UnknownAnnotationHelp::Removed {
+                        history: "history/1.0.json".into(),
+                        name: "FLS 6:9".into(),
+                        // The closest paragraph, by title of its section.
+                        closest: Some(suggestion("fls_new_name", "FLS 6.1:1")),
+                    }
+                ),
+                (
+                    "fls_removed_section".into(),
+                    UnknownAnnotationHelp::Removed {
+                        history: "history/1.0.json".into(),
+                        name: "FLS 7 Removed section".into(),
+                        closest: None,
+                    }
+                ),
+                (
+                    "fls_expresions".into(),
+                    UnknownAnnotationHelp::Typo {
+                        closest: suggestion("fls_expressions", "FLS 6.1 Expressions"),
+                    }
+                ),
+                (
+                    "FLS:fls_new_nam".into(),
+                    UnknownAnnotationHelp::Typo {
+                        closest: suggestion("fls_new_name", "FLS 6.1:1"),
+                    }
+                ),
+                (
+                    "OTHER:fls_new_name".into(),
+                    UnknownAnnotationHelp::Typo {
+                        closest: suggestion("fls_new_name", "FLS 6.1:1"),
+                    }
+                ),
+            ]),
+            warnings.unknown
+        );
+        assert_eq!(
+            Some("renamed to fls_new_name (FLS 6.1:1) since history/1.0.json".into()),
+            warnings.help_for("fls_renamed")
+        );
+        assert_eq!(
+            Some(
+                "FLS 6:9 was removed since history/1.0.json, \
+                 the closest element is fls_new_name (FLS 6.1:1)"
+                    .into()
+            ),
+            warnings.help_for("fls_gone")
+        );
+        assert_eq!(
+            Some("did you mean fls_expressions (FLS 6.1 Expressions)?".into()),
+            warnings.help_for("fls_expresions")
+        );
+        assert_eq!(None, warnings.help_for("fls_completely_different"));
+    }
+
+    #[test]
+    fn test_informational_only() {
+        let documentations = [documentation(
+            "FLS",
+            ids(&[
+                ("fls_normative", "1", "Normative", false, &[("fls_normative_p", "1:1")]),
+                ("fls_informative", "2", "Informative", true, &[("fls_informative_p", "2:1")]),
+            ]),
+            Vec::new(),
+        )];
+
+        let warnings = warnings(
+            &documentations,
+            &[
+                ("fls_informative", "tests/ui/only-informational.rs"),
+                ("FLS:fls_informative_p", "tests/ui/only-informational.rs"),
+                ("fls_informative", "tests/ui/mixed.rs"),
+                ("fls_normative_p", "tests/ui/mixed.rs"),
+                ("fls_normative", "tests/ui/normative.rs"),
+                // Unknown annotations are not considered.
+                ("fls_unknown", "tests/ui/normative.rs"),
+                ("fls_unknown", "tests/ui/unknown.rs"),
+            ],
+        );
+
+        assert_eq!(
+            BTreeMap::from([(
+                "tests/ui/only-informational.rs".into(),
+                BTreeSet::from(["FLS:fls_informative_p".into(), "fls_informative".into()])
+            )]),
+            warnings.informational_only
+        );
+    }
+
+    fn warnings(
+        documentations: &[Documentation],
+        annotated: &[(&str, &str)],
+    ) -> AnnotationWarnings {
+        let mut annotations = Annotations::new();
+        for (id, test) in annotated {
+            annotations.ids.entry(id.to_string()).or_default().insert(AnnotatedFile {
+                test: test.into(),
+                source: AnnotationSource::TestItself,
+                targets: Default::default(),
+            });
+        }
+        let matrix = crate::matrix::prepare(documentations, &annotations).unwrap();
+        analyze(documentations, &annotations, &matrix)
+    }
+
+    fn documentation(
+        name: &str,
+        ids: TraceabilityIds,
+        history: Vec<(PathBuf, TraceabilityIds)>,
+    ) -> Documentation {
+        Documentation {
+            name: name.into(),
+            url: "".into(),
+            ids,
+            kinds: ELEMENT_KINDS.to_vec(),
+            history,
+        }
+    }
+
+    type SectionSpec<'a> = (&'a str, &'a str, &'a str, bool, &'a [(&'a str, &'a str)]);
+
+    fn ids(sections: &[SectionSpec<'_>]) -> TraceabilityIds {
+        TraceabilityIds {
+            documents: vec![Document {
+                title: "Document".into(),
+                link: "document.html".into(),
+                informational: false,
+                sections: sections
+                    .iter()
+                    .map(|(id, number, title, informational, paragraphs)| Section {
+                        id: id.to_string(),
+                        number: number.to_string(),
+                        title: title.to_string(),
+                        link: "".into(),
+                        informational: *informational,
+                        paragraphs: paragraphs
+                            .iter()
+                            .map(|(id, number)| Paragraph {
+                                id: id.to_string(),
+                                number: number.to_string(),
+                                link: "".into(),
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+                options: Vec::new(),
+            }],
+        }
+    }
+
+    fn suggestion(id: &str, name: &str) -> Suggestion {
+        Suggestion { id: id.into(), name: name.into() }
+    }
+}
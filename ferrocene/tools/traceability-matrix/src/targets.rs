@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Coverage of the traceability matrix on each target. An element is only covered on a target if
+//! one of its tests was executed on that target: a paragraph only tested on x86_64 is not covered
+//! for aarch64-unknown-none.
+
+use crate::annotations::Annotations;
+use crate::documentations::Documentation;
+use crate::matrix::{ElementKind, LinkStatus};
+use anyhow::Error;
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct TargetSummary {
+    pub(crate) target: String,
+    pub(crate) kinds: Vec<TargetKindSummary>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct TargetKindSummary {
+    pub(crate) kind: &'static ElementKind,
+    pub(crate) linked: usize,
+    pub(crate) total: usize,
+}
+
+impl TargetKindSummary {
+    pub(crate) fn percentage(&self) -> f32 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.linked as f32 * 100.0 / self.total as f32
+        }
+    }
+}
+
+/// Prepares the matrix of each target tests were executed on, and summarizes their coverage.
+pub(crate) fn summarize(
+    documentations: &[Documentation],
+    annotations: &Annotations,
+) -> Result<Vec<TargetSummary>, Error> {
+    let mut summaries = Vec::new();
+    for target in annotations.targets() {
+        let matrix = crate::matrix::prepare(documentations, &annotations.for_target(&target))?;
+        let kinds = matrix
+            .analyses_by_kind()
+            .map(|analysis| {
+                let (mut linked, mut total) = (0, 0);
+                for (_, status, _) in analysis.elements() {
+                    total += 1;
+                    if status == LinkStatus::Linked {
+                        linked += 1;
+                    }
+                }
+                TargetKindSummary { kind: analysis.kind, linked, total }
+            })
+            .collect();
+        summaries.push(TargetSummary { target, kinds });
+    }
+    Ok(summaries)
+}
+
+/// Restricts the annotations to the target selected by the user, if any.
+pub(crate) fn select(annotations: Annotations, target: Option<&str>) -> Result<Annotations, Error> {
+    let Some(target) = target else { return Ok(annotations) };
+    if !annotations.considers_ignored_tests {
+        anyhow::bail!("selecting a target requires the test outcomes to be provided");
+    }
+    if !annotations.targets().contains(target) {
+        anyhow::bail!("no test was executed on target {target}");
+    }
+    Ok(annotations.for_target(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::{AnnotatedFile, AnnotationSource, DisplayCommaSeparatedSet, Targets};
+    use crate::documentations::{Document, Paragraph, Section, TraceabilityIds};
+    use crate::matrix::{
+        ELEMENT_KINDS, ELEMENT_KIND_CLI_OPTION, ELEMENT_KIND_PARAGRAPH, ELEMENT_KIND_SECTION,
+    };
+    use std::collections::BTreeSet;
+
+    const X86: &str = "x86_64-unknown-linux-gnu";
+    const ARM: &str = "aarch64-unknown-none";
+
+    #[test]
+    fn test_summarize() -> Result<(), Error> {
+        let summaries = summarize(&documentations(), &annotations())?;
+
+        let kinds = |sections, paragraphs| {
+            vec![
+                TargetKindSummary { kind: &ELEMENT_KIND_SECTION, linked: sections, total: 1 },
+                TargetKindSummary { kind: &ELEMENT_KIND_PARAGRAPH, linked: paragraphs, total: 2 },
+                TargetKindSummary { kind: &ELEMENT_KIND_CLI_OPTION, linked: 0, total: 0 },
+            ]
+        };
+        assert_eq!(
+            vec![
+                TargetSummary { target: ARM.into(), kinds: kinds(0, 1) },
+                TargetSummary { target: X86.into(), kinds: kinds(1, 2) },
+            ],
+            summaries
+        );
+        assert_eq!(50.0, summaries[0].kinds[1].percentage());
+        assert_eq!(100.0, summaries[0].kinds[2].percentage());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select() -> Result<(), Error> {
+        let all = select(annotations(), None)?;
+        assert_eq!(annotations().ids, all.ids);
+
+        let arm = select(annotations(), Some(ARM))?;
+        assert_eq!(vec!["fls_both"], arm.ids.keys().collect::<Vec<_>>());
+
+        let err = select(annotations(), Some("riscv64gc-unknown-none-elf")).unwrap_err();
+        assert_eq!("no test was executed on target riscv64gc-unknown-none-elf", err.to_string());
+
+        let mut without_outcomes = annotations();
+        without_outcomes.considers_ignored_tests = false;
+        assert!(select(without_outcomes, Some(ARM)).is_err());
+        Ok(())
+    }
+
+    fn annotations() -> Annotations {
+        let file = |test: &str, executed: &[&str], ignored: &[&str]| AnnotatedFile {
+            test: test.into(),
+            source: AnnotationSource::TestItself,
+            targets: Targets {
+                executed: DisplayCommaSeparatedSet(
+                    executed.iter().map(|t| t.to_string()).collect(),
+                ),
+                ignored: ignored.iter().map(|t| (t.to_string(), BTreeSet::new())).collect(),
+            },
+        };
+
+        let mut annotations = Annotations::new();
+        annotations.ids.insert(
+            "fls_both".into(),
+            BTreeSet::from([file("tests/ui/both.rs", &[X86, ARM], &[])]),
+        );
+        annotations.ids.insert(
+            "fls_section".into(),
+            BTreeSet::from([file("tests/ui/section.rs", &[X86], &[ARM])]),
+        );
+        annotations
+            .ids
+            .insert("fls_x86".into(), BTreeSet::from([file("tests/ui/x86.rs", &[X86], &[])]));
+        annotations
+    }
+
+    fn documentations() -> Vec<Documentation> {
+        let paragraph = |id: &str, number: &str| Paragraph {
+            id: id.into(),
+            number: number.into(),
+            link: "".into(),
+        };
+        vec![Documentation {
+            name: "FLS".into(),
+            url: "".into(),
+            ids: TraceabilityIds {
+                documents: vec![Document {
+                    title: "Expressions".into(),
+                    link: "expressions.html".into(),
+                    informational: false,
+                    sections: vec![Section {
+                        id: "fls_section".into(),
+                        number: "6".into(),
+                        title: "Expressions".into(),
+                        link: "".into(),
+                        informational: false,
+                        paragraphs: vec![paragraph("fls_both", "6:1"), paragraph("fls_x86", "6:2")],
+                    }],
+                    options: Vec::new(),
+                }],
+            },
+            kinds: ELEMENT_KINDS.to_vec(),
+            history: Vec::new(),
+        }]
+    }
+}
@@ -13,13 +13,19 @@ pub(crate) struct TestOutcomes {
     // value is targets on which the tests were executed
     pub(crate) executed_tests: BTreeMap<String, BTreeSet<String>>,
     // key is name of test, also represented by a path
-    // value can be any number of ignored tests
-    pub(crate) ignored_tests: BTreeMap<String, BTreeSet<String>>,
+    // value is targets on which the tests were ignored, with the reasons why
+    pub(crate) ignored_tests: BTreeMap<String, IgnoredOn>,
 }
 
+/// Targets a test was ignored on, each with the reasons reported by libtest. A test can have
+/// multiple reasons on the same target when its revisions are ignored for different reasons, and
+/// none when it was ignored without a message.
+pub(crate) type IgnoredOn = BTreeMap<String, BTreeSet<String>>;
+
 impl TestOutcomes {
     pub(crate) fn load(directory: &Path) -> Result<Self, Error> {
         let mut test_outcomes = TestOutcomes::default();
+        let mut unattributed = BTreeSet::new();
 
         for entry in std::fs::read_dir(directory)? {
             let path = entry?.path();
@@ -59,32 +65,104 @@ pub(crate) fn load(directory: &Path) -> Result<Self, Error> {
                             };
                             let name = name.rsplit_once('#').map(|(n, _)| n).unwrap_or(name).into();
 
-                            if let MetricsTestOutcome::Ignored = outcome {
-                                test_outcomes
-                                    .ignored_tests
-                                    .entry(name)
-                                    .or_insert_with(BTreeSet::new)
-                                    .insert(target.to_owned());
-                            } else {
-                                test_outcomes
-                                    .executed_tests
-                                    .entry(name)
-                                    .or_insert_with(BTreeSet::new)
-                                    .insert(target.to_owned());
-                            }
+                            test_outcomes.record(name, target, outcome);
                         }
                     }
-                    // Ignore test results from Cargo packages, as we don't consider those in the
-                    // traceability matrix (yet?).
                     MetricsNode::TestSuite {
-                        metadata: TestSuiteMetadata::CargoPackage, ..
-                    } => {}
+                        tests,
+                        metadata: TestSuiteMetadata::CargoPackage { crates, target },
+                    } => {
+                        for Test { name, outcome } in tests {
+                            match test_crate(crates, name) {
+                                Some(krate) => test_outcomes.record(
+                                    cargo_package_test_key(krate, name),
+                                    target,
+                                    outcome,
+                                ),
+                                None => {
+                                    unattributed.insert(format!("{name} ({})", crates.join(", ")));
+                                }
+                            }
+                        }
+                    }
                 }
             }
         }
 
+        print_unattributed_tests(unattributed);
         Ok(test_outcomes)
     }
+
+    fn record(&mut self, name: String, target: &str, outcome: &MetricsTestOutcome) {
+        match outcome {
+            MetricsTestOutcome::Ignored { ignore_reason } => {
+                let reasons = self
+                    .ignored_tests
+                    .entry(name)
+                    .or_default()
+                    .entry(target.to_owned())
+                    .or_default();
+                reasons.extend(ignore_reason.iter().cloned());
+            }
+            MetricsTestOutcome::Passed => {
+                self.executed_tests.entry(name).or_default().insert(target.to_owned());
+            }
+        }
+    }
+}
+
+/// Name used in the test outcomes for a `#[test]` function of a Cargo package, with `name` being
+/// the full path of the function inside of the crate.
+pub(crate) fn test_function_key(krate: &str, name: &str) -> String {
+    format!("{krate}::{name}")
+}
+
+/// Name used in the test outcomes for a doctest of a Cargo package, with `file` being relative to
+/// the root of the package (like `src/num/mod.rs`).
+pub(crate) fn doctest_key(krate: &str, file: &str, line: usize) -> String {
+    format!("{krate}::{file}:{line}")
+}
+
+/// Returns which of the crates tested by the same invocation the test belongs to. Libtest test
+/// names don't include the crate name, so when multiple crates are tested at once only doctests
+/// (whose name includes the path of the file) can be attributed. Crediting the test to all the
+/// crates instead would mark a test of another crate with the same path as executed.
+fn test_crate<'a>(crates: &'a [String], name: &str) -> Option<&'a str> {
+    if let [krate] = crates {
+        return Some(krate);
+    }
+    let (file, _) = name.split_once(" - ")?;
+    let components = file.split('/').collect::<Vec<_>>();
+    let src = components.iter().position(|component| *component == "src")?;
+    let package_dir = components.get(src.checked_sub(1)?)?;
+    crates.iter().map(|krate| krate.as_str()).find(|krate| krate == package_dir)
+}
+
+fn print_unattributed_tests(unattributed: BTreeSet<String>) {
+    if !unattributed.is_empty() {
+        eprintln!(
+            "The following tests were executed together with other crates and can't be \
+            attributed to one of them, so their outcome is not considered (test the crates \
+            separately to consider them):"
+        );
+        for test in unattributed {
+            eprintln!("  - {test}");
+        }
+    }
+}
+
+fn cargo_package_test_key(krate: &str, name: &str) -> String {
+    // Doctest names are in the "path/to/file.rs - item::path (line N)" format, with the path
+    // being relative to the directory Cargo was invoked from rather than to the package.
+    let doctest = name.split_once(" - ").and_then(|(file, rest)| {
+        let line = rest.rsplit_once("(line ")?.1.strip_suffix(')')?.parse().ok()?;
+        let start = file.split('/').position(|component| component == "src")?;
+        Some((file.split('/').skip(start).collect::<Vec<_>>().join("/"), line))
+    });
+    match doctest {
+        Some((file, line)) => doctest_key(krate, &file, line),
+        None => test_function_key(krate, name),
+    }
 }
 
 #[derive(serde::Deserialize)]
@@ -109,23 +187,24 @@ enum MetricsNode {
 #[derive(serde::Deserialize)]
 #[serde(tag = "kind", rename_all = "snake_case")]
 enum TestSuiteMetadata {
-    CargoPackage,
+    CargoPackage { crates: Vec<String>, target: String },
     Compiletest { target: String },
 }
 
 #[derive(serde::Deserialize)]
 struct Test {
     name: String,
+    #[serde(flatten)]
     outcome: MetricsTestOutcome,
 }
 
 #[derive(Debug, serde::Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[serde(tag = "outcome", rename_all = "snake_case")]
 // Failed is missing so as to trigger a deserialize failure,
 // because we should not reach this far if any test fails.
 enum MetricsTestOutcome {
     Passed,
-    Ignored,
+    Ignored { ignore_reason: Option<String> },
 }
 
 #[cfg(test)]
@@ -174,6 +253,7 @@ fn test_load_outcomes() {
                                                 {
                                                     "name": "[ui] tests/ui/baz.rs",
                                                     "outcome": "ignored",
+                                                    "ignore_reason": "only executed on x86_64",
                                                 },
                                             ],
                                         },
@@ -206,6 +286,36 @@ fn test_load_outcomes() {
                                 }
                             ],
                         },
+                        {
+                            "kind": "rustbuild_step",
+                            "type": "bootstrap::test::Crate",
+                            "children": [
+                                {
+                                    "kind": "test_suite",
+                                    "metadata": {
+                                        "crates": ["core"],
+                                        "target": "aarch64-unknown-linux-gnu",
+                                        "host": "aarch64-unknown-linux-gnu",
+                                        "stage": 1,
+                                        "kind": "cargo_package",
+                                    },
+                                    "tests": [
+                                        {
+                                            "name": "num::test_add",
+                                            "outcome": "passed",
+                                        },
+                                        {
+                                            "name": "num::test_sub",
+                                            "outcome": "ignored",
+                                        },
+                                        {
+                                            "name": "core/src/num/mod.rs - num::u8::checked_add (line 42)",
+                                            "outcome": "passed",
+                                        },
+                                    ],
+                                },
+                            ],
+                        },
                         {
                             "kind": "rustbuild_step",
                             "type": "bootstrap::test::Crate",
@@ -325,13 +435,92 @@ fn test_load_outcomes() {
                         "tests/codegen/foo.rs".into(),
                         BTreeSet::from(["aarch64-unknown-linux-gnu".into()])
                     ),
+                    (
+                        "core::num::test_add".into(),
+                        BTreeSet::from(["aarch64-unknown-linux-gnu".into()])
+                    ),
+                    (
+                        "core::src/num/mod.rs:42".into(),
+                        BTreeSet::from(["aarch64-unknown-linux-gnu".into()])
+                    ),
+                ]),
+                ignored_tests: BTreeMap::from([
+                    (
+                        "tests/ui/baz.rs".into(),
+                        BTreeMap::from([(
+                            "aarch64-unknown-linux-gnu".into(),
+                            BTreeSet::from(["only executed on x86_64".into()])
+                        )])
+                    ),
+                    (
+                        "core::num::test_sub".into(),
+                        BTreeMap::from([("aarch64-unknown-linux-gnu".into(), BTreeSet::new())])
+                    ),
                 ]),
-                ignored_tests: BTreeMap::from([(
-                    "tests/ui/baz.rs".into(),
-                    BTreeSet::from(["aarch64-unknown-linux-gnu".into()])
-                )]),
             },
             outcomes,
         )
     }
+
+    #[test]
+    fn test_load_outcomes_of_multiple_crates() {
+        let dir = TempDir::new().unwrap();
+        let content = json!({
+            "format_version": 1,
+            "invocations": [
+                {
+                    "children": [
+                        {
+                            "kind": "test_suite",
+                            "metadata": {
+                                "crates": ["core", "alloc"],
+                                "target": "x86_64-unknown-linux-gnu",
+                                "kind": "cargo_package",
+                            },
+                            "tests": [
+                                {
+                                    "name": "num::foo",
+                                    "outcome": "passed",
+                                },
+                                {
+                                    "name": "alloc/src/vec.rs - vec::Vec (line 10)",
+                                    "outcome": "passed",
+                                },
+                                {
+                                    "name": "std/src/lib.rs - (line 1)",
+                                    "outcome": "passed",
+                                },
+                            ],
+                        },
+                    ],
+                },
+            ],
+        });
+        std::fs::write(dir.path().join("runner.json"), content.to_string()).unwrap();
+
+        // num::foo could be either core::num::foo or alloc::num::foo, so it's not recorded.
+        assert_eq!(
+            TestOutcomes {
+                executed_tests: BTreeMap::from([(
+                    "alloc::src/vec.rs:10".into(),
+                    BTreeSet::from(["x86_64-unknown-linux-gnu".into()])
+                )]),
+                ignored_tests: BTreeMap::new(),
+            },
+            TestOutcomes::load(dir.path()).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_test_crate() {
+        let crates = ["core".to_string(), "alloc".to_string()];
+        assert_eq!(Some("core"), test_crate(&crates[..1], "num::foo"));
+        assert_eq!(None, test_crate(&crates, "num::foo"));
+        assert_eq!(Some("alloc"), test_crate(&crates, "alloc/src/vec.rs - vec::Vec (line 10)"));
+        assert_eq!(
+            Some("core"),
+            test_crate(&crates, "library/core/src/num/mod.rs - num::u8::checked_add (line 42)")
+        );
+        assert_eq!(None, test_crate(&crates, "std/src/lib.rs - (line 1)"));
+    }
 }
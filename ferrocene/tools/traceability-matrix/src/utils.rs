@@ -19,6 +19,23 @@ pub(crate) fn capitalize(input: &str) -> String {
     }
 }
 
+/// Number of single character insertions, deletions or substitutions needed to turn one string
+/// into the other (the Levenshtein distance).
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -31,4 +48,16 @@ fn test_capitalize() {
         assert_eq!("HELLO WORLD", capitalize("HELLO WORLD"));
         assert_eq!("SSello", capitalize("ßello"));
     }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(0, edit_distance("", ""));
+        assert_eq!(3, edit_distance("", "abc"));
+        assert_eq!(3, edit_distance("abc", ""));
+        assert_eq!(0, edit_distance("fls_abc", "fls_abc"));
+        assert_eq!(1, edit_distance("fls_abc", "fls_abd"));
+        assert_eq!(1, edit_distance("fls_abc", "fls_ab"));
+        assert_eq!(2, edit_distance("fls_abc", "fls_bac"));
+        assert_eq!(3, edit_distance("kitten", "sitting"));
+    }
 }
@@ -1807,9 +1807,10 @@ fn run(self, builder: &Builder<'_>) {
             cmd.arg(&exclude);
         }
 
-        for ignored in crate::ferrocene::ignored_tests_for_suite(builder, self.target, self.path) {
-            cmd.arg("--skip");
-            cmd.arg(&ignored);
+        for (test, reason) in
+            crate::ferrocene::ignored_tests_for_suite(builder, self.target, self.path)
+        {
+            cmd.arg("--ferrocene-ignored-test").arg(format!("{test}={reason}"));
         }
 
         // Get paths from cmd args
@@ -903,6 +903,7 @@ macro_rules! describe {
             ),
             Kind::Run => describe!(
                 crate::ferrocene::run::TraceabilityMatrix,
+                crate::ferrocene::run::DocumentSignaturesStatus,
                 run::ExpandYamlAnchors,
                 run::BuildManifest,
                 run::BumpStage0,
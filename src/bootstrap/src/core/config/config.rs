@@ -340,8 +340,10 @@ pub struct Config {
     pub ferrocene_aws_profile: Option<String>,
     pub ferrocene_traceability_matrix_mode: FerroceneTraceabilityMatrixMode,
     pub ferrocene_test_outcomes_dir: Option<PathBuf>,
+    pub ferrocene_traceability_matrix_gates: Option<PathBuf>,
     pub ferrocene_oxidos_src: Option<String>,
     pub ferrocene_tarball_signing_kms_key_arn: Option<String>,
+    pub ferrocene_tarball_signing_key_file: Option<PathBuf>,
     pub ferrocene_document_signatures_s3_bucket: String,
     pub ferrocene_ignore_document_signatures: bool,
     pub ferrocene_technical_report_url: Option<String>,
@@ -1172,8 +1174,10 @@ struct Ferrocene {
         aws_profile: Option<String> = "aws-profile",
         traceability_matrix_mode: Option<String> = "traceability-matrix-mode",
         test_outcomes_dir: Option<PathBuf> = "test-outcomes-dir",
+        traceability_matrix_gates: Option<PathBuf> = "traceability-matrix-gates",
         oxidos_src: Option<String> = "oxidos-src",
         tarball_signing_kms_key_arn: Option<String> = "tarball-signing-kms-key-arn",
+        tarball_signing_key_file: Option<PathBuf> = "tarball-signing-key-file",
         document_signatures_s3_bucket: Option<String> = "document-signatures-s3-bucket",
         ignore_document_signatures: Option<bool> = "ignore-document-signatures",
         technical_report_url: Option<String> = "technical-report-url",
@@ -1896,8 +1900,10 @@ fn get_table(option: &str) -> Result<TomlConfig, toml::de::Error> {
             };
             config.ferrocene_aws_profile = f.aws_profile;
             config.ferrocene_test_outcomes_dir = f.test_outcomes_dir;
+            config.ferrocene_traceability_matrix_gates = f.traceability_matrix_gates;
             config.ferrocene_oxidos_src = f.oxidos_src;
             config.ferrocene_tarball_signing_kms_key_arn = f.tarball_signing_kms_key_arn;
+            config.ferrocene_tarball_signing_key_file = f.tarball_signing_key_file;
             config.ferrocene_document_signatures_s3_bucket = f
                 .document_signatures_s3_bucket
                 .unwrap_or_else(|| "ferrocene-document-signatures".into());
@@ -299,6 +299,11 @@ fn run(self, builder: &Builder<'_>) -> Self::Output {
 
         let tarball = Tarball::new(builder, "ferrocene-self-test", &self.target.triple);
         tarball.add_file(self_test, "bin", 0o755);
+        tarball.add_file(
+            builder.src.join("ferrocene").join("tools").join("self-test").join("targets.toml"),
+            "share/ferrocene/self-test",
+            0o644,
+        );
         tarball.generate()
     }
 }
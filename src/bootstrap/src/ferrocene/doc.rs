@@ -526,6 +526,24 @@ fn gather_other_document_ids(builder: &Builder<'_>, target: TargetSelection) ->
             document_ids
         }
 
+        /// Builds the HTML output of the book with the given name, returning the path of the
+        /// output along with its destination relative to the documentation root.
+        pub(crate) fn ensure_html_book(
+            builder: &Builder<'_>,
+            target: TargetSelection,
+            name: &str,
+        ) -> Option<(PathBuf, &'static str)> {
+            $(if name == $name {
+                let path = builder.ensure($ty {
+                    mode: SphinxMode::Html,
+                    target,
+                    fresh_build: false,
+                });
+                return Some((path, $dest));
+            })*
+            None
+        }
+
         pub(crate) fn ensure_all_xml_doctrees(
             builder: &Builder<'_>,
             target: TargetSelection,
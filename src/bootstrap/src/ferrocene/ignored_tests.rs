@@ -0,0 +1,240 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Loading and validation of `ferrocene/ignored-tests.toml`, listing the tests we don't run on
+//! some targets. The file is validated as a whole every time it's loaded, so that stale entries
+//! fail the build rather than silently accumulating.
+
+use crate::builder::Builder;
+use crate::core::config::TargetSelection;
+use crate::t;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use toml::value::Datetime;
+
+#[derive(serde_derive::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Entry {
+    /// Paths of the ignored tests, relative to the root of the repository. They can contain glob
+    /// patterns: `*` and `?` don't match across directories, while `**` does.
+    tests: Vec<String>,
+    targets: Vec<String>,
+    reason: String,
+    tracking_issue: Option<String>,
+    /// Date after which the test must not be ignored anymore, failing the build.
+    expires: Option<Datetime>,
+    /// Paths of the ignored tests, after expanding the glob patterns.
+    #[serde(skip)]
+    expanded: Vec<String>,
+}
+
+/// Returns the tests of the suite ignored on the target, along with the reason they're ignored.
+pub(crate) fn ignored_tests_for_suite(
+    builder: &Builder<'_>,
+    target: TargetSelection,
+    suite: &str,
+) -> Vec<(String, String)> {
+    static IGNORED_TESTS: OnceLock<HashMap<String, Vec<Entry>>> = OnceLock::new();
+    let ignored = IGNORED_TESTS.get_or_init(|| {
+        let path = builder.src.join("ferrocene").join("ignored-tests.toml");
+        let mut contents: HashMap<String, Vec<Entry>> =
+            t!(toml::from_slice(&t!(std::fs::read(&path))));
+
+        let errors = validate(&builder.src, &mut contents, &today());
+        if !errors.is_empty() {
+            eprintln!("error: invalid {}:", path.display());
+            for error in &errors {
+                eprintln!("- {error}");
+            }
+            std::process::exit(1);
+        }
+        contents
+    });
+
+    let triple = target.triple.to_string();
+    ignored
+        .get(suite)
+        .map(|s| s.as_slice())
+        .unwrap_or(&[])
+        .iter()
+        .filter(|entry| entry.targets.contains(&triple))
+        .flat_map(|entry| {
+            let reason = entry.message();
+            entry.expanded.iter().map(move |test| (test.clone(), reason.clone()))
+        })
+        .collect()
+}
+
+impl Entry {
+    /// The message shown when a test is ignored by this entry.
+    fn message(&self) -> String {
+        match &self.tracking_issue {
+            Some(issue) => format!("{} (tracking issue: {issue})", self.reason.trim()),
+            None => self.reason.trim().to_string(),
+        }
+    }
+}
+
+/// Checks the contents of the file and expands the glob patterns, returning all errors found.
+fn validate(src: &Path, contents: &mut HashMap<String, Vec<Entry>>, today: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (suite, entries) in contents {
+        if !suite.starts_with("tests/") || !src.join(suite.as_str()).is_dir() {
+            errors.push(format!("unknown test suite {suite}"));
+            continue;
+        }
+        let suite_files = if entries.iter().flat_map(|e| &e.tests).any(|t| is_glob(t)) {
+            list_suite(src, suite)
+        } else {
+            Vec::new()
+        };
+
+        for (i, entry) in entries.iter_mut().enumerate() {
+            let location = format!("entry #{} of {suite}", i + 1);
+            if entry.tests.is_empty() {
+                errors.push(format!("{location} has no tests"));
+            }
+            if entry.targets.is_empty() {
+                errors.push(format!("{location} has no targets"));
+            }
+            if entry.reason.trim().is_empty() {
+                errors.push(format!("{location} has no reason"));
+            }
+            if entry.tracking_issue.as_ref().is_some_and(|issue| issue.trim().is_empty()) {
+                errors.push(format!("{location} has an empty tracking-issue"));
+            }
+            if let Some(expires) = &entry.expires {
+                let expires = expires.to_string();
+                if !is_date(&expires) {
+                    errors.push(format!("{location} expires at {expires}, which is not a date"));
+                } else if expires.as_str() < today {
+                    let issue = match &entry.tracking_issue {
+                        Some(issue) => format!(" (tracking issue: {issue})"),
+                        None => String::new(),
+                    };
+                    errors.push(format!(
+                        "{location} expired on {expires}{issue}: stop ignoring {} or extend \
+                         the expiry date",
+                        entry.tests.join(", ")
+                    ));
+                }
+            }
+
+            for test in &entry.tests {
+                if test != suite && !test.starts_with(&format!("{suite}/")) {
+                    errors.push(format!("{test} in {location} is not part of the suite"));
+                } else if is_glob(test) {
+                    let matches = suite_files.iter().filter(|path| glob_matches(test, path));
+                    let before = entry.expanded.len();
+                    entry.expanded.extend(matches.cloned());
+                    if entry.expanded.len() == before {
+                        errors.push(format!("{test} in {location} doesn't match any test"));
+                    }
+                } else if !src.join(test).exists() {
+                    errors.push(format!("{test} in {location} doesn't exist"));
+                } else {
+                    entry.expanded.push(test.clone());
+                }
+            }
+        }
+
+        // Only check for duplicates after expanding globs, as a pattern could match a test
+        // that's already ignored by another entry.
+        let mut seen: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            for test in &entry.expanded {
+                for target in &entry.targets {
+                    match seen.insert((test.as_str(), target.as_str()), i) {
+                        Some(other) if other == i => errors.push(format!(
+                            "{test} is ignored multiple times in entry #{} of {suite}",
+                            i + 1
+                        )),
+                        Some(other) => errors.push(format!(
+                            "{test} is ignored on {target} by both entries #{} and #{} of {suite}",
+                            other + 1,
+                            i + 1
+                        )),
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+    errors.sort();
+    errors
+}
+
+/// Returns the paths of all files and directories in the suite, relative to the repository root.
+fn list_suite(src: &Path, suite: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    for entry in walkdir::WalkDir::new(src.join(suite)).min_depth(1) {
+        let entry = t!(entry);
+        let relative = t!(entry.path().strip_prefix(src));
+        let components = relative.components().map(|c| c.as_os_str().to_string_lossy());
+        paths.push(components.collect::<Vec<_>>().join("/"));
+    }
+    paths.sort();
+    paths
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Matches a path against a glob pattern, where `*` and `?` match any sequence of characters or
+/// a single character within a path component, and `**` matches across components.
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[u8], path: &[u8]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            [b'*', b'*', rest @ ..] => {
+                // `**/` also matches no directories at all.
+                rest.strip_prefix(b"/").is_some_and(|rest| matches(rest, path))
+                    || (0..=path.len()).any(|i| matches(rest, &path[i..]))
+            }
+            [b'*', rest @ ..] => (0..=path.len())
+                .take_while(|&i| i == 0 || path[i - 1] != b'/')
+                .any(|i| matches(rest, &path[i..])),
+            [b'?', rest @ ..] => {
+                matches!(path.first(), Some(c) if *c != b'/') && matches(rest, &path[1..])
+            }
+            [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+        }
+    }
+    matches(pattern.as_bytes(), path.as_bytes())
+}
+
+fn is_date(date: &str) -> bool {
+    let bytes = date.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+/// Returns the current date in UTC, formatted as `YYYY-MM-DD`.
+fn today() -> String {
+    let days = t!(SystemTime::now().duration_since(UNIX_EPOCH)).as_secs() / 86400;
+    civil_date(days as i64)
+}
+
+/// Converts days since the UNIX epoch into a `YYYY-MM-DD` date, using the algorithm from
+/// <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_date(days: i64) -> String {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests;
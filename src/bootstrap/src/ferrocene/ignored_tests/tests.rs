@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use super::{civil_date, glob_matches, validate, Entry};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+fn src() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join("..")
+}
+
+fn check(contents: &str, today: &str) -> (HashMap<String, Vec<Entry>>, Vec<String>) {
+    let mut parsed = toml::from_str(contents).unwrap();
+    let errors = validate(&src(), &mut parsed, today);
+    (parsed, errors)
+}
+
+#[test]
+fn test_repository_file_is_valid() {
+    let contents = std::fs::read_to_string(src().join("ferrocene/ignored-tests.toml")).unwrap();
+    let (_, errors) = check(&contents, "0000-01-01");
+    assert_eq!(errors, Vec::<String>::new());
+}
+
+#[test]
+fn test_glob_matches() {
+    assert!(glob_matches("tests/ui/*.rs", "tests/ui/foo.rs"));
+    assert!(!glob_matches("tests/ui/*.rs", "tests/ui/panics/foo.rs"));
+    assert!(glob_matches("tests/ui/**/*.rs", "tests/ui/foo.rs"));
+    assert!(glob_matches("tests/ui/**/*.rs", "tests/ui/panics/location/foo.rs"));
+    assert!(glob_matches("tests/ui/**", "tests/ui/panics/foo.rs"));
+    assert!(glob_matches("tests/ui/foo-?.rs", "tests/ui/foo-1.rs"));
+    assert!(!glob_matches("tests/ui/foo-?.rs", "tests/ui/foo-10.rs"));
+    assert!(!glob_matches("tests/ui/foo?bar.rs", "tests/ui/foo/bar.rs"));
+}
+
+#[test]
+fn test_civil_date() {
+    assert_eq!(civil_date(0), "1970-01-01");
+    assert_eq!(civil_date(11016), "2000-02-29");
+    assert_eq!(civil_date(19723), "2024-01-01");
+}
+
+#[test]
+fn test_globs_are_expanded() {
+    let (parsed, errors) = check(
+        r#"
+        [["tests/ui"]]
+        tests = ["tests/ui/panics/location-detail-panic-no-*.rs"]
+        targets = ["x86_64-unknown-linux-gnu"]
+        reason = "testing"
+        "#,
+        "2024-01-01",
+    );
+    assert_eq!(errors, Vec::<String>::new());
+    let expanded = &parsed["tests/ui"][0].expanded;
+    assert!(expanded.contains(&"tests/ui/panics/location-detail-panic-no-file.rs".to_string()));
+    assert!(expanded.iter().all(|test| test.starts_with("tests/ui/panics/location-detail-")));
+}
+
+#[test]
+fn test_invalid_entries() {
+    let (_, errors) = check(
+        r#"
+        [["tests/unknown-suite"]]
+        tests = ["tests/unknown-suite/foo.rs"]
+        targets = ["x86_64-unknown-linux-gnu"]
+        reason = "testing"
+
+        [["tests/ui"]]
+        tests = [
+            "tests/ui/this-test-does-not-exist.rs",
+            "tests/ui/this-*-does-not-exist.rs",
+            "tests/incremental/issue-80691-bad-eval-cache.rs",
+            "tests/ui/panics/issue-47429-short-backtraces.rs",
+            "tests/ui/panics/issue-47429-short-backtraces.rs",
+        ]
+        targets = ["x86_64-unknown-linux-gnu"]
+        reason = "testing"
+
+        [["tests/ui"]]
+        tests = ["tests/ui/panics/issue-47429-*.rs"]
+        targets = ["x86_64-unknown-linux-gnu"]
+        reason = ""
+        tracking-issue = "https://github.com/ferrocene/ferrocene/issues/1"
+        expires = 2023-12-31
+        "#,
+        "2024-01-01",
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "entry #2 of tests/ui expired on 2023-12-31 (tracking issue: \
+             https://github.com/ferrocene/ferrocene/issues/1): stop ignoring \
+             tests/ui/panics/issue-47429-*.rs or extend the expiry date",
+            "entry #2 of tests/ui has no reason",
+            "tests/incremental/issue-80691-bad-eval-cache.rs in entry #1 of tests/ui is not part \
+             of the suite",
+            "tests/ui/panics/issue-47429-short-backtraces.rs is ignored multiple times in entry #1 \
+             of tests/ui",
+            "tests/ui/panics/issue-47429-short-backtraces.rs is ignored on \
+             x86_64-unknown-linux-gnu by both entries #1 and #2 of tests/ui",
+            "tests/ui/this-*-does-not-exist.rs in entry #1 of tests/ui doesn't match any test",
+            "tests/ui/this-test-does-not-exist.rs in entry #1 of tests/ui doesn't exist",
+            "unknown test suite tests/unknown-suite",
+        ]
+    );
+}
+
+#[test]
+fn test_unexpired_entries() {
+    let (_, errors) = check(
+        r#"
+        [["tests/ui"]]
+        tests = ["tests/ui/panics/issue-47429-short-backtraces.rs"]
+        targets = ["x86_64-unknown-linux-gnu"]
+        reason = "testing"
+        expires = 2024-01-01
+        "#,
+        "2024-01-01",
+    );
+    assert_eq!(errors, Vec::<String>::new());
+}
+
+#[test]
+fn test_entry_message() {
+    let (parsed, _) = check(
+        r#"
+        [["tests/ui"]]
+        tests = ["tests/ui/panics/issue-47429-short-backtraces.rs"]
+        targets = ["x86_64-unknown-linux-gnu"]
+        reason = "no backtraces "
+
+        [["tests/ui"]]
+        tests = ["tests/ui/panics/location-detail-panic-no-file.rs"]
+        targets = ["x86_64-unknown-linux-gnu"]
+        reason = "no location details"
+        tracking-issue = "https://github.com/ferrocene/ferrocene/issues/1"
+        "#,
+        "2024-01-01",
+    );
+    assert_eq!(parsed["tests/ui"][0].message(), "no backtraces");
+    assert_eq!(
+        parsed["tests/ui"][1].message(),
+        "no location details (tracking issue: https://github.com/ferrocene/ferrocene/issues/1)"
+    );
+}
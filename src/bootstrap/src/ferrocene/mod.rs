@@ -4,19 +4,21 @@
 pub(crate) mod code_coverage;
 pub(crate) mod dist;
 pub(crate) mod doc;
+mod ignored_tests;
 pub(crate) mod partners;
 pub(crate) mod run;
 pub(crate) mod sign;
 pub(crate) mod test;
 pub(crate) mod tool;
+mod traceability_documentations;
 
 use crate::builder::Builder;
-use crate::core::config::{Config, TargetSelection};
-use crate::t;
-use std::collections::HashMap;
+use crate::core::config::Config;
 use std::path::Path;
 use std::process::Command;
 
+pub(crate) use ignored_tests::ignored_tests_for_suite;
+
 /// Helper function used to download files from S3. This is used to be able to download artifacts
 /// from our buckets for download-ci-llvm and download-rustc.
 pub(crate) fn download_from_s3(config: &Config, url: &str, tempfile: &Path, help_on_error: &str) {
@@ -70,34 +72,6 @@ pub(crate) fn download_from_local_filesystem(path: &str, dest: &Path, help_on_er
     }
 }
 
-pub(crate) fn ignored_tests_for_suite(
-    builder: &Builder<'_>,
-    target: TargetSelection,
-    suite: &str,
-) -> Vec<String> {
-    #[derive(serde_derive::Deserialize)]
-    struct Item {
-        tests: Vec<String>,
-        targets: Vec<String>,
-        // Other fields ignored here...
-    }
-
-    let contents: HashMap<String, Vec<Item>> = t!(toml::from_slice(&t!(std::fs::read(
-        builder.src.join("ferrocene").join("ignored-tests.toml")
-    ))));
-
-    let triple = target.triple.to_string();
-    contents
-        .get(suite)
-        .map(|s| s.as_slice())
-        .unwrap_or(&[])
-        .iter()
-        .filter(|item| item.targets.contains(&triple))
-        .flat_map(|item| item.tests.iter())
-        .map(|i| i.clone())
-        .collect()
-}
-
 fn ferrocene_channel(
     builder: &Builder<'_>,
     ferrocene_channel: &str,
@@ -4,7 +4,11 @@
 use crate::builder::{Builder, RunConfig, ShouldRun, Step};
 use crate::core::build_steps::tool::Tool;
 use crate::core::config::{FerroceneTraceabilityMatrixMode, TargetSelection};
-use crate::ferrocene::doc::{Specification, SphinxMode, UserManual};
+use crate::ferrocene::doc::ensure_html_book;
+use crate::ferrocene::sign::{error_when_signatures_are_ignored, CacheSignatureFiles};
+use crate::ferrocene::traceability_documentations::{self, Book};
+use crate::t;
+use std::cell::RefCell;
 use std::path::PathBuf;
 use std::process::Command;
 
@@ -30,17 +34,6 @@ fn run(self, builder: &Builder<'_>) -> Self::Output {
         let test_annotations_base =
             builder.out.join(self.target.triple).join("ferrocene").join("test-annotations");
 
-        let specification = builder.ensure(Specification {
-            mode: SphinxMode::Html,
-            target: self.target,
-            fresh_build: false,
-        });
-        let user_manual = builder.ensure(UserManual {
-            mode: SphinxMode::Html,
-            target: self.target,
-            fresh_build: false,
-        });
-
         let compiletest = builder.tool_exe(Tool::Compiletest);
         for (suite, mode) in &[("tests/ui", "ui"), ("tests/run-make", "run-make")] {
             builder.info(&format!("Loading test annotations from {suite}"));
@@ -64,38 +57,131 @@ fn run(self, builder: &Builder<'_>) -> Self::Output {
             .join("traceability-matrix.html");
         builder.create_dir(html_output.parent().unwrap());
 
-        let (spec_url, user_manual_url, src_url);
-        match builder.config.ferrocene_traceability_matrix_mode {
-            FerroceneTraceabilityMatrixMode::Local => {
-                spec_url = format!("file://{}", specification.display());
-                user_manual_url = format!("file://{}", user_manual.display());
-                src_url = format!("file://{}", builder.src.display());
-            }
-            FerroceneTraceabilityMatrixMode::Ci => {
-                spec_url = "../specification".into();
-                user_manual_url = "../user-manual".into();
-                src_url = format!(
-                    "https://github.com/ferrocene/ferrocene/blob/{}",
-                    builder.rust_sha().unwrap_or("main")
-                );
+        let mode = builder.config.ferrocene_traceability_matrix_mode;
+        let src_url = match mode {
+            FerroceneTraceabilityMatrixMode::Local => format!("file://{}", builder.src.display()),
+            FerroceneTraceabilityMatrixMode::Ci => format!(
+                "https://github.com/ferrocene/ferrocene/blob/{}",
+                builder.rust_sha().unwrap_or("main")
+            ),
+        };
+
+        let documentations_source_path =
+            builder.src.join("ferrocene").join("traceability-documentations.toml");
+        let documentations_source = t!(std::fs::read_to_string(&documentations_source_path));
+        let documentations_config = traceability_documentations::generate(
+            &builder.src,
+            &documentations_source_path,
+            &documentations_source,
+            |name| {
+                let (path, dest) = ensure_html_book(builder, self.target, name)?;
+                let url = match mode {
+                    FerroceneTraceabilityMatrixMode::Local => format!("file://{}", path.display()),
+                    // The matrix is stored in the qualification directory of the docs.
+                    FerroceneTraceabilityMatrixMode::Ci => format!("../{dest}"),
+                };
+                Some(Book { path, url })
+            },
+        );
+        let documentations_config = match documentations_config {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("error: {err}");
+                std::process::exit(1);
             }
-        }
+        };
+        let documentations_config_path =
+            test_annotations_base.parent().unwrap().join("traceability-documentations.toml");
+        builder.create(&documentations_config_path, &documentations_config);
 
         let mut cmd = builder.tool_cmd(Tool::FerroceneTraceabilityMatrix);
-        cmd.env("TRACEABILITY_MATRIX_FLS_IDS", specification.join("paragraph-ids.json"))
-            .env("TRACEABILITY_MATRIX_FLS_URL", spec_url)
-            .env("TRACEABILITY_MATRIX_UM_IDS", user_manual.join("traceability-ids.json"))
-            .env("TRACEABILITY_MATRIX_UM_URL", user_manual_url)
+        cmd.env("TRACEABILITY_MATRIX_DOCUMENTATIONS", &documentations_config_path)
             .env("TRACEABILITY_MATRIX_ANNOTATIONS", test_annotations_base)
             .env("TRACEABILITY_MATRIX_HTML_OUT", &html_output)
+            .env("TRACEABILITY_MATRIX_JSON_OUT", html_output.with_extension("json"))
+            .env("TRACEABILITY_MATRIX_CSV_OUT", html_output.with_extension("csv"))
             .env("TRACEABILITY_MATRIX_SRC_BASE", &builder.src)
-            .env("TRACEABILITY_MATRIX_SRC_URL", src_url);
+            .env("TRACEABILITY_MATRIX_SRC_URL", src_url)
+            .env(
+                "TRACEABILITY_MATRIX_CARGO_PACKAGES",
+                ["core", "alloc"]
+                    .iter()
+                    .map(|krate| {
+                        format!("{krate}={}", builder.src.join("library").join(krate).display())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
 
         if let Some(dir) = &builder.config.ferrocene_test_outcomes_dir {
             cmd.env("TRACEABILITY_MATRIX_TEST_OUTCOMES_DIR", dir);
         }
+        if let Some(gates) = &builder.config.ferrocene_traceability_matrix_gates {
+            cmd.env("TRACEABILITY_MATRIX_GATES", gates);
+        }
 
         builder.run(&mut cmd);
         html_output
     }
 }
+
+/// Reports which signable documents are signed, by which roles, and whether their signatures are
+/// outdated, without stopping at the first document needing attention.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub(crate) struct DocumentSignaturesStatus {
+    pub(crate) target: TargetSelection,
+}
+
+impl Step for DocumentSignaturesStatus {
+    type Output = ();
+    const ONLY_HOSTS: bool = true;
+    const DEFAULT: bool = false;
+
+    fn should_run(run: ShouldRun<'_>) -> ShouldRun<'_> {
+        run.alias("ferrocene-document-signatures-status")
+    }
+
+    fn make_run(run: RunConfig<'_>) {
+        run.builder.ensure(DocumentSignaturesStatus { target: run.target });
+    }
+
+    fn run(self, builder: &Builder<'_>) {
+        error_when_signatures_are_ignored(builder, "check the status of document signatures");
+
+        let documents = RefCell::new(Vec::new());
+        let cache_dir = RefCell::new(None);
+        crate::ferrocene::sign::for_each_signable_document(
+            builder,
+            self.target,
+            // Condition
+            |source| source.join("signature").join("config.toml").exists(),
+            // Function executed
+            |source, output| {
+                // All documents share the same cache directory.
+                let cache = builder.ensure(CacheSignatureFiles { source_dir: source.into() });
+                *cache_dir.borrow_mut() = Some(cache);
+                documents.borrow_mut().push((source.to_path_buf(), output.to_path_buf()));
+            },
+        );
+        let Some(cache_dir) = cache_dir.into_inner() else {
+            builder.info("No signable documents found");
+            return;
+        };
+
+        let json_output = builder
+            .out
+            .join(self.target.triple)
+            .join("ferrocene")
+            .join("document-signatures-status.json");
+        builder.create_dir(json_output.parent().unwrap());
+
+        let mut cmd = builder.tool_cmd(Tool::FerroceneDocumentSignatures);
+        cmd.env("DOCUMENT_SIGNATURES_S3_CACHE_DIR", &cache_dir)
+            .env("DOCUMENT_SIGNATURES_STATUS_JSON", &json_output)
+            .arg("status");
+        for (source, output) in documents.into_inner() {
+            cmd.arg(source).arg(output);
+        }
+        builder.run(&mut cmd);
+    }
+}
@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+//! Generation of the configuration file listing the documentations traced by the traceability
+//! matrix. The documentations are listed in `ferrocene/traceability-documentations.toml` by the
+//! name of the Sphinx book containing them, which bootstrap resolves to the built book.
+
+use std::path::{Path, PathBuf};
+
+#[derive(serde_derive::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Source {
+    #[serde(rename = "documentation")]
+    documentations: Vec<SourceDocumentation>,
+}
+
+#[derive(serde_derive::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SourceDocumentation {
+    name: String,
+    book: String,
+    /// Path of the file containing the traceability IDs, relative to the built book.
+    ids: PathBuf,
+    kinds: Option<Vec<String>>,
+    /// Paths of the IDs files of previous versions of the documentation, relative to the root of
+    /// the repository.
+    #[serde(default)]
+    history: Vec<PathBuf>,
+}
+
+/// Configuration file understood by the traceability matrix tool.
+#[derive(serde_derive::Serialize)]
+struct Generated {
+    #[serde(rename = "documentation")]
+    documentations: Vec<GeneratedDocumentation>,
+}
+
+#[derive(serde_derive::Serialize)]
+struct GeneratedDocumentation {
+    name: String,
+    ids: PathBuf,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kinds: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    history: Vec<PathBuf>,
+}
+
+/// A Sphinx book built by bootstrap.
+pub(crate) struct Book {
+    /// Path of the HTML output of the book.
+    pub(crate) path: PathBuf,
+    /// URL the traceability matrix links to for the book.
+    pub(crate) url: String,
+}
+
+/// Generates the configuration file for the traceability matrix from the contents of
+/// `ferrocene/traceability-documentations.toml`, calling `build_book` to build each book it
+/// refers to. `build_book` returns `None` when no book with that name exists. The IDs files of
+/// previous versions are resolved from `src`, the root of the repository.
+pub(crate) fn generate(
+    src: &Path,
+    source_path: &Path,
+    source: &str,
+    mut build_book: impl FnMut(&str) -> Option<Book>,
+) -> Result<String, String> {
+    let source: Source = toml::from_str(source)
+        .map_err(|e| format!("failed to parse {}: {e}", source_path.display()))?;
+
+    let mut documentations = Vec::new();
+    for documentation in source.documentations {
+        let Some(book) = build_book(&documentation.book) else {
+            return Err(format!(
+                "documentation {} in {} refers to the unknown book {}",
+                documentation.name,
+                source_path.display(),
+                documentation.book
+            ));
+        };
+        documentations.push(GeneratedDocumentation {
+            name: documentation.name,
+            ids: book.path.join(&documentation.ids),
+            url: book.url,
+            kinds: documentation.kinds,
+            history: documentation.history.iter().map(|path| src.join(path)).collect(),
+        });
+    }
+
+    toml::to_string(&Generated { documentations })
+        .map_err(|e| format!("failed to serialize the traceability documentations: {e}"))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+// SPDX-FileCopyrightText: The Ferrocene Developers
+
+use super::{generate, Book};
+use std::path::{Path, PathBuf};
+
+fn book(name: &str) -> Option<Book> {
+    Some(Book { path: PathBuf::from("/build/doc").join(name), url: format!("../{name}") })
+}
+
+fn parse(generated: &str) -> toml::Value {
+    toml::from_str(generated).unwrap()
+}
+
+#[test]
+fn test_repository_file() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join("..");
+    let path = src.join("ferrocene/traceability-documentations.toml");
+    let contents = std::fs::read_to_string(&path).unwrap();
+
+    let generated = parse(&generate(&src, &path, &contents, book).unwrap());
+    let documentations = generated["documentation"].as_array().unwrap();
+    let names = documentations.iter().map(|d| d["name"].as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(names, ["FLS", "UM"]);
+
+    for history in documentations.iter().filter_map(|d| d.get("history")) {
+        for path in history.as_array().unwrap() {
+            let path = Path::new(path.as_str().unwrap());
+            assert!(path.is_file(), "missing history file {}", path.display());
+        }
+    }
+}
+
+#[test]
+fn test_generate() {
+    let source = r#"
+        [[documentation]]
+        name = "FLS"
+        book = "specification"
+        ids = "paragraph-ids.json"
+
+        [[documentation]]
+        name = "SM"
+        book = "safety-manual"
+        ids = "traceability-ids.json"
+        kinds = ["paragraph"]
+    "#;
+    let generated =
+        parse(&generate(Path::new("/src"), Path::new("source.toml"), source, book).unwrap());
+
+    let expected = parse(
+        r#"
+            [[documentation]]
+            name = "FLS"
+            ids = "/build/doc/specification/paragraph-ids.json"
+            url = "../specification"
+
+            [[documentation]]
+            name = "SM"
+            ids = "/build/doc/safety-manual/traceability-ids.json"
+            url = "../safety-manual"
+            kinds = ["paragraph"]
+        "#,
+    );
+    assert_eq!(generated, expected);
+}
+
+#[test]
+fn test_generate_history() {
+    let source = r#"
+        [[documentation]]
+        name = "FLS"
+        book = "specification"
+        ids = "paragraph-ids.json"
+        history = ["ferrocene/history/fls-1.0.json", "ferrocene/history/fls-2.0.json"]
+
+        [[documentation]]
+        name = "UM"
+        book = "user-manual"
+        ids = "traceability-ids.json"
+    "#;
+    let generated =
+        parse(&generate(Path::new("/src"), Path::new("source.toml"), source, book).unwrap());
+
+    let expected = parse(
+        r#"
+            [[documentation]]
+            name = "FLS"
+            ids = "/build/doc/specification/paragraph-ids.json"
+            url = "../specification"
+            history = ["/src/ferrocene/history/fls-1.0.json", "/src/ferrocene/history/fls-2.0.json"]
+
+            [[documentation]]
+            name = "UM"
+            ids = "/build/doc/user-manual/traceability-ids.json"
+            url = "../user-manual"
+        "#,
+    );
+    assert_eq!(generated, expected);
+}
+
+#[test]
+fn test_generate_escapes_non_ascii() {
+    let source = r#"
+        [[documentation]]
+        name = "Spécification"
+        book = "spécification"
+        ids = "ids \"quoted\".json"
+    "#;
+    let build_book = |name: &str| {
+        Some(Book {
+            path: PathBuf::from("/home/józef").join(name),
+            url: format!("file:///ü/{name}"),
+        })
+    };
+    let generated =
+        parse(&generate(Path::new("/src"), Path::new("source.toml"), source, build_book).unwrap());
+
+    let documentation = &generated["documentation"][0];
+    assert_eq!(documentation["name"].as_str(), Some("Spécification"));
+    assert_eq!(
+        documentation["ids"].as_str(),
+        Some("/home/józef/spécification/ids \"quoted\".json")
+    );
+    assert_eq!(documentation["url"].as_str(), Some("file:///ü/spécification"));
+}
+
+#[test]
+fn test_generate_unknown_book() {
+    let source = r#"
+        [[documentation]]
+        name = "SM"
+        book = "safety-manual"
+        ids = "traceability-ids.json"
+    "#;
+    let err = generate(Path::new("/src"), Path::new("source.toml"), source, |_| None).unwrap_err();
+    assert_eq!(err, "documentation SM in source.toml refers to the unknown book safety-manual");
+}
+
+#[test]
+fn test_generate_unknown_field() {
+    let source = r#"
+        [[documentation]]
+        name = "SM"
+        book = "safety-manual"
+        ids = "traceability-ids.json"
+        url = "../safety-manual"
+    "#;
+    let err = generate(Path::new("/src"), Path::new("source.toml"), source, book).unwrap_err();
+    assert!(err.starts_with("failed to parse source.toml: unknown field `url`"), "{err}");
+}
@@ -328,6 +328,8 @@ fn non_bare_args(&self, cmd: &mut Command) {
         }
         if let Some(kms_key_arn) = &self.builder.config.ferrocene_tarball_signing_kms_key_arn {
             cmd.arg("--ferrocene-signing-kms-key-arn").arg(kms_key_arn);
+        } else if let Some(key_file) = &self.builder.config.ferrocene_tarball_signing_key_file {
+            cmd.arg("--ferrocene-signing-key-file").arg(key_file);
         }
         for proxied_binary in &self.proxied_binaries {
             cmd.arg("--ferrocene-proxied-binary").arg(proxied_binary);
@@ -383,7 +385,11 @@ fn run(self, build_cli: impl FnOnce(&Tarball<'a>, &mut Command)) -> GeneratedTar
             .dist_compression_formats
             .as_ref()
             .and_then(|formats| formats.get(0))
-            .map(|s| s.as_str())
+            .map(|s| match s.as_str() {
+                // The zstd format is named differently from its file extension.
+                "zstd" => "zst",
+                other => other,
+            })
             .unwrap_or("gz");
 
         GeneratedTarball {
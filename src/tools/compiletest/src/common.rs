@@ -252,6 +252,10 @@ pub struct Config {
     /// `test::TestOpts::skip`. `filter_exact` does not apply to these flags.
     pub skip: Vec<String>,
 
+    /// Tests ignored by `ferrocene/ignored-tests.toml` on this target, with the reason they're
+    /// ignored. Paths are relative to the repository root, and can point to a directory.
+    pub ferrocene_ignored_tests: Vec<(String, String)>,
+
     /// Exactly match the filter, rather than a substring
     pub filter_exact: bool,
 
@@ -85,6 +85,12 @@ pub fn parse_config(args: Vec<String>) -> Config {
         .optflag("", "ignored", "run tests marked as ignored")
         .optflag("", "with-debug-assertions", "whether to run tests with `ignore-debug` header")
         .optmulti("", "skip", "skip tests matching SUBSTRING. Can be passed multiple times", "SUBSTRING")
+        .optmulti(
+            "",
+            "ferrocene-ignored-test",
+            "ignore the test at PATH, showing REASON. Can be passed multiple times",
+            "PATH=REASON",
+        )
         .optflag("", "exact", "filters match exactly")
         .optopt(
             "",
@@ -246,6 +252,14 @@ fn make_absolute(path: PathBuf) -> PathBuf {
         with_debug_assertions,
         filters: matches.free.clone(),
         skip: matches.opt_strs("skip"),
+        ferrocene_ignored_tests: matches
+            .opt_strs("ferrocene-ignored-test")
+            .into_iter()
+            .map(|arg| match arg.split_once('=') {
+                Some((path, reason)) => (path.to_string(), reason.to_string()),
+                None => panic!("invalid `--ferrocene-ignored-test` option `{}` given", arg),
+            })
+            .collect(),
         filter_exact: matches.opt_present("exact"),
         force_pass_mode: matches.opt_str("pass").map(|mode| {
             mode.parse::<PassMode>()
@@ -338,6 +352,7 @@ pub fn log_config(config: &Config) {
     logv(c, format!("run_ignored: {}", config.run_ignored));
     logv(c, format!("filters: {:?}", config.filters));
     logv(c, format!("skip: {:?}", config.skip));
+    logv(c, format!("ferrocene_ignored_tests: {:?}", config.ferrocene_ignored_tests));
     logv(c, format!("filter_exact: {}", config.filter_exact));
     logv(
         c,
@@ -777,6 +792,13 @@ fn make_test(
             let mut desc = make_test_description(
                 &config, cache, test_name, &test_path, src_file, cfg, poisoned,
             );
+            if let Some(reason) = ferrocene_ignore_reason(&config, testpaths) {
+                desc.ignore = true;
+                // Like the ignore reasons from the headers, the message must be a &'static str.
+                desc.ignore_message = Some(&*Box::leak(
+                    format!("ignored by ferrocene/ignored-tests.toml: {reason}").into_boxed_str(),
+                ));
+            }
             // Ignore tests that already run and are up to date with respect to inputs.
             if !config.force_rerun {
                 desc.ignore |= is_up_to_date(
@@ -795,6 +817,17 @@ fn make_test(
         .collect()
 }
 
+/// Returns why the test is ignored by `ferrocene/ignored-tests.toml`, if it is.
+fn ferrocene_ignore_reason<'a>(config: &'a Config, testpaths: &TestPaths) -> Option<&'a str> {
+    let root_directory = config.src_base.parent().unwrap().parent().unwrap();
+    let path = testpaths.file.strip_prefix(root_directory).ok()?;
+    config
+        .ferrocene_ignored_tests
+        .iter()
+        .find(|(ignored, _)| path.starts_with(ignored))
+        .map(|(_, reason)| reason.as_str())
+}
+
 fn stamp(config: &Config, testpaths: &TestPaths, revision: Option<&str>) -> PathBuf {
     output_base_dir(config, testpaths, revision).join("stamp")
 }